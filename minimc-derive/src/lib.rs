@@ -0,0 +1,380 @@
+//! Derive macros for `minimc`.
+#![warn(
+    missing_docs,
+    clippy::missing_docs_in_private_items,
+    clippy::pedantic,
+    clippy::all,
+    clippy::ignore_without_reason
+)]
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::{
+    Attribute, Data, DeriveInput, Error, Expr, ExprLit, Fields, GenericParam, Ident, Lifetime,
+    LifetimeParam, Lit, LitInt, Path, Result, parse_macro_input,
+};
+
+/// Derive `McProtoSelf` for a struct or enum.
+///
+/// Struct fields are written in order. By default a field is encoded with its
+/// own `McProto` impl and the default metadata, which can be changed with:
+///
+/// - `#[mc(varint)]` or `#[mc(varlong)]`: encode through `VarNum`.
+/// - `#[mc(with = Codec)]`: encode through `Codec`'s `McProto<FieldType>` impl.
+/// - `#[mc(max_len = N)]`: read with a `Length` of `N` as metadata, and check
+///   the field against it with `Bounded` before writing.
+/// - `#[mc(meta = expr)]`: read with `expr` as metadata.
+///
+/// Enums need `#[mc(tag = varint)]` or `#[mc(tag = u8)]` to pick how the
+/// variant is encoded. Each variant's ID is taken from `#[mc(id = N)]`, then
+/// its explicit discriminant, then one more than the previous variant's ID.
+/// The fields of each variant follow the tag.
+#[proc_macro_derive(McProto, attributes(mc))]
+pub fn derive_mc_proto(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Derive `McProtoBorrow` for a struct or enum that borrows from the frame
+/// it's read from, such as one with `&'a str` fields.
+///
+/// Fields are read in the same order and take the same `mc` attributes as
+/// with [`McProto`](macro@McProto), but through `McProtoBorrow`. The first
+/// lifetime parameter is the one borrowed from. Only reading is generated.
+/// Types that borrow nothing shouldn't derive this: their `McProto` impl
+/// already makes them `McProtoBorrow`.
+#[proc_macro_derive(McProtoBorrow, attributes(mc))]
+pub fn derive_mc_proto_borrow(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_borrow(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Generate the expression reading a field with `codec`.
+type ReadField<'a> = &'a dyn Fn(&Codec) -> TokenStream2;
+
+/// Generate the `McProtoSelf` impl.
+fn expand(input: &DeriveInput) -> Result<TokenStream2> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let read = read_body(
+        input,
+        &|Codec { path, ty, meta, .. }| quote!(<#path as ::minimc::McProto<#ty>>::read(reader, #meta)?),
+    )?;
+    let write = match &input.data {
+        Data::Struct(data) => {
+            let (pattern, write) = write_fields(&quote!(Self), &data.fields)?;
+            quote! {
+                let #pattern = self;
+                #write
+            }
+        }
+        Data::Enum(data) => {
+            let tag = enum_tag(&input.attrs)?;
+            let (tag_codec, tag_ty) = (&tag.path, &tag.ty);
+            let mut write_arms = Vec::new();
+            for (variant, id) in data.variants.iter().zip(variant_ids(data)?) {
+                let ident = &variant.ident;
+                let (pattern, write) = write_fields(&quote!(Self::#ident), &variant.fields)?;
+                write_arms.push(quote! {
+                    #pattern => {
+                        <#tag_codec as ::minimc::McProto<#tag_ty>>::write(#id, writer)?;
+                        #write
+                    }
+                });
+            }
+            quote! {
+                match self {
+                    #(#write_arms)*
+                }
+            }
+        }
+        Data::Union(_) => unreachable!("read_body rejects unions"),
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::minimc::McProtoSelf for #name #ty_generics #where_clause {
+            type Meta = ();
+            fn write(
+                self,
+                writer: &mut dyn ::minimc::McWriter,
+            ) -> ::core::result::Result<(), ::minimc::ProtocolError> {
+                #write
+            }
+            fn read(
+                reader: &mut dyn ::minimc::McReader,
+                (): (),
+            ) -> ::core::result::Result<Self, ::minimc::ProtocolError> {
+                #read
+            }
+        }
+    })
+}
+
+/// Generate the `McProtoBorrow` impl.
+fn expand_borrow(input: &DeriveInput) -> Result<TokenStream2> {
+    let name = &input.ident;
+    let mut generics = input.generics.clone();
+    let lifetime = if let Some(param) = generics.lifetimes().next() {
+        param.lifetime.clone()
+    } else {
+        let lifetime = Lifetime::new("'__mc", Span::call_site());
+        generics.params.insert(
+            0,
+            GenericParam::Lifetime(LifetimeParam::new(lifetime.clone())),
+        );
+        lifetime
+    };
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+    let read = read_body(
+        input,
+        &|Codec { path, ty, meta, .. }| quote!(<#path as ::minimc::McProtoBorrow<#lifetime, #ty>>::read_borrowed(reader, #meta)?),
+    )?;
+
+    Ok(quote! {
+        impl #impl_generics ::minimc::McProtoBorrow<#lifetime> for #name #ty_generics #where_clause {
+            type Meta = ();
+            fn read_borrowed(
+                reader: &mut ::minimc::io::SliceReader<#lifetime>,
+                (): (),
+            ) -> ::core::result::Result<Self, ::minimc::ProtocolError> {
+                #read
+            }
+        }
+    })
+}
+
+/// Generate the body of a read function, reading each field with `read`.
+fn read_body(input: &DeriveInput, read: ReadField<'_>) -> Result<TokenStream2> {
+    match &input.data {
+        Data::Struct(data) => {
+            let read = read_fields(&quote!(Self), &data.fields, read)?;
+            Ok(quote!(::core::result::Result::Ok(#read)))
+        }
+        Data::Enum(data) => {
+            let tag = enum_tag(&input.attrs)?;
+            let (tag_codec, tag_ty) = (&tag.path, &tag.ty);
+            let mut read_arms = Vec::new();
+            for (variant, id) in data.variants.iter().zip(variant_ids(data)?) {
+                let ident = &variant.ident;
+                let fields = read_fields(&quote!(Self::#ident), &variant.fields, read)?;
+                read_arms.push(quote!(#id => ::core::result::Result::Ok(#fields),));
+            }
+            let name = input.ident.to_string();
+            Ok(quote! {
+                let offset = ::minimc::McReader::position(reader);
+                let tag = <#tag_codec as ::minimc::McProto<#tag_ty>>::read(reader, ())?;
+                match tag {
+                    #(#read_arms)*
+                    other => ::core::result::Result::Err(::minimc::ProtocolError::InvalidEnum {
+                        offset,
+                        name: #name,
+                        value: ::core::convert::From::from(other),
+                    }),
+                }
+            })
+        }
+        Data::Union(data) => Err(Error::new(
+            data.union_token.span,
+            "McProto can't be derived for unions",
+        )),
+    }
+}
+
+/// Work out the ID of every variant of an enum, as literals.
+fn variant_ids(data: &syn::DataEnum) -> Result<Vec<LitInt>> {
+    let mut ids = Vec::new();
+    let mut next_id = 0u64;
+    for variant in &data.variants {
+        let id = variant_id(variant, next_id)?;
+        next_id = id + 1;
+        ids.push(LitInt::new(&id.to_string(), Span::call_site()));
+    }
+    Ok(ids)
+}
+
+/// How a field is encoded.
+struct Codec {
+    /// The type implementing `McProto<Field>`.
+    path: TokenStream2,
+    /// The field's type.
+    ty: TokenStream2,
+    /// The expression for the metadata used when reading.
+    meta: TokenStream2,
+    /// The length limit to check before writing, from `max_len`.
+    max_len: Option<LitInt>,
+}
+
+/// Work out the codec of a field from its `mc` attributes.
+fn field_codec(field: &syn::Field) -> Result<Codec> {
+    let ty = &field.ty;
+    let mut codec = Codec {
+        path: quote!(#ty),
+        ty: quote!(#ty),
+        meta: quote!(::core::default::Default::default()),
+        max_len: None,
+    };
+    for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("mc")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("varint") || meta.path.is_ident("varlong") {
+                codec.path = quote!(::minimc::types::VarNum);
+            } else if meta.path.is_ident("with") {
+                let path: Path = meta.value()?.parse()?;
+                codec.path = quote!(#path);
+            } else if meta.path.is_ident("max_len") {
+                let len: LitInt = meta.value()?.parse()?;
+                if len.base10_parse::<usize>()? == 0 {
+                    return Err(meta.error("`max_len` must be non-zero"));
+                }
+                codec.meta = quote!(::minimc::types::Length::new(#len));
+                codec.max_len = Some(len);
+            } else if meta.path.is_ident("meta") {
+                let expr: Expr = meta.value()?.parse()?;
+                codec.meta = quote!(#expr);
+            } else {
+                return Err(meta.error("unknown `mc` field attribute"));
+            }
+            Ok(())
+        })?;
+    }
+    Ok(codec)
+}
+
+/// Generate a pattern binding every field of `path`, and the statements
+/// writing those bindings.
+fn write_fields(path: &TokenStream2, fields: &Fields) -> Result<(TokenStream2, TokenStream2)> {
+    let bindings: Vec<Ident> = fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| {
+            field.ident.as_ref().map_or_else(
+                || format_ident!("field_{i}"),
+                |ident| format_ident!("{ident}"),
+            )
+        })
+        .collect();
+    let pattern = match fields {
+        Fields::Named(_) => quote!(#path { #(#bindings),* }),
+        Fields::Unnamed(_) => quote!(#path(#(#bindings),*)),
+        Fields::Unit => quote!(#path),
+    };
+    let mut writes = Vec::new();
+    for (field, binding) in fields.iter().zip(&bindings) {
+        let Codec {
+            path, ty, max_len, ..
+        } = field_codec(field)?;
+        if let Some(len) = max_len {
+            writes.push(quote! {
+                ::minimc::types::Bounded::check_len(
+                    &#binding,
+                    ::minimc::types::Length::new(#len),
+                )?;
+            });
+        }
+        writes.push(quote!(<#path as ::minimc::McProto<#ty>>::write(#binding, writer)?;));
+    }
+    Ok((
+        pattern,
+        quote! {
+            #(#writes)*
+            ::core::result::Result::Ok(())
+        },
+    ))
+}
+
+/// Generate an expression reading every field of `path` in order, each with
+/// `read`.
+fn read_fields(path: &TokenStream2, fields: &Fields, read: ReadField<'_>) -> Result<TokenStream2> {
+    let mut reads = Vec::new();
+    for field in fields {
+        let read = read(&field_codec(field)?);
+        reads.push(match &field.ident {
+            Some(ident) => quote!(#ident: #read),
+            None => read,
+        });
+    }
+    Ok(match fields {
+        Fields::Named(_) => quote!(#path { #(#reads),* }),
+        Fields::Unnamed(_) => quote!(#path(#(#reads),*)),
+        Fields::Unit => quote!(#path),
+    })
+}
+
+/// How an enum's tag is encoded.
+struct Tag {
+    /// The type implementing `McProto<Tag>`.
+    path: TokenStream2,
+    /// The tag's type.
+    ty: TokenStream2,
+}
+
+/// Read the `#[mc(tag = ...)]` attribute of an enum.
+fn enum_tag(attrs: &[Attribute]) -> Result<Tag> {
+    let mut tag = None;
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("mc")) {
+        attr.parse_nested_meta(|meta| {
+            if !meta.path.is_ident("tag") {
+                return Err(meta.error("unknown `mc` enum attribute"));
+            }
+            let kind: Ident = meta.value()?.parse()?;
+            tag = Some(match kind.to_string().as_str() {
+                "varint" => Tag {
+                    path: quote!(::minimc::types::VarNum),
+                    ty: quote!(u32),
+                },
+                "u8" => Tag {
+                    path: quote!(u8),
+                    ty: quote!(u8),
+                },
+                _ => return Err(Error::new(kind.span(), "expected `varint` or `u8`")),
+            });
+            Ok(())
+        })?;
+    }
+    tag.ok_or_else(|| {
+        Error::new(
+            Span::call_site(),
+            "enums need `#[mc(tag = varint)]` or `#[mc(tag = u8)]`",
+        )
+    })
+}
+
+/// Work out the ID of a variant, given the ID it gets if it doesn't say.
+fn variant_id(variant: &syn::Variant, implicit: u64) -> Result<u64> {
+    let mut id = None;
+    for attr in variant
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("mc"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if !meta.path.is_ident("id") {
+                return Err(meta.error("unknown `mc` variant attribute"));
+            }
+            let lit: LitInt = meta.value()?.parse()?;
+            id = Some(lit.base10_parse()?);
+            Ok(())
+        })?;
+    }
+    if let Some(id) = id {
+        return Ok(id);
+    }
+    match &variant.discriminant {
+        Some((
+            _,
+            Expr::Lit(ExprLit {
+                lit: Lit::Int(lit), ..
+            }),
+        )) => lit.base10_parse(),
+        Some((_, expr)) => Err(Error::new_spanned(
+            expr,
+            "discriminant must be an integer literal, or use `#[mc(id = N)]`",
+        )),
+        None => Ok(implicit),
+    }
+}
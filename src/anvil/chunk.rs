@@ -0,0 +1,539 @@
+//! Chunks as they're saved in region files, in the format used since 1.18,
+//! and their conversion to the [sections](ChunkSection) sent to clients.
+
+use std::{collections::HashMap, hash::Hash};
+
+use anyhow::{Context, anyhow, bail, ensure};
+use simdnbt::owned::{BaseNbt, Nbt, NbtCompound, NbtList, NbtTag};
+
+use crate::{
+    chunk::{
+        ChunkSection, Heightmap, Heightmaps, PalettedContainer, Strategy, pack, packed_len, unpack,
+    },
+    packets::play::{BlockEntity, LightData},
+    types::Identifier,
+};
+
+/// A block state as it's saved: the block's name and its properties.
+#[derive(Clone, PartialEq, Debug, Eq, Hash)]
+pub struct BlockState {
+    /// The block's name, such as `minecraft:stone`.
+    pub name: Identifier,
+    /// The state's properties and their values, such as `facing=north`.
+    pub properties: Vec<(String, String)>,
+}
+
+impl BlockState {
+    /// A block state with no properties.
+    #[must_use]
+    pub fn new(name: Identifier) -> Self {
+        Self {
+            name,
+            properties: Vec::new(),
+        }
+    }
+
+    /// Whether the block is one of the kinds of air, which don't count
+    /// towards a section's block count.
+    #[must_use]
+    pub fn is_air(&self) -> bool {
+        self.name.namespace() == Identifier::DEFAULT_NAMESPACE
+            && matches!(self.name.path(), "air" | "cave_air" | "void_air")
+    }
+
+    /// Read a block state from its compound.
+    fn from_nbt(nbt: &NbtCompound) -> Result<Self, anyhow::Error> {
+        let name = nbt.string("Name").context("block state has no Name")?;
+        let mut properties = Vec::new();
+        if let Some(compound) = nbt.compound("Properties") {
+            for (key, value) in compound.iter() {
+                let value = value
+                    .string()
+                    .with_context(|| format!("block state property {key} isn't a string"))?;
+                properties.push((key.to_string(), value.to_string()));
+            }
+        }
+        Ok(Self {
+            name: name.to_str().parse()?,
+            properties,
+        })
+    }
+
+    /// The block state as a compound.
+    fn to_nbt(&self) -> NbtCompound {
+        let mut nbt = NbtCompound::new();
+        nbt.insert("Name", NbtTag::String(self.name.to_string().into()));
+        if !self.properties.is_empty() {
+            let properties = self
+                .properties
+                .iter()
+                .map(|(key, value)| (key.as_str().into(), NbtTag::String(value.as_str().into())))
+                .collect();
+            nbt.insert(
+                "Properties",
+                NbtTag::Compound(NbtCompound::from_values(properties)),
+            );
+        }
+        nbt
+    }
+}
+
+/// A paletted container as it's saved. Unlike on the network, there's always
+/// a list of values, and entries are packed at the fewest bits per entry the
+/// [strategy](Strategy) allows, with nothing packed for a single value.
+#[derive(Clone, PartialEq, Debug, Eq, Hash)]
+pub struct SavedContainer<T> {
+    /// The distinct values.
+    pub palette: Vec<T>,
+    /// The entries, as packed indices into the palette.
+    pub data: Vec<i64>,
+}
+
+impl<T: Clone + Eq + Hash> SavedContainer<T> {
+    /// Pack `values`, listing each distinct one in the palette once.
+    ///
+    /// # Panics
+    /// If there isn't exactly one value per [entry](Strategy::entries).
+    #[must_use]
+    pub fn new(strategy: Strategy, values: &[T]) -> Self {
+        assert_eq!(
+            values.len(),
+            strategy.entries(),
+            "wrong number of values for {strategy:?}"
+        );
+        let mut palette = Vec::new();
+        let mut indices = HashMap::new();
+        let entries: Vec<u32> = values
+            .iter()
+            .map(|value| {
+                *indices.entry(value).or_insert_with(|| {
+                    palette.push(value.clone());
+                    palette.len() as u32 - 1
+                })
+            })
+            .collect();
+        let bits = Self::bits(strategy, palette.len());
+        let data = if bits == 0 {
+            Vec::new()
+        } else {
+            pack(bits, entries.into_iter())
+                .into_iter()
+                .map(u64::cast_signed)
+                .collect()
+        };
+        Self { palette, data }
+    }
+}
+
+impl<T> SavedContainer<T> {
+    /// The bits per entry of a saved container with `len` palette entries.
+    fn bits(strategy: Strategy, len: usize) -> u8 {
+        if len <= 1 {
+            0
+        } else {
+            PalettedContainer::direct_bits(len).max(strategy.min_indirect_bits())
+        }
+    }
+
+    /// The palette index of each entry.
+    ///
+    /// # Errors
+    /// If the data is the wrong length for the palette, or an index is past
+    /// the end of it, return an error.
+    pub fn indices(&self, strategy: Strategy) -> Result<Vec<usize>, anyhow::Error> {
+        ensure!(!self.palette.is_empty(), "empty {strategy:?} palette");
+        let bits = Self::bits(strategy, self.palette.len());
+        if bits == 0 {
+            return Ok(vec![0; strategy.entries()]);
+        }
+        ensure!(
+            self.data.len() == packed_len(bits, strategy.entries()),
+            "{} longs of packed {strategy:?}, expected {}",
+            self.data.len(),
+            packed_len(bits, strategy.entries())
+        );
+        let data: Vec<u64> = self.data.iter().map(|&long| long.cast_unsigned()).collect();
+        (0..strategy.entries())
+            .map(|i| {
+                let index = unpack(bits, &data, i) as usize;
+                ensure!(
+                    index < self.palette.len(),
+                    "palette index {index} is out of range"
+                );
+                Ok(index)
+            })
+            .collect()
+    }
+
+    /// Convert to a [`PalettedContainer`], mapping each palette entry to its
+    /// ID with `id`. A direct palette uses `direct_bits` per entry.
+    fn to_network(
+        &self,
+        strategy: Strategy,
+        id: impl Fn(&T) -> Result<u32, anyhow::Error>,
+        direct_bits: u8,
+    ) -> Result<PalettedContainer, anyhow::Error> {
+        let ids: Vec<u32> = self.palette.iter().map(id).collect::<Result<_, _>>()?;
+        ensure!(
+            ids.iter().all(|&id| id >> direct_bits == 0),
+            "an ID doesn't fit in {direct_bits} bits"
+        );
+        let values: Vec<u32> = self
+            .indices(strategy)?
+            .into_iter()
+            .map(|i| ids[i])
+            .collect();
+        Ok(PalettedContainer::new(strategy, &values, direct_bits))
+    }
+
+    /// The container as a compound, with its palette already converted to an
+    /// NBT list.
+    fn to_nbt(&self, palette: NbtList) -> NbtCompound {
+        let mut nbt = NbtCompound::new();
+        nbt.insert("palette", NbtTag::List(palette));
+        if !self.data.is_empty() {
+            nbt.insert("data", NbtTag::LongArray(self.data.clone()));
+        }
+        nbt
+    }
+}
+
+/// A 16×16×16 section of a saved chunk. The sections just below and above the
+/// world only have light.
+#[derive(Clone, PartialEq, Debug, Eq, Hash)]
+pub struct SavedSection {
+    /// The section's Y coordinate, in sections.
+    pub y: i8,
+    /// The block states.
+    pub block_states: Option<SavedContainer<BlockState>>,
+    /// The biomes.
+    pub biomes: Option<SavedContainer<Identifier>>,
+    /// The block light, as 2048 bytes of nibbles.
+    pub block_light: Option<Vec<u8>>,
+    /// The sky light, as 2048 bytes of nibbles.
+    pub sky_light: Option<Vec<u8>>,
+}
+
+impl SavedSection {
+    /// The number of bytes of light in a section.
+    const LIGHT_BYTES: usize = 2048;
+
+    /// Read a section from its compound.
+    fn from_nbt(nbt: &NbtCompound) -> Result<Self, anyhow::Error> {
+        let y = nbt.byte("Y").context("section has no Y")?;
+        let block_states = nbt
+            .compound("block_states")
+            .map(|nbt| {
+                let palette = palette(nbt)?
+                    .compounds()
+                    .context("block state palette isn't compounds")?
+                    .iter()
+                    .map(BlockState::from_nbt)
+                    .collect::<Result<_, _>>()?;
+                Ok::<_, anyhow::Error>(SavedContainer {
+                    palette,
+                    data: nbt.long_array("data").unwrap_or_default().to_vec(),
+                })
+            })
+            .transpose()?;
+        let biomes = nbt
+            .compound("biomes")
+            .map(|nbt| {
+                let palette = palette(nbt)?
+                    .strings()
+                    .context("biome palette isn't strings")?
+                    .iter()
+                    .map(|name| name.to_str().parse())
+                    .collect::<Result<_, _>>()?;
+                Ok::<_, anyhow::Error>(SavedContainer {
+                    palette,
+                    data: nbt.long_array("data").unwrap_or_default().to_vec(),
+                })
+            })
+            .transpose()?;
+        let light = |key| {
+            nbt.byte_array(key)
+                .map(|light| {
+                    ensure!(
+                        light.len() == Self::LIGHT_BYTES,
+                        "{key} of section {y} is {} bytes",
+                        light.len()
+                    );
+                    Ok(light.to_vec())
+                })
+                .transpose()
+        };
+        Ok(Self {
+            y,
+            block_states,
+            biomes,
+            block_light: light("BlockLight")?,
+            sky_light: light("SkyLight")?,
+        })
+    }
+
+    /// The section as a compound.
+    fn to_nbt(&self) -> NbtCompound {
+        let mut nbt = NbtCompound::new();
+        nbt.insert("Y", NbtTag::Byte(self.y));
+        if let Some(block_states) = &self.block_states {
+            let palette = block_states
+                .palette
+                .iter()
+                .map(BlockState::to_nbt)
+                .collect();
+            nbt.insert(
+                "block_states",
+                NbtTag::Compound(block_states.to_nbt(NbtList::Compound(palette))),
+            );
+        }
+        if let Some(biomes) = &self.biomes {
+            let palette = biomes
+                .palette
+                .iter()
+                .map(|biome| biome.to_string().into())
+                .collect();
+            nbt.insert(
+                "biomes",
+                NbtTag::Compound(biomes.to_nbt(NbtList::String(palette))),
+            );
+        }
+        if let Some(light) = &self.block_light {
+            nbt.insert("BlockLight", NbtTag::ByteArray(light.clone()));
+        }
+        if let Some(light) = &self.sky_light {
+            nbt.insert("SkyLight", NbtTag::ByteArray(light.clone()));
+        }
+        nbt
+    }
+}
+
+/// The palette of a saved container.
+fn palette(nbt: &NbtCompound) -> Result<&NbtList, anyhow::Error> {
+    nbt.list("palette")
+        .ok_or_else(|| anyhow!("container has no palette"))
+}
+
+/// A chunk as it's saved in a region file.
+#[derive(Clone, PartialEq, Debug)]
+pub struct SavedChunk {
+    /// The data version of the game that saved it.
+    pub data_version: i32,
+    /// The chunk's X coordinate.
+    pub x: i32,
+    /// The chunk's Z coordinate.
+    pub z: i32,
+    /// The Y coordinate of the lowest section with blocks, in sections.
+    pub min_section: i32,
+    /// How far through world generation the chunk is, such as
+    /// `minecraft:full`.
+    pub status: String,
+    /// The sections, including the light-only ones just below and above the
+    /// world.
+    pub sections: Vec<SavedSection>,
+    /// Everything else, such as heightmaps, entities and block entities, kept
+    /// as is so it's saved back unchanged.
+    pub rest: NbtCompound,
+}
+
+impl SavedChunk {
+    /// Read a chunk from its root compound.
+    ///
+    /// # Errors
+    /// If the chunk is from before 1.18, or is missing its position or a
+    /// section is malformed, return an error.
+    pub fn from_nbt(mut nbt: NbtCompound) -> Result<Self, anyhow::Error> {
+        if nbt.contains("Level") {
+            bail!("chunks from before 1.18 aren't supported");
+        }
+        let mut int = |key| {
+            nbt.remove(key)
+                .and_then(NbtTag::into_int)
+                .with_context(|| format!("chunk has no {key}"))
+        };
+        let (data_version, x, z, min_section) = (
+            int("DataVersion")?,
+            int("xPos")?,
+            int("zPos")?,
+            int("yPos")?,
+        );
+        let status = nbt
+            .remove("Status")
+            .and_then(NbtTag::into_string)
+            .context("chunk has no Status")?
+            .to_string();
+        let sections = match nbt.remove("sections").and_then(NbtTag::into_list) {
+            Some(NbtList::Empty) | None => Vec::new(),
+            Some(NbtList::Compound(sections)) => sections
+                .iter()
+                .map(SavedSection::from_nbt)
+                .collect::<Result<_, _>>()?,
+            Some(_) => bail!("chunk sections aren't compounds"),
+        };
+        Ok(Self {
+            data_version,
+            x,
+            z,
+            min_section,
+            status,
+            sections,
+            rest: nbt,
+        })
+    }
+
+    /// The chunk as a root compound.
+    #[must_use]
+    pub fn to_nbt(&self) -> NbtCompound {
+        let sections = self.sections.iter().map(SavedSection::to_nbt).collect();
+        let mut nbt = NbtCompound::from_values(vec![
+            ("DataVersion".into(), NbtTag::Int(self.data_version)),
+            ("xPos".into(), NbtTag::Int(self.x)),
+            ("zPos".into(), NbtTag::Int(self.z)),
+            ("yPos".into(), NbtTag::Int(self.min_section)),
+            ("Status".into(), NbtTag::String(self.status.as_str().into())),
+            ("sections".into(), NbtTag::List(NbtList::Compound(sections))),
+        ]);
+        for (key, value) in self.rest.iter() {
+            nbt.insert(key.to_owned(), value.clone());
+        }
+        nbt
+    }
+
+    /// The section at `y`, in sections.
+    fn section(&self, y: i32) -> Option<&SavedSection> {
+        self.sections
+            .iter()
+            .find(|section| i32::from(section.y) == y)
+    }
+
+    /// The `count` sections from [`min_section`](Self::min_section) up, as
+    /// they're sent to clients. Block states and biomes are mapped to their
+    /// IDs with `block_state` and `biome`, and direct biome palettes use
+    /// `biome_bits` per entry. Missing sections are air in the biome with ID
+    /// zero.
+    ///
+    /// # Errors
+    /// If a section's packed data doesn't match its palette, mapping an entry
+    /// fails, or an ID doesn't fit in a direct palette, return an error.
+    pub fn to_sections(
+        &self,
+        count: usize,
+        block_state: impl Fn(&BlockState) -> Result<u32, anyhow::Error>,
+        biome: impl Fn(&Identifier) -> Result<u32, anyhow::Error>,
+        biome_bits: u8,
+    ) -> Result<Vec<ChunkSection>, anyhow::Error> {
+        let air = block_state(&BlockState::new(Identifier::minecraft("air")?))?;
+        (self.min_section..)
+            .take(count)
+            .map(|y| {
+                let section = self.section(y);
+                let (block_count, block_states) =
+                    match section.and_then(|section| section.block_states.as_ref()) {
+                        Some(states) => {
+                            let solid: Vec<bool> =
+                                states.palette.iter().map(|state| !state.is_air()).collect();
+                            let block_count = states
+                                .indices(Strategy::BlockStates)?
+                                .into_iter()
+                                .filter(|&i| solid[i])
+                                .count();
+                            let block_states = states.to_network(
+                                Strategy::BlockStates,
+                                &block_state,
+                                PalettedContainer::DIRECT_BLOCK_STATE_BITS,
+                            )?;
+                            (i16::try_from(block_count)?, block_states)
+                        }
+                        None => (0, PalettedContainer::single(Strategy::BlockStates, air)),
+                    };
+                let biomes = match section.and_then(|section| section.biomes.as_ref()) {
+                    Some(biomes) => biomes.to_network(Strategy::Biomes, &biome, biome_bits)?,
+                    None => PalettedContainer::single(Strategy::Biomes, 0),
+                };
+                Ok(ChunkSection {
+                    block_count,
+                    block_states,
+                    biomes,
+                })
+            })
+            .collect()
+    }
+
+    /// The heightmaps clients need, for a world `world_height` blocks tall, if
+    /// they've been saved.
+    #[must_use]
+    pub fn heightmaps(&self, world_height: u32) -> Option<Heightmaps> {
+        let heightmaps = self.rest.compound("Heightmaps")?;
+        let heightmap = |key| Heightmap::from_longs(heightmaps.long_array(key)?, world_height);
+        Some(Heightmaps {
+            motion_blocking: heightmap("MOTION_BLOCKING")?,
+            world_surface: heightmap("WORLD_SURFACE")?,
+        })
+    }
+
+    /// The chunk's block entities as they're sent to clients, with their
+    /// types mapped to IDs with `kind`. Each one's data is what was saved,
+    /// without its position and type.
+    ///
+    /// # Errors
+    /// If a block entity is malformed or mapping its type fails, return an
+    /// error.
+    pub fn block_entities(
+        &self,
+        kind: impl Fn(&Identifier) -> Result<u32, anyhow::Error>,
+    ) -> Result<Vec<BlockEntity>, anyhow::Error> {
+        let block_entities = match self.rest.list("block_entities") {
+            Some(NbtList::Compound(block_entities)) => block_entities,
+            Some(NbtList::Empty) | None => return Ok(Vec::new()),
+            Some(_) => bail!("block entities aren't compounds"),
+        };
+        block_entities
+            .iter()
+            .map(|saved| {
+                let int = |key| {
+                    saved
+                        .int(key)
+                        .with_context(|| format!("block entity has no {key}"))
+                };
+                let (x, y, z) = (int("x")?, int("y")?, int("z")?);
+                let id: Identifier = saved
+                    .string("id")
+                    .context("block entity has no id")?
+                    .to_str()
+                    .parse()?;
+                let mut data = saved.clone();
+                for key in ["id", "x", "y", "z", "keepPacked"] {
+                    data.remove(key);
+                }
+                Ok(BlockEntity {
+                    packed_xz: u8::try_from((x & 15) << 4 | (z & 15))?,
+                    y: i16::try_from(y)?,
+                    kind: kind(&id)?,
+                    data: Nbt::Some(BaseNbt::new("", data)),
+                })
+            })
+            .collect()
+    }
+
+    /// The saved light of the `count` sections from
+    /// [`min_section`](Self::min_section) up, and the sections just below and
+    /// above them.
+    #[must_use]
+    pub fn light(&self, count: usize) -> LightData {
+        let mut light = LightData::default();
+        for (i, y) in (self.min_section - 1..).take(count + 2).enumerate() {
+            let Some(section) = self.section(y) else {
+                continue;
+            };
+            if let Some(sky_light) = &section.sky_light {
+                light.sky_light_mask.set(i, true);
+                light.sky_light.push(sky_light.clone());
+            }
+            if let Some(block_light) = &section.block_light {
+                light.block_light_mask.set(i, true);
+                light.block_light.push(block_light.clone());
+            }
+        }
+        light
+    }
+}
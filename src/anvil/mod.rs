@@ -0,0 +1,573 @@
+//! Region files in the Anvil format, which store a world's chunks 32×32 to a
+//! file.
+//!
+//! A region file starts with two tables of 1024 big-endian integers, one
+//! entry per chunk: where the chunk is, as its first 4 KiB sector in the high
+//! 24 bits and its sector count in the low 8, then when it was last saved.
+//! Each chunk is its length, a byte for its compression and its compressed
+//! NBT. Chunks too big for 255 sectors go in a `.mcc` file of their own.
+
+pub mod chunk;
+
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::{ErrorKind, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, bail, ensure};
+use flate2::{
+    Compression as Level,
+    read::{GzDecoder, ZlibDecoder},
+    write::{GzEncoder, ZlibEncoder},
+};
+use lz4_java_wrc::{Lz4BlockInput, Lz4BlockOutput};
+use simdnbt::owned::{BaseNbt, Nbt};
+
+use crate::{
+    McProtoSelf,
+    io::SliceReader,
+    types::{NbtFormat, NbtMeta},
+};
+use chunk::SavedChunk;
+
+/// How a chunk is compressed in a region file.
+#[derive(Clone, Copy, PartialEq, Debug, Default, Eq, Hash)]
+pub enum ChunkCompression {
+    /// Gzip, which vanilla never writes.
+    Gzip = 1,
+    /// Zlib, vanilla's default.
+    #[default]
+    Zlib = 2,
+    /// Not compressed.
+    None = 3,
+    /// LZ4, in lz4-java's block stream format.
+    Lz4 = 4,
+}
+
+impl ChunkCompression {
+    /// The empty block lz4-java ends a stream with. `lz4_java_wrc` doesn't
+    /// write it, but vanilla fails to read a chunk without it.
+    const LZ4_END: [u8; 21] = *b"LZ4Block\x16\0\0\0\0\0\0\0\0\0\0\0\0";
+
+    /// The compression with the ID `id`, if it's supported.
+    #[must_use]
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            1 => Some(Self::Gzip),
+            2 => Some(Self::Zlib),
+            3 => Some(Self::None),
+            4 => Some(Self::Lz4),
+            _ => None,
+        }
+    }
+
+    /// Compress `data`.
+    ///
+    /// # Errors
+    /// If compressing fails, return an error.
+    pub fn compress(self, data: &[u8]) -> Result<Vec<u8>, anyhow::Error> {
+        Ok(match self {
+            Self::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), Level::default());
+                encoder.write_all(data)?;
+                encoder.finish()?
+            }
+            Self::Zlib => {
+                let mut encoder = ZlibEncoder::new(Vec::new(), Level::default());
+                encoder.write_all(data)?;
+                encoder.finish()?
+            }
+            Self::None => data.to_vec(),
+            Self::Lz4 => {
+                let mut out = Vec::new();
+                let mut encoder = Lz4BlockOutput::new(&mut out);
+                encoder.write_all(data)?;
+                encoder.flush()?;
+                drop(encoder);
+                out.extend_from_slice(&Self::LZ4_END);
+                out
+            }
+        })
+    }
+
+    /// Decompress `data`.
+    ///
+    /// # Errors
+    /// If `data` is corrupt, return an error.
+    pub fn decompress(self, data: &[u8]) -> Result<Vec<u8>, anyhow::Error> {
+        let mut out = Vec::new();
+        match self {
+            Self::Gzip => drop(GzDecoder::new(data).read_to_end(&mut out)?),
+            Self::Zlib => drop(ZlibDecoder::new(data).read_to_end(&mut out)?),
+            Self::None => out.extend_from_slice(data),
+            Self::Lz4 => drop(Lz4BlockInput::new(data).read_to_end(&mut out)?),
+        }
+        Ok(out)
+    }
+}
+
+/// An open region file.
+#[derive(Debug)]
+pub struct Region {
+    /// The file.
+    file: File,
+    /// The directory it's in, where `.mcc` files go.
+    dir: PathBuf,
+    /// Where each chunk is: its first sector in the high 24 bits and its
+    /// sector count in the low 8, or zero if it isn't saved.
+    locations: Vec<u32>,
+    /// When each chunk was last saved, in seconds since the Unix epoch.
+    timestamps: Vec<u32>,
+    /// Which sectors are in use, including the two of the header.
+    used: Vec<bool>,
+}
+
+impl Region {
+    /// The size of a sector.
+    pub const SECTOR: usize = 4096;
+    /// The number of chunks in a region.
+    const CHUNKS: usize = 32 * 32;
+    /// The number of sectors the header takes up.
+    const HEADER_SECTORS: usize = 2;
+    /// The most sectors a chunk can take up in the region file itself.
+    const MAX_SECTORS: usize = 255;
+    /// Set in the compression byte of chunks stored in a `.mcc` file.
+    const EXTERNAL: u8 = 0x80;
+
+    /// The name of the region file holding the chunk at `x`, `z`.
+    #[must_use]
+    pub fn file_name(x: i32, z: i32) -> String {
+        format!("r.{}.{}.mca", x >> 5, z >> 5)
+    }
+
+    /// Open the region file at `path`, creating it if it doesn't exist.
+    /// Chunks whose locations are out of the file or overlap another chunk's
+    /// are ignored.
+    ///
+    /// # Errors
+    /// If the file can't be opened or read, return an error.
+    pub fn open(path: &Path) -> Result<Self, anyhow::Error> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .with_context(|| format!("failed to open {}", path.display()))?;
+        let len = file.metadata()?.len() as usize;
+        let header_len = Self::HEADER_SECTORS * Self::SECTOR;
+        let mut header = vec![0; header_len];
+        if len < header_len {
+            file.set_len(header_len as u64)?;
+        } else {
+            file.read_exact(&mut header)?;
+        }
+        let mut tables = header
+            .chunks_exact(4)
+            .map(|int| u32::from_be_bytes([int[0], int[1], int[2], int[3]]));
+        let mut locations: Vec<u32> = tables.by_ref().take(Self::CHUNKS).collect();
+        let timestamps = tables.collect();
+
+        let mut used = vec![true; Self::HEADER_SECTORS];
+        used.resize(len.max(header_len).div_ceil(Self::SECTOR), false);
+        for (i, location) in locations.iter_mut().enumerate() {
+            if *location == 0 {
+                continue;
+            }
+            let (offset, count) = ((*location >> 8) as usize, (*location & 0xFF) as usize);
+            let sectors = offset..offset + count;
+            if count == 0
+                || offset < Self::HEADER_SECTORS
+                || sectors.end > used.len()
+                || used[sectors.clone()].iter().any(|&used| used)
+            {
+                eprintln!("{}: ignoring chunk {i} at a bad location", path.display());
+                *location = 0;
+                continue;
+            }
+            used[sectors].fill(true);
+        }
+
+        Ok(Self {
+            file,
+            dir: path.parent().unwrap_or(Path::new(".")).to_owned(),
+            locations,
+            timestamps,
+            used,
+        })
+    }
+
+    /// The index of the chunk at `x`, `z` in the header tables.
+    fn index(x: i32, z: i32) -> usize {
+        ((x & 31) | (z & 31) << 5).cast_unsigned() as usize
+    }
+
+    /// The path of the `.mcc` file for the chunk at `x`, `z`.
+    fn external_path(&self, x: i32, z: i32) -> PathBuf {
+        self.dir.join(format!("c.{x}.{z}.mcc"))
+    }
+
+    /// Whether the chunk at `x`, `z` is saved.
+    #[must_use]
+    pub fn contains(&self, x: i32, z: i32) -> bool {
+        self.locations[Self::index(x, z)] != 0
+    }
+
+    /// When the chunk at `x`, `z` was last saved, in seconds since the Unix
+    /// epoch, if it's saved.
+    #[must_use]
+    pub fn timestamp(&self, x: i32, z: i32) -> Option<u32> {
+        self.contains(x, z)
+            .then(|| self.timestamps[Self::index(x, z)])
+    }
+
+    /// The decompressed NBT of the chunk at `x`, `z`, if it's saved.
+    ///
+    /// # Errors
+    /// If the chunk can't be read or decompressed, return an error.
+    pub fn read(&mut self, x: i32, z: i32) -> Result<Option<Vec<u8>>, anyhow::Error> {
+        let location = self.locations[Self::index(x, z)];
+        if location == 0 {
+            return Ok(None);
+        }
+        let (offset, count) = ((location >> 8) as usize, (location & 0xFF) as usize);
+        self.file
+            .seek(SeekFrom::Start((offset * Self::SECTOR) as u64))?;
+        let mut header = [0; 5];
+        self.file.read_exact(&mut header)?;
+        let len = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        ensure!(
+            len >= 1 && len + 4 <= count * Self::SECTOR,
+            "chunk {x}, {z} has a bad length {len}"
+        );
+        let compression = header[4];
+        let data = if compression & Self::EXTERNAL == 0 {
+            let mut data = vec![0; len - 1];
+            self.file.read_exact(&mut data)?;
+            data
+        } else {
+            let path = self.external_path(x, z);
+            fs::read(&path).with_context(|| format!("failed to read {}", path.display()))?
+        };
+        let compression = ChunkCompression::from_id(compression & !Self::EXTERNAL)
+            .with_context(|| format!("chunk {x}, {z} has unknown compression {compression}"))?;
+        Ok(Some(compression.decompress(&data)?))
+    }
+
+    /// Save `data` as the NBT of the chunk at `x`, `z`, compressed with
+    /// `compression`. The chunk goes in the first run of free sectors big
+    /// enough for it, and its old sectors are only freed once it's written.
+    ///
+    /// # Errors
+    /// If the chunk can't be written, return an error.
+    pub fn write(
+        &mut self,
+        x: i32,
+        z: i32,
+        data: &[u8],
+        compression: ChunkCompression,
+    ) -> Result<(), anyhow::Error> {
+        let compressed = compression.compress(data)?;
+        let external = self.external_path(x, z);
+        let mut payload = Vec::with_capacity(compressed.len() + 5);
+        let is_external = compressed.len() + 5 > Self::MAX_SECTORS * Self::SECTOR;
+        if is_external {
+            let temp = external.with_extension("mcc.tmp");
+            fs::write(&temp, &compressed)?;
+            fs::rename(&temp, &external)?;
+            payload.extend_from_slice(&1u32.to_be_bytes());
+            payload.push(compression as u8 | Self::EXTERNAL);
+        } else {
+            payload.extend_from_slice(&(compressed.len() as u32 + 1).to_be_bytes());
+            payload.push(compression as u8);
+            payload.extend_from_slice(&compressed);
+        }
+        let count = payload.len().div_ceil(Self::SECTOR);
+        payload.resize(count * Self::SECTOR, 0);
+
+        let offset = self.allocate(count);
+        ensure!(offset < 1 << 24, "region file is full");
+        self.file
+            .seek(SeekFrom::Start((offset * Self::SECTOR) as u64))?;
+        self.file.write_all(&payload)?;
+
+        let index = Self::index(x, z);
+        let old = self.locations[index];
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_secs() as u32);
+        self.set_header(index, (offset as u32) << 8 | count as u32, timestamp)?;
+        self.free(old);
+        if !is_external {
+            remove_if_exists(&external)?;
+        }
+        Ok(())
+    }
+
+    /// Delete the chunk at `x`, `z`, if it's saved.
+    ///
+    /// # Errors
+    /// If the header or a `.mcc` file can't be updated, return an error.
+    pub fn remove(&mut self, x: i32, z: i32) -> Result<(), anyhow::Error> {
+        let index = Self::index(x, z);
+        let old = self.locations[index];
+        if old == 0 {
+            return Ok(());
+        }
+        self.set_header(index, 0, 0)?;
+        self.free(old);
+        remove_if_exists(&self.external_path(x, z))
+    }
+
+    /// Read and parse the chunk at `x`, `z`, if it's saved.
+    ///
+    /// # Errors
+    /// If the chunk can't be read, or isn't a valid chunk, return an error.
+    pub fn read_chunk(&mut self, x: i32, z: i32) -> Result<Option<SavedChunk>, anyhow::Error> {
+        let Some(data) = self.read(x, z)? else {
+            return Ok(None);
+        };
+        let meta = NbtMeta {
+            format: NbtFormat::Named,
+            max_size: data.len(),
+            ..NbtMeta::default()
+        };
+        let Nbt::Some(nbt) = Nbt::read(&mut SliceReader::new(&data), meta)? else {
+            bail!("chunk {x}, {z} is empty");
+        };
+        SavedChunk::from_nbt(nbt.as_compound())
+            .with_context(|| format!("failed to parse chunk {x}, {z}"))
+            .map(Some)
+    }
+
+    /// Save `chunk` at its position, compressed with `compression`.
+    ///
+    /// # Errors
+    /// If the chunk can't be written, return an error.
+    pub fn write_chunk(
+        &mut self,
+        chunk: &SavedChunk,
+        compression: ChunkCompression,
+    ) -> Result<(), anyhow::Error> {
+        let mut data = Vec::new();
+        Nbt::Some(BaseNbt::new("", chunk.to_nbt())).write(&mut data)?;
+        self.write(chunk.x, chunk.z, &data, compression)
+    }
+
+    /// Find `count` free sectors, preferring the first gap big enough and
+    /// otherwise growing the file, and mark them used.
+    fn allocate(&mut self, count: usize) -> usize {
+        let mut run = 0;
+        let mut start = None;
+        for (i, &used) in self.used.iter().enumerate() {
+            run = if used { 0 } else { run + 1 };
+            if run == count {
+                start = Some(i + 1 - count);
+                break;
+            }
+        }
+        // Past the end, starting in any free sectors already there.
+        let start = start.unwrap_or(self.used.len() - run);
+        if self.used.len() < start + count {
+            self.used.resize(start + count, false);
+        }
+        self.used[start..start + count].fill(true);
+        start
+    }
+
+    /// Mark the sectors at `location` free.
+    fn free(&mut self, location: u32) {
+        let (offset, count) = ((location >> 8) as usize, (location & 0xFF) as usize);
+        if let Some(sectors) = self.used.get_mut(offset..offset + count) {
+            sectors.fill(false);
+        }
+    }
+
+    /// Set the header entries of the chunk at `index`.
+    fn set_header(
+        &mut self,
+        index: usize,
+        location: u32,
+        timestamp: u32,
+    ) -> Result<(), anyhow::Error> {
+        self.locations[index] = location;
+        self.timestamps[index] = timestamp;
+        self.file.seek(SeekFrom::Start(index as u64 * 4))?;
+        self.file.write_all(&location.to_be_bytes())?;
+        self.file
+            .seek(SeekFrom::Start((Self::SECTOR + index * 4) as u64))?;
+        self.file.write_all(&timestamp.to_be_bytes())?;
+        Ok(())
+    }
+}
+
+/// The chunks of a dimension, read from the region files in one directory
+/// and saved back to them. Region files are kept open once they're used, and
+/// chunks are kept once they're read until they're
+/// [unloaded](Self::unload).
+///
+/// It can be shared between threads: its locks are only held to look things
+/// up, and never while reading or writing a region file, apart from the lock
+/// on that file.
+#[derive(Debug)]
+pub struct ChunkStorage {
+    /// The directory the region files are in.
+    dir: PathBuf,
+    /// The open region files, by region coordinates.
+    regions: Mutex<HashMap<(i32, i32), SharedRegion>>,
+    /// The chunks that have been read or changed, by chunk coordinates.
+    chunks: Mutex<HashMap<(i32, i32), Cached>>,
+}
+
+/// An open region file, locked while it's read or written.
+type SharedRegion = Arc<Mutex<Region>>;
+
+/// A chunk kept by a [`ChunkStorage`].
+#[derive(Clone, Debug)]
+struct Cached {
+    /// The chunk.
+    chunk: Arc<SavedChunk>,
+    /// Whether it's changed since it was last saved.
+    changed: bool,
+}
+
+impl ChunkStorage {
+    /// The chunks in the region files in `dir`, such as a world's `region`
+    /// directory. Nothing is read until it's needed.
+    #[must_use]
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            regions: Mutex::new(HashMap::new()),
+            chunks: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The chunk at `x`, `z`, read from its region file the first time, if
+    /// it's saved. Region files that don't exist aren't created.
+    ///
+    /// # Errors
+    /// If the region file can't be opened, or the chunk can't be read, return
+    /// an error.
+    pub fn chunk(&self, x: i32, z: i32) -> Result<Option<Arc<SavedChunk>>, anyhow::Error> {
+        if let Some(cached) = self.chunks().get(&(x, z)) {
+            return Ok(Some(Arc::clone(&cached.chunk)));
+        }
+        let Some(region) = self.region(x, z, false)? else {
+            return Ok(None);
+        };
+        let Some(chunk) = lock(&region).read_chunk(x, z)? else {
+            return Ok(None);
+        };
+        // Another thread may have read or changed it in the meantime.
+        let mut chunks = self.chunks();
+        let cached = chunks.entry((x, z)).or_insert_with(|| Cached {
+            chunk: Arc::new(chunk),
+            changed: false,
+        });
+        Ok(Some(Arc::clone(&cached.chunk)))
+    }
+
+    /// Replace the chunk at its coordinates with `chunk`, to be written to
+    /// its region file when the storage is next saved.
+    pub fn insert(&self, chunk: SavedChunk) {
+        self.chunks().insert(
+            (chunk.x, chunk.z),
+            Cached {
+                chunk: Arc::new(chunk),
+                changed: true,
+            },
+        );
+    }
+
+    /// Whether the chunk at `x`, `z` is kept in memory.
+    #[must_use]
+    pub fn is_loaded(&self, x: i32, z: i32) -> bool {
+        self.chunks().contains_key(&(x, z))
+    }
+
+    /// Stop keeping the chunks `keep` returns false for, given their
+    /// coordinates. Chunks that have changed are kept until they're saved.
+    pub fn unload(&self, keep: impl Fn(i32, i32) -> bool) {
+        self.chunks()
+            .retain(|&(x, z), cached| cached.changed || keep(x, z));
+    }
+
+    /// Save the chunks that have changed since they were last saved back to
+    /// their region files, with vanilla's default compression.
+    ///
+    /// # Errors
+    /// If a chunk can't be written, return an error. It and the chunks that
+    /// weren't written yet are still saved next time.
+    pub fn save(&self) -> Result<(), anyhow::Error> {
+        let changed: Vec<_> = self
+            .chunks()
+            .iter_mut()
+            .filter(|(_, cached)| cached.changed)
+            .map(|(&at, cached)| {
+                cached.changed = false;
+                (at, Arc::clone(&cached.chunk))
+            })
+            .collect();
+        for (i, ((x, z), chunk)) in changed.iter().enumerate() {
+            let written = self.region(*x, *z, true).and_then(|region| match region {
+                Some(region) => lock(&region).write_chunk(chunk, ChunkCompression::default()),
+                None => Ok(()),
+            });
+            if let Err(e) = written {
+                let mut chunks = self.chunks();
+                for (at, chunk) in &changed[i..] {
+                    chunks
+                        .entry(*at)
+                        .or_insert_with(|| Cached {
+                            chunk: Arc::clone(chunk),
+                            changed: true,
+                        })
+                        .changed = true;
+                }
+                return Err(e.context(format!("failed to save chunk {x}, {z}")));
+            }
+        }
+        Ok(())
+    }
+
+    /// The chunks that have been read or changed.
+    fn chunks(&self) -> MutexGuard<'_, HashMap<(i32, i32), Cached>> {
+        lock(&self.chunks)
+    }
+
+    /// The region file holding the chunk at `x`, `z`, opening it if it isn't
+    /// open yet. If it doesn't exist, it's only created if `create` is set.
+    fn region(&self, x: i32, z: i32, create: bool) -> Result<Option<SharedRegion>, anyhow::Error> {
+        let at = (x >> 5, z >> 5);
+        if let Some(region) = lock(&self.regions).get(&at) {
+            return Ok(Some(Arc::clone(region)));
+        }
+        let path = self.dir.join(Region::file_name(x, z));
+        if !create && !path.exists() {
+            return Ok(None);
+        }
+        let region = Arc::new(Mutex::new(Region::open(&path)?));
+        // If another thread opened it in the meantime, use theirs, so there's
+        // only ever one view of which sectors are used.
+        let mut regions = lock(&self.regions);
+        Ok(Some(Arc::clone(regions.entry(at).or_insert(region))))
+    }
+}
+
+/// Lock `mutex`, carrying on if a thread panicked while holding it.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Delete the file at `path`, if there is one.
+fn remove_if_exists(path: &Path) -> Result<(), anyhow::Error> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
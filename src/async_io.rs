@@ -0,0 +1,138 @@
+//! Async counterparts of [`McReader`](crate::McReader) and
+//! [`McWriter`](crate::McWriter), behind the `async` feature.
+//!
+//! They're implemented for everything that implements tokio's [`AsyncRead`]
+//! or [`AsyncWrite`], such as its `TcpStream`. For another runtime, implement
+//! them for its socket by forwarding to its `read_exact` and `write_all`.
+//! Only framing is async. Once a whole frame is in memory its body is decoded with the usual
+//! blocking [`McProto`](crate::McProto) impls through [`Packet::decode`].
+
+use core::future::Future;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::{
+    McProto, ProtocolError,
+    io::SliceReader,
+    packet::{Compression, Packet},
+    types::VarNum,
+};
+
+/// An async reader.
+pub trait AsyncMcReader: Send {
+    /// Read exactly `bytes.len()` bytes into `bytes`.
+    ///
+    /// # Errors
+    /// If there's an error reading that many bytes, return an error.
+    /// The contents of the buffer is unspecified if an error is returned.
+    fn read(&mut self, bytes: &mut [u8]) -> impl Future<Output = Result<(), ProtocolError>> + Send;
+
+    /// Read a single byte.
+    ///
+    /// # Errors
+    /// Propagates errors from [`read`](AsyncMcReader::read).
+    fn read_byte(&mut self) -> impl Future<Output = Result<u8, ProtocolError>> + Send {
+        async {
+            let mut out = [0u8];
+            self.read(&mut out).await?;
+            Ok(out[0])
+        }
+    }
+}
+
+/// An async writer.
+pub trait AsyncMcWriter: Send {
+    /// Write all of `bytes`.
+    ///
+    /// # Errors
+    /// If there's an error writing all bytes, return an error.
+    fn write(&mut self, bytes: &[u8]) -> impl Future<Output = Result<(), ProtocolError>> + Send;
+}
+
+impl AsyncMcReader for SliceReader<'_> {
+    async fn read(&mut self, bytes: &mut [u8]) -> Result<(), ProtocolError> {
+        crate::McReader::read(self, bytes)
+    }
+}
+
+impl<R: AsyncRead + Unpin + Send> AsyncMcReader for R {
+    async fn read(&mut self, bytes: &mut [u8]) -> Result<(), ProtocolError> {
+        self.read_exact(bytes).await?;
+        Ok(())
+    }
+}
+
+impl<W: AsyncWrite + Unpin + Send> AsyncMcWriter for W {
+    async fn write(&mut self, bytes: &[u8]) -> Result<(), ProtocolError> {
+        Ok(self.write_all(bytes).await?)
+    }
+}
+
+/// Read a `VarInt` a byte at a time.
+///
+/// # Errors
+/// If the reader fails or the `VarInt` is longer than five bytes, return an
+/// error.
+pub async fn read_var_int(reader: &mut impl AsyncMcReader) -> Result<u32, ProtocolError> {
+    let mut bytes = [0u8; 5];
+    for len in 1..=bytes.len() {
+        bytes[len - 1] = reader.read_byte().await?;
+        if bytes[len - 1] & VarNum::CONTINUE_BIT == 0 {
+            return VarNum::read(&mut SliceReader::new(&bytes[..len]), ());
+        }
+    }
+    Err(ProtocolError::VarIntTooLong {
+        offset: None,
+        max: bytes.len(),
+    })
+}
+
+impl Packet {
+    /// Read a single frame asynchronously, decompressing it if `compression`
+    /// is enabled.
+    ///
+    /// # Errors
+    /// If the reader fails, the frame is empty or over
+    /// [`MAX_LENGTH`](Self::MAX_LENGTH), or it fails to decompress, return an
+    /// error.
+    pub async fn read_async(
+        reader: &mut impl AsyncMcReader,
+        compression: Option<Compression>,
+    ) -> Result<Self, ProtocolError> {
+        let length = read_var_int(reader).await?;
+        let mut frame = vec![0u8; Self::check_length(length as usize)?];
+        reader.read(&mut frame).await?;
+        Self::from_frame(frame, compression)
+    }
+
+    /// Write this frame asynchronously, prefixed with its length and
+    /// compressed if `compression` is enabled. The frame is encoded in memory
+    /// first, then written all at once.
+    ///
+    /// # Errors
+    /// If the writer or compression fails, or the frame is over
+    /// [`MAX_LENGTH`](Self::MAX_LENGTH), return an error.
+    pub async fn write_async(
+        &self,
+        writer: &mut impl AsyncMcWriter,
+        compression: Option<Compression>,
+    ) -> Result<(), ProtocolError> {
+        let mut bytes = Vec::new();
+        self.write_with(&mut bytes, compression)?;
+        writer.write(&bytes).await
+    }
+
+    /// Read a single frame asynchronously and decode its body as a `T`,
+    /// returning the packet ID alongside it.
+    ///
+    /// # Errors
+    /// If reading the frame or decoding its body fails, return an error.
+    pub async fn read_value_async<T, P: McProto<T>>(
+        reader: &mut impl AsyncMcReader,
+        compression: Option<Compression>,
+        meta: P::Meta,
+    ) -> Result<(u32, T), ProtocolError> {
+        let packet = Self::read_async(reader, compression).await?;
+        Ok((packet.id, packet.decode::<T, P>(meta)?))
+    }
+}
@@ -0,0 +1,172 @@
+//! Authenticating online-mode players with a session server.
+
+use core::{
+    fmt::{Debug, Write},
+    time::Duration,
+};
+
+use anyhow::{Context, bail};
+use serde_json::Value;
+use sha1::{Digest, Sha1};
+use ureq::Agent;
+
+use crate::{packets::login::Property, types::Uuid};
+
+/// A player's profile, as vouched for by the session server.
+#[derive(Clone, PartialEq, Debug, Eq, Hash)]
+pub struct GameProfile {
+    /// The player's UUID.
+    pub id: Uuid,
+    /// The player's username, with the capitalization the session server knows.
+    pub name: String,
+    /// The player's profile properties, such as their signed skin.
+    pub properties: Vec<Property>,
+}
+
+/// A session server, which online-mode clients tell about the servers they
+/// join. [`MojangSessionService`] asks Mojang's; implement this to use another,
+/// or to stand in for it in tests.
+pub trait SessionService: Send + Sync + Debug {
+    /// Check that `username` told the session server they're joining the
+    /// server identified by `server_hash`, returning their profile if so and
+    /// `None` if not.
+    ///
+    /// # Errors
+    /// If the session server can't be reached, return an error.
+    fn has_joined(
+        &self,
+        username: &str,
+        server_hash: &str,
+    ) -> Result<Option<GameProfile>, anyhow::Error>;
+}
+
+impl GameProfile {
+    /// Parse a profile from the session server's JSON, which has the UUID as
+    /// hex without hyphens.
+    ///
+    /// # Errors
+    /// If the JSON isn't a profile, return an error.
+    pub fn from_json(json: &Value) -> Result<Self, anyhow::Error> {
+        let string = |json: &Value, key| {
+            json.get(key)
+                .and_then(Value::as_str)
+                .map(str::to_owned)
+                .with_context(|| format!("profile has no {key}"))
+        };
+        let id = string(json, "id")?;
+        if id.len() != 32 {
+            bail!("invalid profile UUID {id:?}");
+        }
+        let id = u128::from_str_radix(&id, 16)
+            .with_context(|| format!("invalid profile UUID {id:?}"))?;
+        let properties = match json.get("properties") {
+            Some(properties) => properties
+                .as_array()
+                .context("profile properties aren't a list")?
+                .iter()
+                .map(|property| {
+                    Ok(Property {
+                        name: string(property, "name")?,
+                        value: string(property, "value")?,
+                        signature: string(property, "signature").ok(),
+                    })
+                })
+                .collect::<Result<_, anyhow::Error>>()?,
+            None => Vec::new(),
+        };
+        Ok(Self {
+            id: Uuid(id),
+            name: string(json, "name")?,
+            properties,
+        })
+    }
+}
+
+/// Mojang's session server, which vanilla servers authenticate players with.
+#[derive(Debug)]
+pub struct MojangSessionService {
+    /// The HTTP client.
+    agent: Agent,
+}
+
+impl MojangSessionService {
+    /// The endpoint servers ask whether a player has joined.
+    pub const HAS_JOINED_URL: &str = "https://sessionserver.mojang.com/session/minecraft/hasJoined";
+    /// How long to wait for the session server before giving up, matching
+    /// the client's login timeout.
+    pub const TIMEOUT: Duration = Duration::from_secs(30);
+
+    /// A client for Mojang's session server.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            agent: Agent::config_builder()
+                .timeout_global(Some(Self::TIMEOUT))
+                .build()
+                .into(),
+        }
+    }
+}
+
+impl Default for MojangSessionService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SessionService for MojangSessionService {
+    fn has_joined(
+        &self,
+        username: &str,
+        server_hash: &str,
+    ) -> Result<Option<GameProfile>, anyhow::Error> {
+        let mut response = self
+            .agent
+            .get(Self::HAS_JOINED_URL)
+            .query("username", username)
+            .query("serverId", server_hash)
+            .call()
+            .context("asking the session server")?;
+        // The session server answers No Content if the player hasn't joined.
+        if response.status() == 204 {
+            return Ok(None);
+        }
+        let body = response.body_mut().read_to_string()?;
+        let json: Value =
+            serde_json::from_str(&body).context("parsing the session server's reply")?;
+        GameProfile::from_json(&json).map(Some)
+    }
+}
+
+/// The hash the client and server each send to the session server to prove
+/// they're talking to each other.
+#[must_use]
+pub fn server_hash(server_id: &str, shared_secret: &[u8], public_key: &[u8]) -> String {
+    let digest = Sha1::new()
+        .chain_update(server_id)
+        .chain_update(shared_secret)
+        .chain_update(public_key)
+        .finalize();
+    signed_hex(digest.into())
+}
+
+/// Format a digest the way Minecraft does: as a signed two's complement
+/// number in hexadecimal, without leading zeros.
+fn signed_hex(digest: [u8; 20]) -> String {
+    let negative = digest[0] & 0x80 != 0;
+    let mut bytes = digest;
+    if negative {
+        let mut carry = true;
+        for byte in bytes.iter_mut().rev() {
+            let (sum, overflow) = (!*byte).overflowing_add(u8::from(carry));
+            *byte = sum;
+            carry = overflow;
+        }
+    }
+    let hex = bytes.iter().fold(String::new(), |mut hex, byte| {
+        let _ = write!(hex, "{byte:02x}");
+        hex
+    });
+    let hex = hex.trim_start_matches('0');
+    format!("{}{hex}", if negative { "-" } else { "" })
+}
@@ -0,0 +1,164 @@
+//! Block states and block entity types, and the IDs clients know them by.
+
+use std::{collections::HashMap, sync::LazyLock};
+
+use anyhow::{Context, bail};
+use serde_json::Value;
+
+use crate::{anvil::chunk::BlockState, types::Identifier};
+
+/// A block and the range of state IDs it has.
+#[derive(Clone, PartialEq, Debug, Eq)]
+struct Block {
+    /// The ID of the block's first state.
+    id: u32,
+    /// The ID of the state the block is in when it's placed with no
+    /// properties.
+    default: u32,
+    /// The block's properties and the values each can take, in the order
+    /// their states are numbered in: the last property changes fastest.
+    properties: Vec<(String, Vec<String>)>,
+}
+
+/// Every block in the game and its states, and every type of block entity.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Blocks {
+    /// Each block by name.
+    by_name: HashMap<Identifier, Block>,
+    /// The total number of block states.
+    states: u32,
+    /// The block entity types, in ID order.
+    entity_types: Vec<Identifier>,
+}
+
+/// The vanilla blocks, bundled with the server.
+static VANILLA: LazyLock<Blocks> = LazyLock::new(|| {
+    serde_json::from_str(include_str!("../data/blocks.json"))
+        .map_err(anyhow::Error::from)
+        .and_then(|json| Blocks::from_json(&json))
+        .expect("bundled block data is valid")
+});
+
+impl Blocks {
+    /// The vanilla blocks for [`GAME_VERSION`](crate::packets::GAME_VERSION),
+    /// bundled with the server.
+    #[must_use]
+    pub fn vanilla() -> &'static Self {
+        &VANILLA
+    }
+
+    /// Load blocks from JSON, with the `block_entity_types` in ID order, and
+    /// `blocks`, an object with each block's `id`, the ID of its first state.
+    /// Blocks with properties also have their `default` state's ID, and their
+    /// `properties` with the values each can take. A block's states are
+    /// numbered the way vanilla numbers them, with the last property changing
+    /// fastest.
+    ///
+    /// # Errors
+    /// If the JSON isn't in that shape, or a block's states overlap the
+    /// next's, return an error.
+    pub fn from_json(json: &Value) -> Result<Self, anyhow::Error> {
+        let entity_types = json
+            .get("block_entity_types")
+            .and_then(Value::as_array)
+            .context("expected an array of block entity types")?
+            .iter()
+            .map(|id| match id.as_str() {
+                Some(id) => Ok(id.parse()?),
+                None => bail!("expected a block entity type, found {id}"),
+            })
+            .collect::<Result<_, anyhow::Error>>()?;
+        let Some(entries) = json.get("blocks").and_then(Value::as_object) else {
+            bail!("expected an object of blocks");
+        };
+        let mut by_name = HashMap::new();
+        let mut states = 0;
+        for (name, entry) in entries {
+            let int = |key| {
+                entry
+                    .get(key)
+                    .and_then(Value::as_u64)
+                    .and_then(|id| u32::try_from(id).ok())
+            };
+            let id = int("id").with_context(|| format!("{name} has no id"))?;
+            if id != states {
+                bail!("{name} starts at state {id}, expected {states}");
+            }
+            let mut properties = Vec::new();
+            if let Some(values) = entry.get("properties").and_then(Value::as_object) {
+                for (property, values) in values {
+                    let values: Vec<String> = values
+                        .as_array()
+                        .and_then(|values| {
+                            values
+                                .iter()
+                                .map(|v| Some(v.as_str()?.to_owned()))
+                                .collect()
+                        })
+                        .filter(|values: &Vec<_>| !values.is_empty())
+                        .with_context(|| format!("{name} property {property} has no values"))?;
+                    properties.push((property.clone(), values));
+                }
+            }
+            let count = properties
+                .iter()
+                .try_fold(1u32, |count, (_, values)| {
+                    count.checked_mul(u32::try_from(values.len()).ok()?)
+                })
+                .with_context(|| format!("{name} has too many states"))?;
+            let default = int("default").unwrap_or(id);
+            if !(id..id + count).contains(&default) {
+                bail!("{name}'s default state {default} isn't one of its own");
+            }
+            states = id + count;
+            by_name.insert(
+                name.parse()?,
+                Block {
+                    id,
+                    default,
+                    properties,
+                },
+            );
+        }
+        Ok(Self {
+            by_name,
+            states,
+            entity_types,
+        })
+    }
+
+    /// The total number of block states.
+    #[must_use]
+    pub fn states(&self) -> u32 {
+        self.states
+    }
+
+    /// The ID of the block entity type `kind`, if it exists.
+    #[must_use]
+    pub fn block_entity_type(&self, kind: &Identifier) -> Option<u32> {
+        let index = self.entity_types.iter().position(|k| k == kind)?;
+        Some(index as u32)
+    }
+
+    /// The ID of `state`, if its block exists and the values of its
+    /// properties are valid. Properties it doesn't give take their value in
+    /// the block's default state, and ones the block doesn't have are
+    /// ignored, as vanilla does.
+    #[must_use]
+    pub fn id(&self, state: &BlockState) -> Option<u32> {
+        let block = self.by_name.get(&state.name)?;
+        let mut default = block.default - block.id;
+        let (mut offset, mut stride) = (0, 1);
+        for (property, values) in block.properties.iter().rev() {
+            let count = values.len() as u32;
+            let index = match state.properties.iter().find(|(key, _)| key == property) {
+                Some((_, value)) => values.iter().position(|v| v == value)? as u32,
+                None => default % count,
+            };
+            default /= count;
+            offset += index * stride;
+            stride *= count;
+        }
+        Some(block.id + offset)
+    }
+}
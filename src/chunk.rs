@@ -0,0 +1,455 @@
+//! Chunk sections, stored the way they're sent: as paletted containers of
+//! packed long arrays. Also the heightmaps sent alongside them.
+
+use std::collections::HashMap;
+
+use simdnbt::owned::{BaseNbt, Nbt, NbtCompound, NbtTag};
+
+use crate::{
+    McProto, McProtoSelf, McReader, McWriter, ProtocolError,
+    io::SliceReader,
+    types::{ByteArray, Length, VarNum},
+};
+
+/// The kind of values a [`PalettedContainer`] holds, which decides how many
+/// there are and which palette is used at how many bits per entry.
+#[derive(Clone, Copy, PartialEq, Debug, Default, Eq, Hash)]
+pub enum Strategy {
+    /// The block state of each block in a section, indexed by
+    /// `(y * 16 + z) * 16 + x`.
+    #[default]
+    BlockStates,
+    /// The biome of each 4×4×4 cell in a section, indexed the same way.
+    Biomes,
+}
+
+impl Strategy {
+    /// The number of values in a container.
+    #[must_use]
+    pub const fn entries(self) -> usize {
+        match self {
+            Self::BlockStates => 16 * 16 * 16,
+            Self::Biomes => 4 * 4 * 4,
+        }
+    }
+
+    /// The fewest bits per entry an indirect palette uses.
+    #[must_use]
+    pub const fn min_indirect_bits(self) -> u8 {
+        match self {
+            Self::BlockStates => 4,
+            Self::Biomes => 1,
+        }
+    }
+
+    /// The most bits per entry an indirect palette uses. Containers needing
+    /// more use a direct palette.
+    #[must_use]
+    pub const fn max_indirect_bits(self) -> u8 {
+        match self {
+            Self::BlockStates => 8,
+            Self::Biomes => 3,
+        }
+    }
+}
+
+/// How a [`PalettedContainer`] maps its packed entries to values.
+#[derive(Clone, PartialEq, Debug, Eq, Hash)]
+pub enum Palette {
+    /// Every value is this one, and nothing is packed.
+    Single(u32),
+    /// Entries are indices into this list of values.
+    Indirect(Vec<u32>),
+    /// Entries are the values themselves, such as global block state IDs.
+    Direct,
+}
+
+/// A fixed number of values, such as a section's block states, packed into
+/// longs as indices into a palette.
+///
+/// Decoding keeps the palette and packed data as they were sent, so
+/// re-encoding gives back the same bytes even if the sender's palette wasn't
+/// the smallest possible.
+#[derive(Clone, PartialEq, Debug, Eq, Hash)]
+pub struct PalettedContainer {
+    /// What the container holds.
+    strategy: Strategy,
+    /// The bits per packed entry. Zero for a single value.
+    bits: u8,
+    /// How entries map to values.
+    palette: Palette,
+    /// The entries, packed from the least significant bit of each long. An
+    /// entry never spans two longs, so any leftover high bits are unused.
+    data: Vec<u64>,
+}
+
+impl PalettedContainer {
+    /// The bits per entry of a direct block state palette, enough for every
+    /// block state in [`GAME_VERSION`](crate::packets::GAME_VERSION).
+    pub const DIRECT_BLOCK_STATE_BITS: u8 = 15;
+
+    /// A container where every value is `value`.
+    #[must_use]
+    pub fn single(strategy: Strategy, value: u32) -> Self {
+        Self {
+            strategy,
+            bits: 0,
+            palette: Palette::Single(value),
+            data: Vec::new(),
+        }
+    }
+
+    /// Pack `values` with the smallest palette that fits them. A direct
+    /// palette uses `direct_bits` per entry, which should be
+    /// [`direct_bits`](Self::direct_bits) of the registry's size.
+    ///
+    /// # Panics
+    /// If there isn't exactly one value per [entry](Strategy::entries), or a
+    /// value doesn't fit in `direct_bits`.
+    #[must_use]
+    pub fn new(strategy: Strategy, values: &[u32], direct_bits: u8) -> Self {
+        assert_eq!(
+            values.len(),
+            strategy.entries(),
+            "wrong number of values for {strategy:?}"
+        );
+        let mut palette = Vec::new();
+        let mut indices = HashMap::new();
+        for &value in values {
+            indices.entry(value).or_insert_with(|| {
+                palette.push(value);
+                palette.len() as u32 - 1
+            });
+        }
+        if let [value] = palette[..] {
+            return Self::single(strategy, value);
+        }
+
+        let bits = Self::direct_bits(palette.len());
+        if bits <= strategy.max_indirect_bits() {
+            let bits = bits.max(strategy.min_indirect_bits());
+            let data = pack(bits, values.iter().map(|value| indices[value]));
+            Self {
+                strategy,
+                bits,
+                palette: Palette::Indirect(palette),
+                data,
+            }
+        } else {
+            assert!(
+                values.iter().all(|&value| value >> direct_bits == 0),
+                "a value doesn't fit in {direct_bits} bits"
+            );
+            Self {
+                strategy,
+                bits: direct_bits,
+                palette: Palette::Direct,
+                data: pack(direct_bits, values.iter().copied()),
+            }
+        }
+    }
+
+    /// The bits per entry needed to tell `count` values apart.
+    #[must_use]
+    pub fn direct_bits(count: usize) -> u8 {
+        count.next_power_of_two().trailing_zeros() as u8
+    }
+
+    /// What the container holds.
+    #[must_use]
+    pub fn strategy(&self) -> Strategy {
+        self.strategy
+    }
+
+    /// The bits per packed entry, which is zero for a single value.
+    #[must_use]
+    pub fn bits(&self) -> u8 {
+        self.bits
+    }
+
+    /// How entries map to values.
+    #[must_use]
+    pub fn palette(&self) -> &Palette {
+        &self.palette
+    }
+
+    /// The value at `index`.
+    ///
+    /// # Panics
+    /// If `index` is out of range.
+    #[must_use]
+    pub fn get(&self, index: usize) -> u32 {
+        assert!(
+            index < self.strategy.entries(),
+            "index {index} is out of range"
+        );
+        match &self.palette {
+            Palette::Single(value) => *value,
+            Palette::Indirect(palette) => palette[unpack(self.bits, &self.data, index) as usize],
+            Palette::Direct => unpack(self.bits, &self.data, index),
+        }
+    }
+
+    /// Every value, in index order.
+    #[must_use]
+    pub fn values(&self) -> Vec<u32> {
+        (0..self.strategy.entries()).map(|i| self.get(i)).collect()
+    }
+
+    /// The number of bytes the container takes up when written.
+    #[must_use]
+    pub fn encoded_len(&self) -> usize {
+        let palette = match &self.palette {
+            Palette::Single(value) => VarNum::encoded_len(*value),
+            Palette::Indirect(palette) => {
+                VarNum::encoded_len(palette.len() as u32)
+                    + palette
+                        .iter()
+                        .map(|&v| VarNum::encoded_len(v))
+                        .sum::<usize>()
+            }
+            Palette::Direct => 0,
+        };
+        1 + palette + VarNum::encoded_len(self.data.len() as u32) + self.data.len() * 8
+    }
+}
+
+/// The number of longs needed to pack `entries` entries of `bits` bits.
+pub(crate) fn packed_len(bits: u8, entries: usize) -> usize {
+    if bits == 0 {
+        0
+    } else {
+        entries.div_ceil(64 / usize::from(bits))
+    }
+}
+
+/// Pack `entries` of `bits` bits into longs, without spanning longs.
+pub(crate) fn pack(bits: u8, entries: impl ExactSizeIterator<Item = u32>) -> Vec<u64> {
+    let per_long = 64 / usize::from(bits);
+    let mut data = vec![0; packed_len(bits, entries.len())];
+    for (i, entry) in entries.enumerate() {
+        data[i / per_long] |= u64::from(entry) << ((i % per_long) * usize::from(bits));
+    }
+    data
+}
+
+/// Unpack the entry at `index` from longs of `bits`-bit entries.
+pub(crate) fn unpack(bits: u8, data: &[u64], index: usize) -> u32 {
+    let per_long = 64 / usize::from(bits);
+    let entry = data[index / per_long] >> ((index % per_long) * usize::from(bits));
+    (entry & ((1 << bits) - 1)) as u32
+}
+
+impl McProtoSelf for PalettedContainer {
+    type Meta = Strategy;
+    fn write(self, writer: &mut dyn McWriter) -> Result<(), ProtocolError> {
+        self.bits.write(writer)?;
+        match self.palette {
+            Palette::Single(value) => VarNum::write(value, writer)?,
+            Palette::Indirect(palette) => VarNum::write(palette, writer)?,
+            Palette::Direct => {}
+        }
+        self.data.write(writer)
+    }
+    fn read(reader: &mut dyn McReader, strategy: Strategy) -> Result<Self, ProtocolError> {
+        let offset = reader.position();
+        let bits: u8 = McProtoSelf::read(reader, ())?;
+        let palette = match bits {
+            0 => Palette::Single(VarNum::read(reader, ())?),
+            _ if bits < strategy.min_indirect_bits() || bits > 32 => {
+                return Err(ProtocolError::invalid(
+                    offset,
+                    format!("{bits} bits per entry in a {strategy:?} container"),
+                ));
+            }
+            _ if bits <= strategy.max_indirect_bits() => {
+                let palette: Vec<u32> = VarNum::read(reader, ())?;
+                if palette.is_empty() {
+                    return Err(ProtocolError::invalid(offset, "empty palette"));
+                }
+                Palette::Indirect(palette)
+            }
+            _ => Palette::Direct,
+        };
+        let data: Vec<u64> = McProtoSelf::read(reader, ())?;
+        let expected = packed_len(bits, strategy.entries());
+        if data.len() != expected {
+            return Err(ProtocolError::invalid(
+                offset,
+                format!(
+                    "{} longs of packed {strategy:?}, expected {expected}",
+                    data.len()
+                ),
+            ));
+        }
+        if let Palette::Indirect(palette) = &palette
+            && (0..strategy.entries()).any(|i| unpack(bits, &data, i) as usize >= palette.len())
+        {
+            return Err(ProtocolError::invalid(offset, "palette index out of range"));
+        }
+        Ok(Self {
+            strategy,
+            bits,
+            palette,
+            data,
+        })
+    }
+}
+
+/// A 16×16×16 section of a chunk.
+#[derive(Clone, PartialEq, Debug, Eq, Hash)]
+pub struct ChunkSection {
+    /// The number of blocks that aren't air, which the client uses to skip
+    /// empty sections.
+    pub block_count: i16,
+    /// The block states.
+    pub block_states: PalettedContainer,
+    /// The biomes.
+    pub biomes: PalettedContainer,
+}
+
+impl ChunkSection {
+    /// The number of bytes the section takes up when written.
+    #[must_use]
+    pub fn encoded_len(&self) -> usize {
+        2 + self.block_states.encoded_len() + self.biomes.encoded_len()
+    }
+}
+
+impl McProtoSelf for ChunkSection {
+    type Meta = ();
+    fn write(self, writer: &mut dyn McWriter) -> Result<(), ProtocolError> {
+        self.block_count.write(writer)?;
+        self.block_states.write(writer)?;
+        self.biomes.write(writer)
+    }
+    fn read(reader: &mut dyn McReader, (): ()) -> Result<Self, ProtocolError> {
+        Ok(Self {
+            block_count: McProtoSelf::read(reader, ())?,
+            block_states: McProtoSelf::read(reader, Strategy::BlockStates)?,
+            biomes: McProtoSelf::read(reader, Strategy::Biomes)?,
+        })
+    }
+}
+
+/// A chunk's sections from the bottom up, inside a byte array prefixed with
+/// its length as a `VarInt`. Sections are read until the array runs out.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Sections;
+
+impl Sections {
+    /// The most bytes of sections vanilla accepts.
+    pub const MAX_LEN: Length = Length::new(2_097_152);
+}
+
+impl McProto<Vec<ChunkSection>> for Sections {
+    type Meta = ();
+    fn write(sections: Vec<ChunkSection>, writer: &mut dyn McWriter) -> Result<(), ProtocolError> {
+        let len: usize = sections.iter().map(ChunkSection::encoded_len).sum();
+        VarNum::write(len as u32, writer)?;
+        for section in sections {
+            section.write(writer)?;
+        }
+        Ok(())
+    }
+    fn read(reader: &mut dyn McReader, (): ()) -> Result<Vec<ChunkSection>, ProtocolError> {
+        let bytes = ByteArray::read(reader, Self::MAX_LEN)?;
+        let mut reader = SliceReader::new(&bytes);
+        let mut sections = Vec::new();
+        while !reader.is_empty() {
+            sections.push(McProtoSelf::read(&mut reader, ())?);
+        }
+        Ok(sections)
+    }
+}
+
+/// The height of the highest block in each column of a chunk, which the
+/// client uses for rain and sky light.
+#[derive(Clone, PartialEq, Debug, Eq, Hash)]
+pub struct Heightmap(pub Vec<u32>);
+
+impl Heightmap {
+    /// The number of columns in a chunk.
+    pub const COLUMNS: usize = 16 * 16;
+
+    /// A heightmap where every column is `height`.
+    #[must_use]
+    pub fn flat(height: u32) -> Self {
+        Self(vec![height; Self::COLUMNS])
+    }
+
+    /// The bits per height in a world `world_height` blocks tall, which has
+    /// to fit every height from zero to the top.
+    fn bits(world_height: u32) -> u8 {
+        PalettedContainer::direct_bits(world_height as usize + 1)
+    }
+
+    /// The heights packed into longs, for a world `world_height` blocks tall.
+    /// Each column's height is the number of blocks from the bottom of the
+    /// world to the top of its highest block, indexed by `z * 16 + x`.
+    #[must_use]
+    pub fn to_longs(&self, world_height: u32) -> Vec<i64> {
+        pack(Self::bits(world_height), self.0.iter().copied())
+            .into_iter()
+            .map(u64::cast_signed)
+            .collect()
+    }
+
+    /// Unpack heights from `longs`, for a world `world_height` blocks tall.
+    /// Returns `None` if there are the wrong number of longs.
+    #[must_use]
+    pub fn from_longs(longs: &[i64], world_height: u32) -> Option<Self> {
+        let bits = Self::bits(world_height);
+        if longs.len() != packed_len(bits, Self::COLUMNS) {
+            return None;
+        }
+        let data: Vec<u64> = longs.iter().map(|&long| long.cast_unsigned()).collect();
+        Some(Self(
+            (0..Self::COLUMNS).map(|i| unpack(bits, &data, i)).collect(),
+        ))
+    }
+}
+
+/// The heightmaps the client needs, sent as network NBT.
+#[derive(Clone, PartialEq, Debug, Eq, Hash)]
+pub struct Heightmaps {
+    /// The highest block that blocks motion or holds a fluid, for rain.
+    pub motion_blocking: Heightmap,
+    /// The highest block that isn't air.
+    pub world_surface: Heightmap,
+}
+
+impl Heightmaps {
+    /// The heightmaps as a compound of long arrays, for a world
+    /// `world_height` blocks tall.
+    #[must_use]
+    pub fn to_nbt(&self, world_height: u32) -> Nbt {
+        Nbt::Some(BaseNbt::new(
+            "",
+            NbtCompound::from_values(vec![
+                (
+                    "MOTION_BLOCKING".into(),
+                    NbtTag::LongArray(self.motion_blocking.to_longs(world_height)),
+                ),
+                (
+                    "WORLD_SURFACE".into(),
+                    NbtTag::LongArray(self.world_surface.to_longs(world_height)),
+                ),
+            ]),
+        ))
+    }
+
+    /// Read the heightmaps from NBT, for a world `world_height` blocks tall.
+    /// Returns `None` if either is missing or the wrong size.
+    #[must_use]
+    pub fn from_nbt(nbt: &Nbt, world_height: u32) -> Option<Self> {
+        let Nbt::Some(nbt) = nbt else {
+            return None;
+        };
+        let heightmap = |name| Heightmap::from_longs(nbt.long_array(name)?, world_height);
+        Some(Self {
+            motion_blocking: heightmap("MOTION_BLOCKING")?,
+            world_surface: heightmap("WORLD_SURFACE")?,
+        })
+    }
+}
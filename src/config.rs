@@ -0,0 +1,164 @@
+//! Server configuration, loaded from a vanilla-style `server.properties` file.
+
+use std::{
+    fs,
+    io::ErrorKind,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+
+/// The server configuration.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Config {
+    /// The IP address to bind to (`server-ip`). Blank binds every interface.
+    pub ip: IpAddr,
+    /// The port to bind to (`server-port`).
+    pub port: u16,
+    /// The message shown in the server list (`motd`). May contain `§`
+    /// formatting codes and newlines, or be a JSON text component.
+    pub motd: String,
+    /// The most players that can be online at once (`max-players`).
+    pub max_players: u32,
+    /// The 64x64 PNG shown in the server list (`server-icon`).
+    pub icon: PathBuf,
+    /// Whether to encrypt connections and authenticate players with a session
+    /// server (`online-mode`). Needs a [`SessionService`](crate::auth::SessionService).
+    pub online_mode: bool,
+    /// The smallest packet to compress, in bytes, or `None` to disable
+    /// compression (`network-compression-threshold`, where `-1` disables it).
+    pub compression_threshold: Option<u32>,
+    /// How far around players chunks are sent, in chunks (`view-distance`).
+    pub view_distance: u32,
+    /// How far around players entities are ticked, in chunks
+    /// (`simulation-distance`).
+    pub simulation_distance: u32,
+    /// The directory the world is saved in (`level-name`).
+    pub level_name: PathBuf,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            ip: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            port: 25565,
+            motd: "A Minecraft Server".to_owned(),
+            max_players: 20,
+            icon: PathBuf::from("server-icon.png"),
+            online_mode: false,
+            compression_threshold: Some(256),
+            view_distance: 10,
+            simulation_distance: 10,
+            level_name: PathBuf::from("world"),
+        }
+    }
+}
+
+impl Config {
+    /// The address to bind the listener to.
+    #[must_use]
+    pub fn address(&self) -> SocketAddr {
+        SocketAddr::new(self.ip, self.port)
+    }
+
+    /// The path of the world's `level.dat`.
+    #[must_use]
+    pub fn level_path(&self) -> PathBuf {
+        self.level_name.join("level.dat")
+    }
+
+    /// The directory of the world's region files.
+    #[must_use]
+    pub fn region_dir(&self) -> PathBuf {
+        self.level_name.join("region")
+    }
+
+    /// Load the configuration from the file at `path`, falling back to the
+    /// defaults if it doesn't exist.
+    ///
+    /// # Errors
+    /// If the file exists but can't be read or parsed, return an error.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, anyhow::Error> {
+        let path = path.as_ref();
+        match fs::read_to_string(path) {
+            Ok(text) => Self::parse(&text).with_context(|| format!("parsing {}", path.display())),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e).with_context(|| format!("reading {}", path.display())),
+        }
+    }
+
+    /// Parse the configuration from the contents of a `server.properties` file.
+    /// Unknown keys are ignored and missing keys keep their defaults.
+    ///
+    /// # Errors
+    /// If a known key has a malformed value, return an error.
+    pub fn parse(text: &str) -> Result<Self, anyhow::Error> {
+        let mut config = Self::default();
+        for line in text.lines() {
+            let line = line.trim_start();
+            if line.is_empty() || line.starts_with('#') || line.starts_with('!') {
+                continue;
+            }
+            let Some((key, value)) = line.split_once(['=', ':']) else {
+                continue;
+            };
+            let (key, value) = (key.trim(), unescape(value.trim()));
+            match key {
+                "server-ip" if value.is_empty() => config.ip = Self::default().ip,
+                "server-ip" => config.ip = value.parse().context("bad server-ip")?,
+                "server-port" => config.port = value.parse().context("bad server-port")?,
+                "motd" => config.motd = value,
+                "max-players" => config.max_players = value.parse().context("bad max-players")?,
+                "server-icon" => config.icon = PathBuf::from(value),
+                "network-compression-threshold" => {
+                    let threshold: i32 =
+                        value.parse().context("bad network-compression-threshold")?;
+                    config.compression_threshold = u32::try_from(threshold).ok();
+                }
+                "online-mode" => config.online_mode = value.parse().context("bad online-mode")?,
+                "view-distance" => {
+                    config.view_distance = value.parse().context("bad view-distance")?;
+                }
+                "simulation-distance" => {
+                    config.simulation_distance =
+                        value.parse().context("bad simulation-distance")?;
+                }
+                "level-name" => config.level_name = PathBuf::from(value),
+                _ => {}
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// Resolve the backslash escapes of a `.properties` value, such as `\n` and
+/// `\u00A7`.
+fn unescape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('r') => out.push('\r'),
+            Some('f') => out.push('\u{0C}'),
+            Some('u') => {
+                let hex: String = chars.by_ref().take(4).collect();
+                if let Some(c) = u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                    out.push(c);
+                } else {
+                    out.push_str("\\u");
+                    out.push_str(&hex);
+                }
+            }
+            Some(c) => out.push(c),
+            None => {}
+        }
+    }
+    out
+}
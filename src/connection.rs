@@ -0,0 +1,592 @@
+//! A single client connection and its protocol state machine.
+
+use std::{
+    io::{ErrorKind, Read},
+    net::{SocketAddr, TcpStream},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, bail, ensure};
+
+use crate::{
+    McWriter, ProtocolError,
+    auth::{self, GameProfile},
+    crypto,
+    game::Inbound,
+    io::{CipherReader, CipherWriter, IoReader, IoWriter},
+    keep_alive::{Action, KeepAlive},
+    packet::{Compression, Packet},
+    packets::{
+        Clientbound, GAME_VERSION, PacketSet, Registry, Serverbound, State,
+        configuration::{
+            ClientboundKnownPacks, ConfigurationDisconnect, ConfigurationKeepAlive,
+            FinishConfiguration,
+        },
+        handshake::Intent,
+        login::{self, EncryptionRequest, LoginDisconnect, LoginSuccess, SetCompression},
+        play::{
+            ChangeDifficulty, ChunkBatchFinished, ChunkBatchStart, GameEvent, GameMode, LoginPlay,
+            PlayDisconnect, PlayKeepAlive, SetCenterChunk, SetDefaultSpawnPosition,
+            SynchronizePlayerPosition,
+        },
+        status::{PingRequest, PongResponse, StatusResponse},
+    },
+    registries::Registries,
+    server::{Player, Shared},
+    status::{self, LegacyPing},
+    text::TextComponent,
+    types::Uuid,
+};
+
+/// A connection to a single client.
+pub struct Connection {
+    /// The reading half of the socket.
+    reader: CipherReader<IoReader<TcpStream>>,
+    /// The writing half of the socket.
+    writer: CipherWriter<IoWriter<TcpStream>>,
+    /// The address of the client.
+    addr: SocketAddr,
+    /// The current protocol state.
+    state: State,
+    /// The protocol version the client sent in its handshake.
+    protocol_version: u32,
+    /// The packet compression in effect, once enabled.
+    compression: Option<Compression>,
+    /// The packet IDs used on this connection.
+    registry: &'static Registry,
+    /// State shared with the rest of the server.
+    shared: Arc<Shared>,
+    /// The UUID of the player, once they've logged in.
+    player: Option<Uuid>,
+    /// The ID of the next teleport.
+    next_teleport_id: u32,
+    /// The ID of the last teleport, until the client confirms it.
+    awaiting_teleport: Option<u32>,
+    /// Keep-alives, from the configuration state on.
+    keep_alive: KeepAlive,
+}
+
+impl Connection {
+    /// How long to wait for the rest of a pre-1.7 server list ping before
+    /// assuming the client has sent all of it.
+    const LEGACY_PING_TIMEOUT: Duration = Duration::from_millis(100);
+
+    /// Wrap an accepted socket.
+    ///
+    /// # Errors
+    /// If the socket can't be cloned or has no peer address, return an error.
+    pub fn new(stream: TcpStream, shared: Arc<Shared>) -> Result<Self, anyhow::Error> {
+        let addr = stream.peer_addr()?;
+        let writer = CipherWriter::new(IoWriter(stream.try_clone()?));
+        Ok(Self {
+            reader: CipherReader::new(IoReader(stream)),
+            writer,
+            addr,
+            state: State::Handshake,
+            protocol_version: 0,
+            compression: None,
+            registry: Registry::latest(),
+            shared,
+            player: None,
+            next_teleport_id: 0,
+            awaiting_teleport: None,
+            keep_alive: KeepAlive::new(Instant::now()),
+        })
+    }
+
+    /// The address of the client.
+    #[must_use]
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// The current protocol state.
+    #[must_use]
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    /// Run the connection until the client disconnects or the server closes it.
+    ///
+    /// # Errors
+    /// If the client sends something invalid or the socket fails, return an error.
+    /// Clients that send malformed packets are told why before being dropped,
+    /// where the state allows it.
+    pub fn run(mut self) -> Result<(), anyhow::Error> {
+        loop {
+            let next = match self.state {
+                State::Handshake => self.handshake(),
+                State::Status => self.status(),
+                State::Login => self.login(),
+                State::Configuration => self.configuration(),
+                State::Play => self.play(),
+            };
+            match next {
+                Ok(Some(state)) => self.state = state,
+                Ok(None) => return Ok(()),
+                Err(error) => {
+                    if error
+                        .downcast_ref::<ProtocolError>()
+                        .is_some_and(ProtocolError::is_malformed)
+                    {
+                        // The client may already be gone, and the original
+                        // error is the one worth reporting.
+                        let _ =
+                            self.disconnect(&TextComponent::translatable("disconnect.packetError"));
+                    }
+                    return Err(error);
+                }
+            }
+        }
+    }
+
+    /// Read the next packet from the client.
+    fn receive(&mut self) -> Result<Serverbound, ProtocolError> {
+        let packet = Packet::read_with(&mut self.reader, self.compression)?;
+        self.registry.decode(self.state, packet)
+    }
+
+    /// Read the next packet from the client, sending keep-alives while waiting
+    /// for it and handling the client's answers.
+    ///
+    /// # Errors
+    /// If the client doesn't answer a keep-alive in time or answers with the
+    /// wrong ID, disconnect it and return an error.
+    fn receive_alive(&mut self) -> Result<Serverbound, anyhow::Error> {
+        loop {
+            match self.keep_alive.poll(Instant::now()) {
+                Action::Send(id) => self.send(match self.state {
+                    State::Configuration => {
+                        Clientbound::ConfigurationKeepAlive(ConfigurationKeepAlive { id })
+                    }
+                    _ => Clientbound::PlayKeepAlive(PlayKeepAlive { id }),
+                })?,
+                Action::TimedOut => {
+                    self.disconnect(&TextComponent::translatable("disconnect.timeout"))?;
+                    bail!("didn't answer a keep-alive in time");
+                }
+                Action::Wait => {}
+            }
+
+            // Wait for the start of a packet until a keep-alive is due, then
+            // give the client as long as it has to answer one to send the rest.
+            let stream = &mut self.reader.get_mut().0;
+            let wait = self
+                .keep_alive
+                .deadline()
+                .saturating_duration_since(Instant::now());
+            stream.set_read_timeout(Some(wait.max(Duration::from_millis(1))))?;
+            let ready = match stream.peek(&mut [0u8]) {
+                Ok(_) => true,
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => false,
+                Err(e) => return Err(e.into()),
+            };
+            stream.set_read_timeout(Some(KeepAlive::TIMEOUT))?;
+            if !ready {
+                continue;
+            }
+
+            let id = match self.receive()? {
+                Serverbound::ConfigurationKeepAlive(ConfigurationKeepAlive { id })
+                | Serverbound::PlayKeepAlive(PlayKeepAlive { id }) => id,
+                packet => return Ok(packet),
+            };
+            if !self.keep_alive.answer(id, Instant::now()) {
+                self.disconnect(&TextComponent::translatable("disconnect.timeout"))?;
+                bail!("answered a keep-alive with the wrong ID {id}");
+            }
+            if let (Some(player), Some(latency)) = (self.player, self.keep_alive.latency()) {
+                self.shared.set_latency(player, latency);
+            }
+        }
+    }
+
+    /// Send a packet to the client.
+    fn send(&mut self, packet: Clientbound) -> Result<(), anyhow::Error> {
+        let mut bytes = Vec::new();
+        self.registry
+            .encode(self.state, packet)?
+            .write_with(&mut bytes, self.compression)?;
+        Ok(self.writer.write(&bytes)?)
+    }
+
+    /// Disconnect the client with `reason`, a text component.
+    ///
+    /// # Errors
+    /// If sending the reason fails, return an error.
+    fn disconnect(&mut self, reason: &TextComponent) -> Result<Option<State>, anyhow::Error> {
+        match self.state {
+            State::Login => self.send(Clientbound::LoginDisconnect(LoginDisconnect {
+                reason: reason.to_json().to_string(),
+            }))?,
+            State::Configuration => {
+                self.send(Clientbound::ConfigurationDisconnect(
+                    ConfigurationDisconnect {
+                        reason: reason.clone(),
+                    },
+                ))?;
+            }
+            State::Play => self.send(Clientbound::PlayDisconnect(PlayDisconnect {
+                reason: reason.clone(),
+            }))?,
+            _ => bail!("can't disconnect in the {:?} state", self.state),
+        }
+        Ok(None)
+    }
+
+    /// Read the handshake and return the state the client asked for.
+    fn handshake(&mut self) -> Result<Option<State>, anyhow::Error> {
+        let mut first = [0u8];
+        if self.reader.get_mut().0.peek(&mut first)? == 1 && first[0] == status::LEGACY_PING {
+            return self.legacy_ping();
+        }
+        let Serverbound::Handshake(handshake) = self.receive()? else {
+            bail!("expected a handshake");
+        };
+        self.protocol_version = handshake.protocol_version;
+        Ok(Some(match handshake.intent {
+            Intent::Status => State::Status,
+            Intent::Login | Intent::Transfer => State::Login,
+        }))
+    }
+
+    /// Answer a pre-1.7 server list ping, then close the connection.
+    fn legacy_ping(&mut self) -> Result<Option<State>, anyhow::Error> {
+        let stream = &mut self.reader.get_mut().0;
+        stream.read_exact(&mut [0u8])?;
+        stream.set_read_timeout(Some(Self::LEGACY_PING_TIMEOUT))?;
+
+        let ping = match read_byte_or_timeout(stream)? {
+            None => LegacyPing::Beta,
+            Some(0x01) => {
+                if read_byte_or_timeout(stream)? == Some(0xFA) {
+                    // The `MC|PingHost` plugin message: its channel name, as a
+                    // UTF-16 string prefixed with its length in code units, then
+                    // its data, prefixed with its length in bytes.
+                    let mut len = [0u8; 2];
+                    stream.read_exact(&mut len)?;
+                    let mut channel = vec![0u8; usize::from(u16::from_be_bytes(len)) * 2];
+                    stream.read_exact(&mut channel)?;
+                    stream.read_exact(&mut len)?;
+                    let mut data = vec![0u8; u16::from_be_bytes(len).into()];
+                    stream.read_exact(&mut data)?;
+                }
+                LegacyPing::Extended
+            }
+            Some(byte) => bail!("bad legacy ping byte {byte:#04x}"),
+        };
+        self.writer
+            .write(&status::legacy_response(&self.shared, ping))?;
+        Ok(None)
+    }
+
+    /// Answer status requests, closing the connection after a ping.
+    fn status(&mut self) -> Result<Option<State>, anyhow::Error> {
+        loop {
+            match self.receive()? {
+                Serverbound::StatusRequest(_) => {
+                    let json = status::response(&self.shared).to_string();
+                    self.send(Clientbound::StatusResponse(StatusResponse { json }))?;
+                }
+                Serverbound::PingRequest(PingRequest { payload }) => {
+                    self.send(Clientbound::PongResponse(PongResponse { payload }))?;
+                    return Ok(None);
+                }
+                packet => bail!("unexpected {} packet in the status state", packet.name()),
+            }
+        }
+    }
+
+    /// Log the player in, authenticating them first in online mode.
+    fn login(&mut self) -> Result<Option<State>, anyhow::Error> {
+        let Serverbound::LoginStart(start) = self.receive()? else {
+            bail!("expected login start");
+        };
+        let Some(registry) = Registry::for_version(self.protocol_version) else {
+            return self.disconnect(
+                &TextComponent::translatable("multiplayer.disconnect.incompatible")
+                    .with_arg(GAME_VERSION),
+            );
+        };
+        self.registry = registry;
+        if !login::is_valid_username(&start.name) {
+            return self.disconnect(&TextComponent::text("Invalid username"));
+        }
+
+        let (uuid, name, properties) = if self.shared.config.online_mode {
+            match self.authenticate(&start.name)? {
+                Ok(profile) => (profile.id, profile.name, profile.properties),
+                Err(reason) => {
+                    return self.disconnect(&TextComponent::translatable(reason));
+                }
+            }
+        } else {
+            (Uuid::offline(&start.name), start.name, Vec::new())
+        };
+        {
+            let mut players = self.shared.players();
+            let reason = if players.len() >= self.shared.config.max_players as usize {
+                Some("multiplayer.disconnect.server_full")
+            } else if players.iter().any(|player| player.id == uuid) {
+                Some("multiplayer.disconnect.name_taken")
+            } else {
+                None
+            };
+            if let Some(reason) = reason {
+                drop(players);
+                return self.disconnect(&TextComponent::translatable(reason));
+            }
+            players.push(Player {
+                name: name.clone(),
+                id: uuid,
+                latency: Duration::ZERO,
+            });
+        }
+        self.player = Some(uuid);
+
+        if let Some(threshold) = self.shared.config.compression_threshold {
+            self.send(Clientbound::SetCompression(SetCompression {
+                threshold: i32::try_from(threshold)?,
+            }))?;
+            self.compression = Some(Compression::new(threshold as usize));
+        }
+
+        self.send(Clientbound::LoginSuccess(LoginSuccess {
+            uuid,
+            username: name,
+            properties,
+            strict_error_handling: false,
+        }))?;
+        let Serverbound::LoginAcknowledged(_) = self.receive()? else {
+            bail!("expected login acknowledged");
+        };
+        Ok(Some(State::Configuration))
+    }
+
+    /// Send the client the registries, tags and feature flags it needs
+    /// before it can enter the world.
+    ///
+    /// Registry entries are sent without their data if the client already
+    /// has the vanilla core pack they come from.
+    fn configuration(&mut self) -> Result<Option<State>, anyhow::Error> {
+        self.keep_alive = KeepAlive::new(Instant::now());
+        let registries = Registries::vanilla();
+        self.send(Clientbound::FeatureFlags(registries.feature_flags()))?;
+        self.send(Clientbound::ClientboundKnownPacks(ClientboundKnownPacks {
+            packs: vec![Registries::core_pack()],
+        }))?;
+        let known = loop {
+            match self.receive_alive()? {
+                Serverbound::ServerboundKnownPacks(known) => break known.packs,
+                // Settings and plugin messages don't change what's sent.
+                Serverbound::ClientInformation(_) | Serverbound::Unknown(_) => {}
+                packet => bail!(
+                    "unexpected {} packet in the configuration state",
+                    packet.name()
+                ),
+            }
+        };
+
+        let with_data = !known.contains(&Registries::core_pack());
+        for data in registries.registry_data(with_data) {
+            self.send(Clientbound::RegistryData(data))?;
+        }
+        self.send(Clientbound::UpdateTags(registries.update_tags()))?;
+        self.send(Clientbound::FinishConfiguration(FinishConfiguration))?;
+        loop {
+            match self.receive_alive()? {
+                Serverbound::AcknowledgeFinishConfiguration(_) => return Ok(Some(State::Play)),
+                Serverbound::ClientInformation(_) | Serverbound::Unknown(_) => {}
+                packet => bail!(
+                    "unexpected {} packet in the configuration state",
+                    packet.name()
+                ),
+            }
+        }
+    }
+
+    /// Enable encryption, then ask the session server whether the player is
+    /// who they claim to be. Returns their profile, or the translation key of
+    /// the reason to disconnect them with.
+    fn authenticate(
+        &mut self,
+        name: &str,
+    ) -> Result<Result<GameProfile, &'static str>, anyhow::Error> {
+        let shared = Arc::clone(&self.shared);
+        let (Some(key), Some(session)) = (&shared.key, &shared.session) else {
+            bail!("online mode isn't set up");
+        };
+        let public_key = key.public_key().to_der();
+        let mut verify_token = [0u8; 4];
+        crypto::random_bytes(&mut verify_token)?;
+        self.send(Clientbound::EncryptionRequest(EncryptionRequest {
+            server_id: String::new(),
+            public_key: public_key.clone(),
+            verify_token: verify_token.to_vec(),
+            should_authenticate: true,
+        }))?;
+
+        let Serverbound::EncryptionResponse(response) = self.receive()? else {
+            bail!("expected encryption response");
+        };
+        ensure!(
+            key.decrypt(&response.verify_token)? == verify_token,
+            "verify token doesn't match"
+        );
+        let secret: [u8; 16] = key
+            .decrypt(&response.shared_secret)?
+            .try_into()
+            .map_err(|_| anyhow!("shared secret isn't 16 bytes"))?;
+        self.reader.enable(&secret);
+        self.writer.enable(&secret);
+
+        let hash = auth::server_hash("", &secret, &public_key);
+        Ok(match session.has_joined(name, &hash) {
+            Ok(Some(profile)) => Ok(profile),
+            Ok(None) => Err("multiplayer.disconnect.unverified_username"),
+            Err(e) => {
+                eprintln!("{}: couldn't reach the session server: {e:#}", self.addr);
+                Err("multiplayer.disconnect.authservers_down")
+            }
+        })
+    }
+
+    /// Spawn the player, then handle their packets until they leave.
+    fn play(&mut self) -> Result<Option<State>, anyhow::Error> {
+        self.spawn()?;
+        loop {
+            match self.receive_alive() {
+                Ok(Serverbound::ConfirmTeleportation(confirm)) => {
+                    if self.awaiting_teleport == Some(confirm.teleport_id) {
+                        self.awaiting_teleport = None;
+                    }
+                }
+                // Until the client confirms the last teleport, its movement
+                // is from before it and would undo it.
+                Ok(packet) if packet.is_movement() && self.awaiting_teleport.is_some() => {}
+                Ok(packet) => {
+                    if let Some(player) = self.player {
+                        self.shared.queue(Inbound { player, packet });
+                    }
+                }
+                Err(e) if is_closed(&e) => return Ok(None),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Put the player in the world at its spawn point, and send the chunks
+    /// around them.
+    fn spawn(&mut self) -> Result<(), anyhow::Error> {
+        let shared = Arc::clone(&self.shared);
+        let (config, world, level) = (&shared.config, &shared.world, &shared.level);
+        self.send(Clientbound::LoginPlay(LoginPlay {
+            entity_id: shared.next_entity_id(),
+            is_hardcore: false,
+            dimension_names: vec![world.dimension.clone()],
+            max_players: config.max_players,
+            view_distance: config.view_distance,
+            simulation_distance: config.simulation_distance,
+            reduced_debug_info: level.game_rule("reducedDebugInfo"),
+            enable_respawn_screen: !level.game_rule("doImmediateRespawn"),
+            do_limited_crafting: level.game_rule("doLimitedCrafting"),
+            dimension_type: world.dimension_type,
+            dimension_name: world.dimension.clone(),
+            hashed_seed: level.hashed_seed(),
+            game_mode: GameMode::Survival,
+            previous_game_mode: -1,
+            is_debug: false,
+            is_flat: true,
+            death_location: None,
+            portal_cooldown: 0,
+            enforces_secure_chat: false,
+        }))?;
+        self.send(Clientbound::ChangeDifficulty(ChangeDifficulty {
+            difficulty: level.difficulty,
+            locked: level.difficulty_locked,
+        }))?;
+        self.send(Clientbound::SetDefaultSpawnPosition(
+            SetDefaultSpawnPosition {
+                location: world.spawn,
+                angle: level.spawn_angle,
+            },
+        ))?;
+        let spawn = world.spawn;
+        self.teleport(
+            f64::from(spawn.x) + 0.5,
+            f64::from(spawn.y + 1),
+            f64::from(spawn.z) + 0.5,
+        )?;
+        self.send(Clientbound::GameEvent(GameEvent {
+            event: GameEvent::START_WAITING_FOR_CHUNKS,
+            value: 0.0,
+        }))?;
+
+        let (center_x, center_z) = (spawn.x >> 4, spawn.z >> 4);
+        self.send(Clientbound::SetCenterChunk(SetCenterChunk {
+            x: center_x,
+            z: center_z,
+        }))?;
+        // Nearest first, so the client can show the world sooner.
+        let radius = config.view_distance.cast_signed();
+        let mut chunks: Vec<(i32, i32)> = (-radius..=radius)
+            .flat_map(|x| (-radius..=radius).map(move |z| (x, z)))
+            .collect();
+        chunks.sort_by_key(|&(x, z)| x * x + z * z);
+        self.send(Clientbound::ChunkBatchStart(ChunkBatchStart))?;
+        for &(x, z) in &chunks {
+            let chunk = shared.chunk(center_x + x, center_z + z);
+            self.send(Clientbound::ChunkData(chunk))?;
+        }
+        self.send(Clientbound::ChunkBatchFinished(ChunkBatchFinished {
+            batch_size: chunks.len() as u32,
+        }))?;
+        Ok(())
+    }
+
+    /// Move the player to `x`, `y`, `z`, facing south, and wait for the client
+    /// to confirm it.
+    fn teleport(&mut self, x: f64, y: f64, z: f64) -> Result<(), anyhow::Error> {
+        let teleport_id = self.next_teleport_id;
+        self.next_teleport_id = self.next_teleport_id.wrapping_add(1);
+        self.awaiting_teleport = Some(teleport_id);
+        self.send(Clientbound::SynchronizePlayerPosition(
+            SynchronizePlayerPosition {
+                x,
+                y,
+                z,
+                yaw: 0.0,
+                pitch: 0.0,
+                flags: 0,
+                teleport_id,
+            },
+        ))
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        if let Some(uuid) = self.player {
+            self.shared.players().retain(|player| player.id != uuid);
+        }
+    }
+}
+
+/// Read a single byte, returning `None` if the read times out.
+fn read_byte_or_timeout(stream: &mut TcpStream) -> Result<Option<u8>, anyhow::Error> {
+    let mut byte = [0u8];
+    match stream.read(&mut byte) {
+        Ok(0) => bail!("connection closed"),
+        Ok(_) => Ok(Some(byte[0])),
+        Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Whether `error` is the client closing the connection.
+fn is_closed(error: &anyhow::Error) -> bool {
+    error
+        .downcast_ref::<ProtocolError>()
+        .is_some_and(ProtocolError::is_closed)
+}
@@ -0,0 +1,19 @@
+//! The cryptographic primitives the protocol needs.
+
+use aes::Aes128;
+
+pub mod rsa;
+
+/// AES-128 in 8-bit cipher feedback mode, encrypting. The protocol uses the
+/// shared secret as both the key and the initial vector.
+pub type Encryptor = cfb8::Encryptor<Aes128>;
+/// AES-128 in 8-bit cipher feedback mode, decrypting.
+pub type Decryptor = cfb8::Decryptor<Aes128>;
+
+/// Fill `bytes` from the operating system's random number generator.
+///
+/// # Errors
+/// If the operating system has no randomness to give.
+pub fn random_bytes(bytes: &mut [u8]) -> Result<(), anyhow::Error> {
+    Ok(getrandom::fill(bytes)?)
+}
@@ -0,0 +1,88 @@
+//! RSA with PKCS#1 v1.5 padding, and the X.509 `SubjectPublicKeyInfo`
+//! encoding the protocol sends public keys in.
+
+use rsa::{
+    Pkcs1v15Encrypt,
+    pkcs8::{DecodePublicKey, EncodePublicKey},
+    rand_core::OsRng,
+    traits::PublicKeyParts,
+};
+
+/// An RSA public key.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct RsaPublicKey(rsa::RsaPublicKey);
+
+impl RsaPublicKey {
+    /// The modulus length in bytes.
+    #[must_use]
+    pub fn size(&self) -> usize {
+        self.0.size()
+    }
+
+    /// Encode as a DER `SubjectPublicKeyInfo`.
+    ///
+    /// # Panics
+    /// Never: an RSA key always has an encoding.
+    #[must_use]
+    pub fn to_der(&self) -> Vec<u8> {
+        self.0
+            .to_public_key_der()
+            .expect("RSA public keys can be encoded")
+            .into_vec()
+    }
+
+    /// Parse a DER `SubjectPublicKeyInfo`.
+    ///
+    /// # Errors
+    /// If it isn't a well-formed RSA public key.
+    pub fn from_der(der: &[u8]) -> Result<Self, anyhow::Error> {
+        Ok(Self(rsa::RsaPublicKey::from_public_key_der(der)?))
+    }
+
+    /// Encrypt `message` with PKCS#1 v1.5 padding.
+    ///
+    /// # Errors
+    /// If the message is too long for the key, or no randomness is available.
+    pub fn encrypt(&self, message: &[u8]) -> Result<Vec<u8>, anyhow::Error> {
+        Ok(self.0.encrypt(&mut OsRng, Pkcs1v15Encrypt, message)?)
+    }
+}
+
+/// An RSA private key.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct RsaPrivateKey {
+    /// The key itself.
+    key: rsa::RsaPrivateKey,
+    /// The public half.
+    public: RsaPublicKey,
+}
+
+impl RsaPrivateKey {
+    /// Generate a new key with a `bits`-bit modulus.
+    ///
+    /// # Errors
+    /// If no randomness is available, or `bits` is too small to be useful.
+    pub fn generate(bits: usize) -> Result<Self, anyhow::Error> {
+        let key = rsa::RsaPrivateKey::new(&mut OsRng, bits)?;
+        Ok(Self {
+            public: RsaPublicKey(key.to_public_key()),
+            key,
+        })
+    }
+
+    /// The public half of the key.
+    #[must_use]
+    pub fn public_key(&self) -> &RsaPublicKey {
+        &self.public
+    }
+
+    /// Decrypt a PKCS#1 v1.5 padded `ciphertext`. The padding is checked in
+    /// constant time and every way it can be bad gives the same error, so a
+    /// client learns nothing about it.
+    ///
+    /// # Errors
+    /// If the ciphertext is the wrong size or its padding is invalid.
+    pub fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>, anyhow::Error> {
+        Ok(self.key.decrypt(Pkcs1v15Encrypt, ciphertext)?)
+    }
+}
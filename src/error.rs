@@ -0,0 +1,176 @@
+//! The error type for encoding and decoding the protocol.
+
+use core::{
+    fmt::{self, Display, Formatter},
+    str::Utf8Error,
+};
+use std::io;
+
+use crate::packets::{Direction, State};
+
+/// Formats an optional byte offset as a suffix for error messages.
+struct At(Option<usize>);
+
+impl Display for At {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(offset) => write!(f, " at byte {offset}"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// An error encoding or decoding the protocol.
+///
+/// Every variant but [`Io`](Self::Io) and
+/// [`UnregisteredPacket`](Self::UnregisteredPacket) carries the byte offset where the bad
+/// value started, when the reader knows its position (see
+/// [`McReader::position`](crate::McReader::position)).
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum ProtocolError {
+    /// The input ended in the middle of a value. Readers over a stream, which
+    /// don't know their position, report the stream ending this way too,
+    /// with no offset.
+    #[error("unexpected end of input{}", At(*offset))]
+    UnexpectedEof {
+        /// Where the read that ran out started.
+        offset: Option<usize>,
+    },
+    /// A `VarInt` or `VarLong` had more bytes than its type allows.
+    #[error("VarNum is longer than {max} bytes{}", At(*offset))]
+    VarIntTooLong {
+        /// Where the number started.
+        offset: Option<usize>,
+        /// The most bytes the type allows.
+        max: usize,
+    },
+    /// A string, array or other length-prefixed value was over its limit.
+    #[error("{what} of {len} is over the limit of {max}{}", At(*offset))]
+    StringTooLong {
+        /// Where the value started.
+        offset: Option<usize>,
+        /// What was too long, such as `"string"` or `"byte array"`.
+        what: &'static str,
+        /// Its length, in the units of the limit.
+        len: usize,
+        /// The limit.
+        max: usize,
+    },
+    /// A packet was longer than the protocol allows, either as framed or
+    /// once decompressed.
+    #[error("packet of {len} bytes is over the limit of {max}{}", At(*offset))]
+    PacketTooLarge {
+        /// Where the packet started.
+        offset: Option<usize>,
+        /// Its length in bytes.
+        len: usize,
+        /// The limit.
+        max: usize,
+    },
+    /// A string wasn't valid UTF-8.
+    #[error("invalid UTF-8{}", At(*offset))]
+    InvalidUtf8 {
+        /// Where the string started.
+        offset: Option<usize>,
+        /// What was wrong with it.
+        #[source]
+        source: Utf8Error,
+    },
+    /// An enum tag, boolean or other discriminant had an unknown value.
+    #[error("invalid {name} value {value}{}", At(*offset))]
+    InvalidEnum {
+        /// Where the value started.
+        offset: Option<usize>,
+        /// The name of the type.
+        name: &'static str,
+        /// The value that was read.
+        value: i64,
+    },
+    /// NBT was malformed or over its limits.
+    #[error("invalid NBT{}: {message}", At(*offset))]
+    NbtError {
+        /// Where the NBT started.
+        offset: Option<usize>,
+        /// What was wrong with it.
+        message: String,
+    },
+    /// A value was malformed in some other way, such as an identifier with
+    /// invalid characters or bytes left over after a packet.
+    #[error("{message}{}", At(*offset))]
+    Invalid {
+        /// Where the value started.
+        offset: Option<usize>,
+        /// What was wrong with it.
+        message: String,
+    },
+    /// A packet was encoded for a state it isn't registered in.
+    #[error("{direction:?} packet {name} isn't registered in the {state:?} state")]
+    UnregisteredPacket {
+        /// The direction of the packet.
+        direction: Direction,
+        /// The state it was encoded for.
+        state: State,
+        /// The name of the packet.
+        name: &'static str,
+    },
+    /// The underlying reader or writer failed.
+    #[error(transparent)]
+    Io(io::Error),
+}
+
+impl From<io::Error> for ProtocolError {
+    /// Wrap an I/O error, except for running out of input, which is
+    /// [`UnexpectedEof`](Self::UnexpectedEof) whatever the reader.
+    fn from(error: io::Error) -> Self {
+        if error.kind() == io::ErrorKind::UnexpectedEof {
+            Self::UnexpectedEof { offset: None }
+        } else {
+            Self::Io(error)
+        }
+    }
+}
+
+impl ProtocolError {
+    /// A [`ProtocolError::Invalid`] with `message`.
+    pub fn invalid(offset: Option<usize>, message: impl Into<String>) -> Self {
+        Self::Invalid {
+            offset,
+            message: message.into(),
+        }
+    }
+
+    /// The byte offset where the bad value started, if known.
+    #[must_use]
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            Self::UnexpectedEof { offset }
+            | Self::VarIntTooLong { offset, .. }
+            | Self::StringTooLong { offset, .. }
+            | Self::PacketTooLarge { offset, .. }
+            | Self::InvalidUtf8 { offset, .. }
+            | Self::InvalidEnum { offset, .. }
+            | Self::NbtError { offset, .. }
+            | Self::Invalid { offset, .. } => *offset,
+            Self::UnregisteredPacket { .. } | Self::Io(_) => None,
+        }
+    }
+
+    /// Whether the peer sent something malformed, as opposed to the
+    /// connection failing or closing, or the server sending a packet it
+    /// can't.
+    #[must_use]
+    pub fn is_malformed(&self) -> bool {
+        !matches!(
+            self,
+            Self::UnregisteredPacket { .. } | Self::Io(_) | Self::UnexpectedEof { offset: None }
+        )
+    }
+
+    /// Whether this is a stream ending, as when the peer closes the
+    /// connection.
+    #[must_use]
+    pub fn is_closed(&self) -> bool {
+        matches!(self, Self::UnexpectedEof { offset: None })
+    }
+}
@@ -0,0 +1,132 @@
+//! Game state, which only the tick thread changes.
+
+use std::collections::HashMap;
+
+use crate::{
+    level::LevelData,
+    packets::{
+        Serverbound,
+        play::{SetPlayerPosition, SetPlayerPositionAndRotation},
+    },
+    server::Shared,
+    tick::TickLoop,
+    types::Uuid,
+};
+
+/// A packet from a player in game, queued by their connection for the tick
+/// thread to apply.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Inbound {
+    /// The UUID of the player that sent it.
+    pub player: Uuid,
+    /// The packet.
+    pub packet: Serverbound,
+}
+
+/// Everything in the game that changes from tick to tick.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Game {
+    /// Ticks since the world was created.
+    age: u64,
+    /// The time of day in ticks, which is 24000 a day.
+    day_time: u64,
+    /// How many chunks per tick each player would like, once they've said.
+    chunks_per_tick: HashMap<Uuid, f32>,
+    /// The chunk each player is in, once they've moved.
+    chunks: HashMap<Uuid, (i32, i32)>,
+}
+
+impl Game {
+    /// A new world at dawn of the first day.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The world saved in `level`, at the time it was saved.
+    #[must_use]
+    pub fn from_level(level: &LevelData) -> Self {
+        Self {
+            age: u64::try_from(level.time).unwrap_or_default(),
+            day_time: u64::try_from(level.day_time).unwrap_or_default(),
+            ..Self::default()
+        }
+    }
+
+    /// Record the current time in `level`, ready to save it.
+    pub fn write_level(&self, level: &mut LevelData) {
+        level.time = i64::try_from(self.age).unwrap_or(i64::MAX);
+        level.day_time = i64::try_from(self.day_time).unwrap_or(i64::MAX);
+    }
+
+    /// Run one tick: apply the packets queued since the last one, then advance
+    /// time.
+    pub fn tick(&mut self, shared: &Shared) {
+        for inbound in shared.take_inbound() {
+            self.handle(inbound);
+        }
+        self.age += 1;
+        self.day_time += 1;
+        // Forget players that have left, and chunks no one can see, once a
+        // second.
+        if self.age.is_multiple_of(u64::from(TickLoop::TPS)) {
+            let players = shared.players();
+            self.chunks_per_tick
+                .retain(|id, _| players.iter().any(|player| player.id == *id));
+            self.chunks
+                .retain(|id, _| players.iter().any(|player| player.id == *id));
+            let spawn = (shared.world.spawn.x >> 4, shared.world.spawn.z >> 4);
+            let chunks: Vec<_> = players
+                .iter()
+                .map(|player| self.chunk(player.id).unwrap_or(spawn))
+                .collect();
+            drop(players);
+            shared.unload_chunks(&chunks);
+        }
+    }
+
+    /// Apply a packet from a player.
+    fn handle(&mut self, Inbound { player, packet }: Inbound) {
+        let (x, z) = match packet {
+            Serverbound::ChunkBatchReceived(received) => {
+                self.chunks_per_tick
+                    .insert(player, received.chunks_per_tick);
+                return;
+            }
+            Serverbound::SetPlayerPosition(SetPlayerPosition { x, z, .. })
+            | Serverbound::SetPlayerPositionAndRotation(SetPlayerPositionAndRotation {
+                x,
+                z,
+                ..
+            }) => (x, z),
+            _ => return,
+        };
+        self.chunks
+            .insert(player, ((x.floor() as i32) >> 4, (z.floor() as i32) >> 4));
+    }
+
+    /// Ticks since the world was created.
+    #[must_use]
+    pub fn age(&self) -> u64 {
+        self.age
+    }
+
+    /// The time of day in ticks, which is 24000 a day.
+    #[must_use]
+    pub fn day_time(&self) -> u64 {
+        self.day_time
+    }
+
+    /// How many chunks per tick the player with UUID `id` would like, if
+    /// they've said.
+    #[must_use]
+    pub fn chunks_per_tick(&self, id: Uuid) -> Option<f32> {
+        self.chunks_per_tick.get(&id).copied()
+    }
+
+    /// The chunk the player with UUID `id` is in, if they've moved.
+    #[must_use]
+    pub fn chunk(&self, id: Uuid) -> Option<(i32, i32)> {
+        self.chunks.get(&id).copied()
+    }
+}
@@ -0,0 +1,115 @@
+//! Conversion between JSON and NBT, for the parts of the protocol that were
+//! JSON before they were NBT, such as text components and registry data.
+
+use serde_json::{Map, Value};
+use simdnbt::owned::{NbtCompound, NbtList, NbtTag};
+
+/// Convert JSON to NBT the way vanilla does: booleans become bytes, and lists
+/// that mix compounds with other values have the others wrapped in a compound
+/// with an empty key. `null`s are left out of objects and arrays, and become
+/// an empty compound on their own.
+#[must_use]
+pub fn to_nbt(json: &Value) -> NbtTag {
+    match json {
+        Value::Null => NbtTag::Compound(NbtCompound::new()),
+        Value::Bool(b) => NbtTag::Byte(i8::from(*b)),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => i32::try_from(i).map_or(NbtTag::Long(i), NbtTag::Int),
+            None => NbtTag::Double(n.as_f64().unwrap_or_default()),
+        },
+        Value::String(s) => NbtTag::String(s.as_str().into()),
+        Value::Array(values) => NbtTag::List(list_to_nbt(values)),
+        Value::Object(entries) => NbtTag::Compound(NbtCompound::from_values(
+            entries
+                .iter()
+                .filter(|(_, value)| !value.is_null())
+                .map(|(key, value)| (key.as_str().into(), to_nbt(value)))
+                .collect(),
+        )),
+    }
+}
+
+/// Convert NBT to JSON, undoing the wrapping [`to_nbt`] does to mixed lists.
+/// Numbers keep their value but not their width, so bytes come back as
+/// integers.
+#[must_use]
+pub fn from_nbt(tag: &NbtTag) -> Value {
+    match tag {
+        NbtTag::Byte(i) => (*i).into(),
+        NbtTag::Short(i) => (*i).into(),
+        NbtTag::Int(i) => (*i).into(),
+        NbtTag::Long(i) => (*i).into(),
+        NbtTag::Float(x) => (*x).into(),
+        NbtTag::Double(x) => (*x).into(),
+        NbtTag::String(s) => s.to_str().into(),
+        NbtTag::ByteArray(bytes) => bytes.iter().map(|&b| Value::from(b)).collect(),
+        NbtTag::IntArray(ints) => ints.iter().copied().collect(),
+        NbtTag::LongArray(longs) => longs.iter().copied().collect(),
+        NbtTag::List(list) => list.as_nbt_tags().iter().map(from_nbt).collect(),
+        NbtTag::Compound(compound) => match compound.get("") {
+            Some(inner) if compound.len() == 1 => from_nbt(inner),
+            _ => Value::Object(
+                compound
+                    .iter()
+                    .map(|(key, value)| (key.to_str().into_owned(), from_nbt(value)))
+                    .collect::<Map<_, _>>(),
+            ),
+        },
+    }
+}
+
+/// `json` as a boolean. NBT has no booleans, so the numbers 0 and 1 that
+/// [`from_nbt`] turns bytes into are accepted too.
+#[must_use]
+pub fn as_bool(json: &Value) -> Option<bool> {
+    match json {
+        Value::Bool(b) => Some(*b),
+        Value::Number(n) => match n.as_i64()? {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Convert an array to an NBT list, whose elements must all be the same type.
+fn list_to_nbt(values: &[Value]) -> NbtList {
+    let tags: Vec<NbtTag> = values
+        .iter()
+        .filter(|value| !value.is_null())
+        .map(to_nbt)
+        .collect();
+    let Some(first) = tags.first() else {
+        return NbtList::Empty;
+    };
+    if tags.iter().all(|tag| tag.id() == first.id()) {
+        return match first {
+            NbtTag::Byte(_) => {
+                NbtList::Byte(tags.into_iter().filter_map(NbtTag::into_byte).collect())
+            }
+            NbtTag::Int(_) => NbtList::Int(tags.into_iter().filter_map(NbtTag::into_int).collect()),
+            NbtTag::Long(_) => {
+                NbtList::Long(tags.into_iter().filter_map(NbtTag::into_long).collect())
+            }
+            NbtTag::Double(_) => {
+                NbtList::Double(tags.into_iter().filter_map(NbtTag::into_double).collect())
+            }
+            NbtTag::String(_) => {
+                NbtList::String(tags.into_iter().filter_map(NbtTag::into_string).collect())
+            }
+            NbtTag::List(_) => {
+                NbtList::List(tags.into_iter().filter_map(NbtTag::into_list).collect())
+            }
+            _ => NbtList::Compound(tags.into_iter().filter_map(NbtTag::into_compound).collect()),
+        };
+    }
+    NbtList::Compound(
+        tags.into_iter()
+            .map(|tag| match tag {
+                NbtTag::Compound(compound) => compound,
+                tag => NbtCompound::from_values(vec![("".into(), tag)]),
+            })
+            .collect(),
+    )
+}
@@ -0,0 +1,114 @@
+//! Keep-alive scheduling, which detects dead connections and measures latency.
+
+use std::time::{Duration, Instant};
+
+/// What a connection should do about keep-alives.
+#[derive(Clone, Copy, PartialEq, Debug, Eq, Hash)]
+pub enum Action {
+    /// Nothing until the [deadline](KeepAlive::deadline).
+    Wait,
+    /// Send a keep-alive with this ID.
+    Send(i64),
+    /// The client hasn't answered in time, so disconnect it.
+    TimedOut,
+}
+
+/// The keep-alive state of a single connection.
+///
+/// A keep-alive is sent every [interval](Self::INTERVAL) once the last one has
+/// been answered, and the client must echo its ID within the
+/// [timeout](Self::TIMEOUT).
+#[derive(Clone, Debug)]
+pub struct KeepAlive {
+    /// How long to wait after an answer before sending the next keep-alive.
+    interval: Duration,
+    /// How long the client has to answer.
+    timeout: Duration,
+    /// When the next keep-alive is due, if none is pending.
+    next: Instant,
+    /// The ID of the unanswered keep-alive and when it was sent.
+    pending: Option<(i64, Instant)>,
+    /// The ID of the next keep-alive.
+    next_id: i64,
+    /// The smoothed round-trip time, once the client has answered.
+    latency: Option<Duration>,
+}
+
+impl KeepAlive {
+    /// How often vanilla sends keep-alives.
+    pub const INTERVAL: Duration = Duration::from_secs(15);
+    /// How long clients have to answer.
+    pub const TIMEOUT: Duration = Duration::from_secs(30);
+
+    /// Start with vanilla's timing, sending the first keep-alive an interval
+    /// after `now`.
+    #[must_use]
+    pub fn new(now: Instant) -> Self {
+        Self::with_timing(now, Self::INTERVAL, Self::TIMEOUT)
+    }
+
+    /// Start with the given timing, sending the first keep-alive `interval`
+    /// after `now`.
+    #[must_use]
+    pub fn with_timing(now: Instant, interval: Duration, timeout: Duration) -> Self {
+        Self {
+            interval,
+            timeout,
+            next: now + interval,
+            pending: None,
+            next_id: 0,
+            latency: None,
+        }
+    }
+
+    /// The next time [`poll`](Self::poll) has something to do.
+    #[must_use]
+    pub fn deadline(&self) -> Instant {
+        match self.pending {
+            Some((_, sent)) => sent + self.timeout,
+            None => self.next,
+        }
+    }
+
+    /// What to do at `now`. A keep-alive returned with [`Action::Send`] is
+    /// pending until it's [answered](Self::answer).
+    pub fn poll(&mut self, now: Instant) -> Action {
+        match self.pending {
+            Some((_, sent)) if now >= sent + self.timeout => Action::TimedOut,
+            None if now >= self.next => {
+                let id = self.next_id;
+                self.next_id = self.next_id.wrapping_add(1);
+                self.pending = Some((id, now));
+                Action::Send(id)
+            }
+            Some(_) | None => Action::Wait,
+        }
+    }
+
+    /// Handle the client echoing `id` at `now`, returning whether it matches
+    /// the pending keep-alive. Latency is smoothed the way vanilla does, with
+    /// each answer weighted a quarter.
+    pub fn answer(&mut self, id: i64, now: Instant) -> bool {
+        let Some((pending, sent)) = self.pending else {
+            return false;
+        };
+        if id != pending {
+            return false;
+        }
+        let sample = now.saturating_duration_since(sent);
+        self.latency = Some(match self.latency {
+            Some(latency) => (latency * 3 + sample) / 4,
+            None => sample,
+        });
+        self.pending = None;
+        self.next = now + self.interval;
+        true
+    }
+
+    /// The smoothed round-trip time, once the client has answered a
+    /// keep-alive.
+    #[must_use]
+    pub fn latency(&self) -> Option<Duration> {
+        self.latency
+    }
+}
@@ -0,0 +1,305 @@
+//! World metadata, saved as gzipped NBT in a world's `level.dat`.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{ErrorKind, Read, Write},
+    path::Path,
+};
+
+use anyhow::{Context, bail};
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+use sha2::{Digest, Sha256};
+use simdnbt::owned::{BaseNbt, Nbt, NbtCompound, NbtList, NbtTag};
+
+use crate::{
+    McProtoSelf,
+    io::SliceReader,
+    packets::play::Difficulty,
+    types::{NbtMeta, Position},
+};
+
+/// A world's metadata.
+#[derive(Clone, PartialEq, Debug)]
+pub struct LevelData {
+    /// The data version of the game that saved it.
+    pub data_version: i32,
+    /// The block players spawn at.
+    pub spawn: Position,
+    /// The yaw players spawn facing, in degrees.
+    pub spawn_angle: f32,
+    /// Ticks since the world was created.
+    pub time: i64,
+    /// The time of day in ticks, which is 24000 a day, counting every day
+    /// since the world was created.
+    pub day_time: i64,
+    /// The game rules that have been set, such as `doImmediateRespawn`, and
+    /// their values as strings.
+    pub game_rules: BTreeMap<String, String>,
+    /// The difficulty.
+    pub difficulty: Difficulty,
+    /// Whether the difficulty can't be changed.
+    pub difficulty_locked: bool,
+    /// The world seed.
+    pub seed: i64,
+    /// The data packs that are enabled, in order, such as `vanilla`.
+    pub enabled_data_packs: Vec<String>,
+    /// The data packs that are present but disabled.
+    pub disabled_data_packs: Vec<String>,
+    /// Everything else, such as the name and the world generation settings
+    /// besides the seed, kept as is so it's saved back unchanged.
+    pub rest: NbtCompound,
+}
+
+impl LevelData {
+    /// The data version of [`GAME_VERSION`](crate::packets::GAME_VERSION).
+    pub const DATA_VERSION: i32 = 3955;
+    /// The version of the Anvil format, which vanilla checks before loading a
+    /// world.
+    const ANVIL_VERSION: i32 = 19133;
+
+    /// A new world with players spawning at `spawn`, generated from `seed`.
+    #[must_use]
+    pub fn new(spawn: Position, seed: i64) -> Self {
+        let mut rest = NbtCompound::new();
+        rest.insert("version", NbtTag::Int(Self::ANVIL_VERSION));
+        rest.insert("WorldGenSettings", NbtTag::Compound(NbtCompound::new()));
+        Self {
+            data_version: Self::DATA_VERSION,
+            spawn,
+            spawn_angle: 0.0,
+            time: 0,
+            day_time: 0,
+            game_rules: BTreeMap::new(),
+            difficulty: Difficulty::default(),
+            difficulty_locked: false,
+            seed,
+            enabled_data_packs: vec!["vanilla".to_owned()],
+            disabled_data_packs: Vec::new(),
+            rest,
+        }
+    }
+
+    /// Load the metadata from the `level.dat` at `path`, if it exists.
+    ///
+    /// # Errors
+    /// If the file exists but can't be read or parsed, return an error.
+    pub fn load(path: impl AsRef<Path>) -> Result<Option<Self>, anyhow::Error> {
+        let path = path.as_ref();
+        match fs::read(path) {
+            Ok(bytes) => Self::from_bytes(&bytes)
+                .with_context(|| format!("parsing {}", path.display()))
+                .map(Some),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e).with_context(|| format!("reading {}", path.display())),
+        }
+    }
+
+    /// Save the metadata to the `level.dat` at `path`, creating its directory
+    /// if needed. It's written to `level.dat_new` next to it first, then
+    /// renamed over it, so a crash never leaves it half written.
+    ///
+    /// # Errors
+    /// If the file can't be written, return an error.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), anyhow::Error> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+        }
+        let temp = path.with_extension("dat_new");
+        let mut file =
+            fs::File::create(&temp).with_context(|| format!("creating {}", temp.display()))?;
+        file.write_all(&self.to_bytes()?)?;
+        file.sync_all()?;
+        fs::rename(&temp, path).with_context(|| format!("replacing {}", path.display()))
+    }
+
+    /// Parse the metadata from the gzipped contents of a `level.dat`.
+    ///
+    /// # Errors
+    /// If the bytes aren't gzipped NBT in the shape of a `level.dat`, return
+    /// an error.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, anyhow::Error> {
+        let mut data = Vec::new();
+        GzDecoder::new(bytes).read_to_end(&mut data)?;
+        let meta = NbtMeta {
+            max_size: data.len(),
+            ..NbtMeta::default()
+        };
+        let Nbt::Some(nbt) = <Nbt as McProtoSelf>::read(&mut SliceReader::new(&data), meta)? else {
+            bail!("level.dat is empty");
+        };
+        Self::from_nbt(nbt.as_compound())
+    }
+
+    /// The metadata as the gzipped contents of a `level.dat`.
+    ///
+    /// # Errors
+    /// If compressing fails, return an error.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, anyhow::Error> {
+        let mut data = Vec::new();
+        Nbt::Some(BaseNbt::new("", self.to_nbt())).write(&mut data)?;
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&data)?;
+        Ok(encoder.finish()?)
+    }
+
+    /// Read the metadata from the root compound of a `level.dat`, which holds
+    /// it all in `Data`.
+    ///
+    /// # Errors
+    /// If a value is missing or has the wrong type, return an error.
+    pub fn from_nbt(mut root: NbtCompound) -> Result<Self, anyhow::Error> {
+        let Some(NbtTag::Compound(mut data)) = root.remove("Data") else {
+            bail!("level.dat has no Data");
+        };
+        let int = |data: &NbtCompound, key| data.int(key).with_context(|| format!("no {key}"));
+        let long = |data: &NbtCompound, key| data.long(key).with_context(|| format!("no {key}"));
+        let spawn = Position::new(
+            int(&data, "SpawnX")?,
+            int(&data, "SpawnY")?,
+            int(&data, "SpawnZ")?,
+        );
+        let difficulty = data.byte("Difficulty").unwrap_or_default();
+        let difficulty = Difficulty::from_id(difficulty.cast_unsigned())
+            .with_context(|| format!("unknown difficulty {difficulty}"))?;
+        // The rest of the world generation settings are kept as they are.
+        let seed = data
+            .compound_mut("WorldGenSettings")
+            .and_then(|settings| settings.remove("seed"))
+            .and_then(NbtTag::into_long)
+            .context("no WorldGenSettings seed")?;
+
+        let mut level = Self {
+            data_version: int(&data, "DataVersion")?,
+            spawn,
+            spawn_angle: data.float("SpawnAngle").unwrap_or_default(),
+            time: long(&data, "Time")?,
+            day_time: long(&data, "DayTime")?,
+            game_rules: BTreeMap::new(),
+            difficulty,
+            difficulty_locked: data.byte("DifficultyLocked").unwrap_or_default() != 0,
+            seed,
+            enabled_data_packs: Vec::new(),
+            disabled_data_packs: Vec::new(),
+            rest: NbtCompound::new(),
+        };
+        for key in [
+            "DataVersion",
+            "SpawnX",
+            "SpawnY",
+            "SpawnZ",
+            "SpawnAngle",
+            "Time",
+            "DayTime",
+            "Difficulty",
+            "DifficultyLocked",
+        ] {
+            data.remove(key);
+        }
+
+        if let Some(rules) = data.remove("GameRules") {
+            let NbtTag::Compound(rules) = rules else {
+                bail!("GameRules isn't a compound");
+            };
+            for (name, value) in rules.iter() {
+                let value = value
+                    .string()
+                    .with_context(|| format!("game rule {name} isn't a string"))?;
+                level.game_rules.insert(name.to_string(), value.to_string());
+            }
+        }
+        if let Some(packs) = data.remove("DataPacks") {
+            let NbtTag::Compound(packs) = packs else {
+                bail!("DataPacks isn't a compound");
+            };
+            level.enabled_data_packs = strings(&packs, "Enabled")?;
+            level.disabled_data_packs = strings(&packs, "Disabled")?;
+        }
+        level.rest = data;
+        Ok(level)
+    }
+
+    /// The metadata as the root compound of a `level.dat`.
+    #[must_use]
+    pub fn to_nbt(&self) -> NbtCompound {
+        let game_rules = self
+            .game_rules
+            .iter()
+            .map(|(name, value)| (name.as_str().into(), NbtTag::String(value.as_str().into())))
+            .collect();
+        let pack_list = |packs: &[String]| {
+            NbtTag::List(NbtList::String(
+                packs.iter().map(|pack| pack.as_str().into()).collect(),
+            ))
+        };
+        let data_packs = NbtCompound::from_values(vec![
+            ("Enabled".into(), pack_list(&self.enabled_data_packs)),
+            ("Disabled".into(), pack_list(&self.disabled_data_packs)),
+        ]);
+
+        let mut data = NbtCompound::from_values(vec![
+            ("DataVersion".into(), NbtTag::Int(self.data_version)),
+            ("SpawnX".into(), NbtTag::Int(self.spawn.x)),
+            ("SpawnY".into(), NbtTag::Int(self.spawn.y)),
+            ("SpawnZ".into(), NbtTag::Int(self.spawn.z)),
+            ("SpawnAngle".into(), NbtTag::Float(self.spawn_angle)),
+            ("Time".into(), NbtTag::Long(self.time)),
+            ("DayTime".into(), NbtTag::Long(self.day_time)),
+            (
+                "GameRules".into(),
+                NbtTag::Compound(NbtCompound::from_values(game_rules)),
+            ),
+            ("Difficulty".into(), NbtTag::Byte(self.difficulty as i8)),
+            (
+                "DifficultyLocked".into(),
+                NbtTag::Byte(i8::from(self.difficulty_locked)),
+            ),
+            ("DataPacks".into(), NbtTag::Compound(data_packs)),
+        ]);
+        for (key, value) in self.rest.iter() {
+            data.insert(key.to_owned(), value.clone());
+        }
+        // The seed goes back with the rest of the world generation settings.
+        if data.compound("WorldGenSettings").is_none() {
+            data.insert("WorldGenSettings", NbtTag::Compound(NbtCompound::new()));
+        }
+        if let Some(settings) = data.compound_mut("WorldGenSettings") {
+            settings.remove("seed");
+            settings.insert("seed", NbtTag::Long(self.seed));
+        }
+        NbtCompound::from_values(vec![("Data".into(), NbtTag::Compound(data))])
+    }
+
+    /// Whether the boolean game rule `name` is true. Rules that haven't been
+    /// set are false, which is the default of every rule the server uses.
+    #[must_use]
+    pub fn game_rule(&self, name: &str) -> bool {
+        self.game_rules
+            .get(name)
+            .is_some_and(|value| value == "true")
+    }
+
+    /// The seed as clients get it: the first 8 bytes of its SHA-256, for
+    /// biome noise.
+    #[must_use]
+    pub fn hashed_seed(&self) -> i64 {
+        let digest = Sha256::digest(self.seed.to_le_bytes());
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(&digest[..8]);
+        i64::from_le_bytes(bytes)
+    }
+}
+
+/// The list of strings at `key` in `nbt`, or nothing if it's missing.
+fn strings(nbt: &NbtCompound, key: &str) -> Result<Vec<String>, anyhow::Error> {
+    match nbt.list(key) {
+        None | Some(NbtList::Empty) => Ok(Vec::new()),
+        Some(list) => Ok(list
+            .strings()
+            .with_context(|| format!("{key} isn't a list of strings"))?
+            .iter()
+            .map(|pack| pack.to_str().into_owned())
+            .collect()),
+    }
+}
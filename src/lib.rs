@@ -8,6 +8,11 @@
 )]
 #![allow(clippy::cast_possible_truncation)]
 
+extern crate self as minimc;
+
+pub use error::ProtocolError;
+pub use minimc_derive::{McProto, McProtoBorrow};
+
 /// A writer.
 pub trait McWriter {
     /// Write `bytes` to this stream. Should write all provided
@@ -15,7 +20,7 @@ pub trait McWriter {
     /// 
     /// # Errors
     /// If there's an error writing all bytes, return an error.
-    fn write(&mut self, bytes: &[u8]) -> Result<(), anyhow::Error>;
+    fn write(&mut self, bytes: &[u8]) -> Result<(), ProtocolError>;
 }
 
 /// A reader.
@@ -26,16 +31,21 @@ pub trait McReader {
     /// # Errors
     /// If there's an error reading that many bytes, return an error.
     /// The contents of the buffer is unspecified if an error is returned.
-    fn read(&mut self, bytes: &mut [u8]) -> Result<(), anyhow::Error>;
+    fn read(&mut self, bytes: &mut [u8]) -> Result<(), ProtocolError>;
     /// Read a single byte.
     /// 
     /// # Errors
     /// Propogates errors from [`read`](McReader::read).
-    fn read_byte(&mut self) -> Result<u8, anyhow::Error> {
+    fn read_byte(&mut self) -> Result<u8, ProtocolError> {
         let mut out = [0u8];
         self.read(&mut out)?;
         Ok(out[0])
     }
+    /// How many bytes have been read so far, if this reader keeps track.
+    /// Used to give errors a byte offset.
+    fn position(&self) -> Option<usize> {
+        None
+    }
 }
 
 /// A single serializable protocol item.
@@ -46,12 +56,12 @@ pub trait McProto<T = Self>: Sized {
     /// 
     /// # Errors
     /// If the writer returns an error, propogate it.
-    fn write(value: T, writer: &mut dyn McWriter) -> Result<(), anyhow::Error>;
+    fn write(value: T, writer: &mut dyn McWriter) -> Result<(), ProtocolError>;
     /// Read bytes from the reader with the provided metadata.
     /// 
     /// # Errors
     /// If the reader or deserializating encounters an error, propogate it.
-    fn read(reader: &mut dyn McReader, meta: Self::Meta) -> Result<T, anyhow::Error>;
+    fn read(reader: &mut dyn McReader, meta: Self::Meta) -> Result<T, ProtocolError>;
 }
 
 /// A single serializable protocol item.
@@ -62,25 +72,71 @@ pub trait McProtoSelf: Sized {
     /// 
     /// # Errors
     /// If the writer returns an error, propogate it.
-    fn write(self, writer: &mut dyn McWriter) -> Result<(), anyhow::Error>;
+    fn write(self, writer: &mut dyn McWriter) -> Result<(), ProtocolError>;
     /// Read bytes from the reader with the provided metadata.
     /// 
     /// # Errors
     /// If the reader or deserializating encounters an error, propogate it.
-    fn read(reader: &mut dyn McReader, meta: Self::Meta) -> Result<Self, anyhow::Error>;
+    fn read(reader: &mut dyn McReader, meta: Self::Meta) -> Result<Self, ProtocolError>;
 }
 
 impl<T: McProtoSelf> McProto for T {
     type Meta = <Self as McProtoSelf>::Meta;
     
-    fn write(value: Self, writer: &mut dyn McWriter) -> Result<(), anyhow::Error> {
+    fn write(value: Self, writer: &mut dyn McWriter) -> Result<(), ProtocolError> {
         value.write(writer)
     }
     
-    fn read(reader: &mut dyn McReader, meta: Self::Meta) -> Result<Self, anyhow::Error> {
+    fn read(reader: &mut dyn McReader, meta: Self::Meta) -> Result<Self, ProtocolError> {
         Self::read(reader, meta)
     }
     
 }
 
-pub mod types;
\ No newline at end of file
+/// A protocol item that can be read by borrowing from a frame that's already
+/// in memory, instead of copying out of it.
+///
+/// Every [`McProto`] codec is one too, reading through the slice as a
+/// [`McReader`], so borrowed and owned fields can be mixed in one packet.
+pub trait McProtoBorrow<'a, T = Self>: Sized {
+    /// The metadata for reading.
+    type Meta: Default;
+    /// Read from `reader`, borrowing from the slice underneath it.
+    ///
+    /// # Errors
+    /// If the slice runs out or the item is malformed, return an error.
+    fn read_borrowed(reader: &mut io::SliceReader<'a>, meta: Self::Meta) -> Result<T, ProtocolError>;
+}
+
+impl<'a, T, P: McProto<T>> McProtoBorrow<'a, T> for P {
+    type Meta = P::Meta;
+
+    fn read_borrowed(reader: &mut io::SliceReader<'a>, meta: Self::Meta) -> Result<T, ProtocolError> {
+        P::read(reader, meta)
+    }
+}
+
+pub mod anvil;
+#[cfg(feature = "async")]
+pub mod async_io;
+pub mod auth;
+pub mod blocks;
+pub mod chunk;
+pub mod config;
+pub mod connection;
+pub mod crypto;
+pub mod error;
+pub mod game;
+pub mod io;
+pub mod json;
+pub mod keep_alive;
+pub mod level;
+pub mod packet;
+pub mod packets;
+pub mod registries;
+pub mod server;
+pub mod status;
+pub mod text;
+pub mod tick;
+pub mod types;
+pub mod world;
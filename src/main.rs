@@ -7,6 +7,21 @@
     clippy::ignore_without_reason
 )]
 
+use std::path::PathBuf;
+
+use minimc::{auth::MojangSessionService, config::Config, server::Server};
+
 fn main() -> anyhow::Result<()> {
-    Ok(())
+    let path = std::env::args_os()
+        .nth(1)
+        .map_or_else(|| PathBuf::from("server.properties"), PathBuf::from);
+    let config = Config::load(&path)?;
+    let server = Server::bind_with_session(config, Box::new(MojangSessionService::new()))?;
+    println!("listening on {}", server.local_addr()?);
+    let shutdown = server.shutdown_handle();
+    ctrlc::set_handler(move || {
+        println!("stopping server");
+        shutdown.trigger();
+    })?;
+    server.run()
 }
@@ -0,0 +1,246 @@
+//! Length-prefixed packet framing, optionally compressed.
+
+use std::io::{Read, Write};
+
+use flate2::{Compression as Level, read::ZlibDecoder, write::ZlibEncoder};
+
+use crate::{
+    McProto, McProtoBorrow, McReader, McWriter, ProtocolError, io::SliceReader, types::VarNum,
+};
+
+/// Packet compression settings, in effect once Set Compression has been sent.
+///
+/// Compressed frames hold the uncompressed length of the ID and body as a
+/// `VarInt`, then the zlib-compressed ID and body. Frames smaller than the
+/// threshold are sent uncompressed with a length of zero.
+#[derive(Clone, Copy, PartialEq, Debug, Eq, Hash)]
+pub struct Compression {
+    /// The smallest ID and body, in bytes, to compress.
+    pub threshold: usize,
+    /// The largest uncompressed size to accept, to stop zip bombs.
+    pub max_uncompressed: usize,
+}
+
+impl Compression {
+    /// The default limit on uncompressed size, matching vanilla's 8 MiB.
+    pub const DEFAULT_MAX_UNCOMPRESSED: usize = 8 * 1024 * 1024;
+
+    /// Compress frames of at least `threshold` bytes, with the default limit on
+    /// uncompressed size.
+    #[must_use]
+    pub const fn new(threshold: usize) -> Self {
+        Self {
+            threshold,
+            max_uncompressed: Self::DEFAULT_MAX_UNCOMPRESSED,
+        }
+    }
+
+    /// Wrap an uncompressed frame in the compressed format.
+    ///
+    /// # Errors
+    /// If compression fails, return an error.
+    pub fn compress(&self, frame: &[u8]) -> Result<Vec<u8>, ProtocolError> {
+        let mut out = Vec::with_capacity(frame.len() + 5);
+        if frame.len() < self.threshold {
+            VarNum::write(0u32, &mut out)?;
+            out.extend_from_slice(frame);
+            return Ok(out);
+        }
+        VarNum::write(frame.len() as u32, &mut out)?;
+        let mut encoder = ZlibEncoder::new(out, Level::default());
+        encoder.write_all(frame)?;
+        Ok(encoder.finish()?)
+    }
+
+    /// Unwrap a frame in the compressed format.
+    ///
+    /// # Errors
+    /// If the frame is malformed, claims an uncompressed size that's below the
+    /// threshold or over the limit, or doesn't decompress to the size it
+    /// claims, return an error.
+    pub fn decompress(&self, frame: &[u8]) -> Result<Vec<u8>, ProtocolError> {
+        let mut reader = SliceReader::new(frame);
+        let length: u32 = VarNum::read(&mut reader, ())?;
+        let length = length as usize;
+        if length == 0 {
+            return Ok(reader.rest().to_vec());
+        }
+        if length < self.threshold {
+            return Err(ProtocolError::invalid(
+                Some(0),
+                format!(
+                    "compressed packet of {length} bytes is below the {} byte threshold",
+                    self.threshold
+                ),
+            ));
+        }
+        if length > self.max_uncompressed {
+            return Err(ProtocolError::PacketTooLarge {
+                offset: Some(0),
+                len: length,
+                max: self.max_uncompressed,
+            });
+        }
+        let mut out = Vec::with_capacity(length);
+        ZlibDecoder::new(reader.rest())
+            .take(length as u64 + 1)
+            .read_to_end(&mut out)?;
+        if out.len() != length {
+            return Err(ProtocolError::invalid(
+                Some(0),
+                format!(
+                    "compressed packet decompressed to {} bytes instead of {length}",
+                    out.len()
+                ),
+            ));
+        }
+        Ok(out)
+    }
+}
+
+/// A single frame: a packet ID and its still-encoded body.
+#[derive(Clone, PartialEq, Debug, Default, Eq, Hash)]
+pub struct Packet {
+    /// The packet ID.
+    pub id: u32,
+    /// The encoded body, after the ID.
+    pub body: Vec<u8>,
+}
+
+impl Packet {
+    /// The largest frame accepted, not counting the length prefix itself.
+    pub const MAX_LENGTH: usize = 2 * 1024 * 1024;
+
+    /// Encode `value` into the body of a new packet.
+    ///
+    /// # Errors
+    /// If encoding `value` fails, return an error.
+    pub fn encode<T, P: McProto<T>>(id: u32, value: T) -> Result<Self, ProtocolError> {
+        let mut body = Vec::new();
+        P::write(value, &mut body)?;
+        Ok(Self { id, body })
+    }
+
+    /// Decode the body as a `T`. The whole body must be used.
+    ///
+    /// # Errors
+    /// If decoding fails or there are bytes left over afterwards, return an error.
+    pub fn decode<T, P: McProto<T>>(&self, meta: P::Meta) -> Result<T, ProtocolError> {
+        let mut reader = SliceReader::new(&self.body);
+        let value = P::read(&mut reader, meta)?;
+        self.check_trailing(&reader)?;
+        Ok(value)
+    }
+
+    /// Decode the body as a `T` that borrows from it, so strings, byte arrays
+    /// and NBT aren't copied out. The whole body must be used.
+    ///
+    /// # Errors
+    /// If decoding fails or there are bytes left over afterwards, return an error.
+    pub fn decode_borrowed<'a, T, P: McProtoBorrow<'a, T>>(
+        &'a self,
+        meta: P::Meta,
+    ) -> Result<T, ProtocolError> {
+        let mut reader = SliceReader::new(&self.body);
+        let value = P::read_borrowed(&mut reader, meta)?;
+        self.check_trailing(&reader)?;
+        Ok(value)
+    }
+
+    /// Check that `reader` used the whole body.
+    fn check_trailing(&self, reader: &SliceReader<'_>) -> Result<(), ProtocolError> {
+        if !reader.is_empty() {
+            return Err(ProtocolError::invalid(
+                Some(reader.position()),
+                format!(
+                    "{} trailing bytes after packet {:#04x}",
+                    reader.remaining(),
+                    self.id
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Read a single uncompressed frame.
+    ///
+    /// # Errors
+    /// If the reader fails, or the frame is empty or over [`MAX_LENGTH`](Self::MAX_LENGTH),
+    /// return an error.
+    pub fn read(reader: &mut dyn McReader) -> Result<Self, ProtocolError> {
+        Self::read_with(reader, None)
+    }
+
+    /// Read a single frame, decompressing it if `compression` is enabled.
+    ///
+    /// # Errors
+    /// If the reader fails, the frame is empty or over [`MAX_LENGTH`](Self::MAX_LENGTH),
+    /// or it fails to decompress, return an error.
+    pub fn read_with(
+        reader: &mut dyn McReader,
+        compression: Option<Compression>,
+    ) -> Result<Self, ProtocolError> {
+        let length: u32 = VarNum::read(reader, ())?;
+        let mut frame = vec![0u8; Self::check_length(length as usize)?];
+        reader.read(&mut frame)?;
+        Self::from_frame(frame, compression)
+    }
+
+    /// Check the length prefix of a frame against [`MAX_LENGTH`](Self::MAX_LENGTH).
+    pub(crate) fn check_length(length: usize) -> Result<usize, ProtocolError> {
+        if length > Self::MAX_LENGTH {
+            return Err(ProtocolError::PacketTooLarge {
+                offset: None,
+                len: length,
+                max: Self::MAX_LENGTH,
+            });
+        }
+        Ok(length)
+    }
+
+    /// Split a frame, minus its length prefix, into the ID and body.
+    pub(crate) fn from_frame(
+        mut frame: Vec<u8>,
+        compression: Option<Compression>,
+    ) -> Result<Self, ProtocolError> {
+        if let Some(compression) = compression {
+            frame = compression.decompress(&frame)?;
+        }
+        let mut frame_reader = SliceReader::new(&frame);
+        let id = VarNum::read(&mut frame_reader, ())?;
+        let offset = frame_reader.position();
+        frame.drain(..offset);
+        Ok(Self { id, body: frame })
+    }
+
+    /// Write this frame uncompressed, prefixed with its length.
+    ///
+    /// # Errors
+    /// If the writer fails or the frame is over [`MAX_LENGTH`](Self::MAX_LENGTH),
+    /// return an error.
+    pub fn write(&self, writer: &mut dyn McWriter) -> Result<(), ProtocolError> {
+        self.write_with(writer, None)
+    }
+
+    /// Write this frame, prefixed with its length and compressed if
+    /// `compression` is enabled.
+    ///
+    /// # Errors
+    /// If the writer or compression fails, or the frame is over
+    /// [`MAX_LENGTH`](Self::MAX_LENGTH), return an error.
+    pub fn write_with(
+        &self,
+        writer: &mut dyn McWriter,
+        compression: Option<Compression>,
+    ) -> Result<(), ProtocolError> {
+        let mut frame = Vec::with_capacity(5 + self.body.len());
+        VarNum::write(self.id, &mut frame)?;
+        frame.extend_from_slice(&self.body);
+        if let Some(compression) = compression {
+            frame = compression.compress(&frame)?;
+        }
+        let length = Self::check_length(frame.len())?;
+        VarNum::write(length as u32, writer)?;
+        writer.write(&frame)
+    }
+}
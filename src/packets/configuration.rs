@@ -0,0 +1,179 @@
+//! Packets in the configuration state.
+
+use simdnbt::owned::Nbt;
+
+use crate::{
+    McProto, McProtoSelf, McReader, McWriter, ProtocolError,
+    text::TextComponent,
+    types::{Identifier, NetworkNbt},
+};
+
+/// Disconnects the client while configuring it.
+#[derive(Clone, PartialEq, Debug, McProto)]
+pub struct ConfigurationDisconnect {
+    /// The reason.
+    pub reason: TextComponent,
+}
+
+/// Tells the client configuration is done, and to switch to the play state
+/// once it's acknowledged.
+#[derive(Clone, PartialEq, Debug, Eq, Hash, McProto)]
+pub struct FinishConfiguration;
+
+/// Acknowledges a [`FinishConfiguration`], switching to the play state.
+#[derive(Clone, PartialEq, Debug, Eq, Hash, McProto)]
+pub struct AcknowledgeFinishConfiguration;
+
+/// Checks the connection is alive. The other side echoes the ID back.
+#[derive(Clone, PartialEq, Debug, Eq, Hash, McProto)]
+pub struct ConfigurationKeepAlive {
+    /// An ID to echo back.
+    pub id: i64,
+}
+
+/// A single entry of a registry.
+#[derive(Clone, PartialEq, Debug)]
+pub struct RegistryEntry {
+    /// The entry's ID.
+    pub id: Identifier,
+    /// The entry's data, or `None` if the client has it from a known pack.
+    pub data: Option<Nbt>,
+}
+
+/// The data is a network NBT compound, prefixed with whether it's present.
+impl McProtoSelf for RegistryEntry {
+    type Meta = ();
+    fn write(self, writer: &mut dyn McWriter) -> Result<(), ProtocolError> {
+        self.id.write(writer)?;
+        self.data.is_some().write(writer)?;
+        match self.data {
+            Some(data) => NetworkNbt::write(data, writer),
+            None => Ok(()),
+        }
+    }
+    fn read(reader: &mut dyn McReader, (): ()) -> Result<Self, ProtocolError> {
+        let id = <Identifier as McProtoSelf>::read(reader, ())?;
+        let data = if <bool as McProtoSelf>::read(reader, ())? {
+            Some(NetworkNbt::read(reader, ())?)
+        } else {
+            None
+        };
+        Ok(Self { id, data })
+    }
+}
+
+/// The entries of a single data-driven registry, such as dimension types or
+/// biomes. Entries are numbered by their order here.
+#[derive(Clone, PartialEq, Debug, McProto)]
+pub struct RegistryData {
+    /// The registry, such as `minecraft:dimension_type`.
+    pub registry: Identifier,
+    /// The registry's entries.
+    pub entries: Vec<RegistryEntry>,
+}
+
+/// The feature flags enabled on the server, such as `minecraft:vanilla`.
+#[derive(Clone, PartialEq, Debug, Eq, Hash, McProto)]
+pub struct FeatureFlags {
+    /// The enabled flags.
+    pub flags: Vec<Identifier>,
+}
+
+/// A single tag: a name for a set of registry entries.
+#[derive(Clone, PartialEq, Debug, Eq, Hash, McProto)]
+pub struct Tag {
+    /// The tag's name, without the leading `#`.
+    pub name: Identifier,
+    /// The registry IDs of the entries in the tag.
+    #[mc(varint)]
+    pub entries: Vec<u32>,
+}
+
+/// The tags of a single registry.
+#[derive(Clone, PartialEq, Debug, Eq, Hash, McProto)]
+pub struct RegistryTags {
+    /// The registry, such as `minecraft:damage_type`.
+    pub registry: Identifier,
+    /// The registry's tags.
+    pub tags: Vec<Tag>,
+}
+
+/// Replaces the client's tags for every registry listed.
+#[derive(Clone, PartialEq, Debug, Eq, Hash, McProto)]
+pub struct UpdateTags {
+    /// The tags of each registry.
+    pub registries: Vec<RegistryTags>,
+}
+
+/// A data pack, as identified in [`ClientboundKnownPacks`] and
+/// [`ServerboundKnownPacks`].
+#[derive(Clone, PartialEq, Debug, Eq, Hash, McProto)]
+pub struct KnownPack {
+    /// The pack's namespace, such as `minecraft`.
+    pub namespace: String,
+    /// The pack's ID, such as `core`.
+    pub id: String,
+    /// The pack's version, such as `1.21.1`.
+    pub version: String,
+}
+
+/// The packs the server's registry data comes from. The client answers with
+/// the ones it has, and entries from those can be sent without their data.
+#[derive(Clone, PartialEq, Debug, Eq, Hash, McProto)]
+pub struct ClientboundKnownPacks {
+    /// The server's packs.
+    pub packs: Vec<KnownPack>,
+}
+
+/// Answers a [`ClientboundKnownPacks`] with the packs the client has too.
+#[derive(Clone, PartialEq, Debug, Eq, Hash, McProto)]
+pub struct ServerboundKnownPacks {
+    /// The packs both sides have.
+    pub packs: Vec<KnownPack>,
+}
+
+/// Which chat messages the client wants to see.
+#[derive(Clone, Copy, PartialEq, Debug, Eq, Hash, McProto)]
+#[mc(tag = varint)]
+pub enum ChatMode {
+    /// Every message.
+    Enabled = 0,
+    /// Only the output of commands.
+    CommandsOnly = 1,
+    /// Nothing.
+    Hidden = 2,
+}
+
+/// The hand a player uses by default.
+#[derive(Clone, Copy, PartialEq, Debug, Eq, Hash, McProto)]
+#[mc(tag = varint)]
+pub enum MainHand {
+    /// The left hand.
+    Left = 0,
+    /// The right hand.
+    Right = 1,
+}
+
+/// The client's settings. Sent while configuring, and again whenever they
+/// change in game.
+#[derive(Clone, PartialEq, Debug, Eq, Hash, McProto)]
+pub struct ClientInformation {
+    /// The client's language, such as `en_us`.
+    #[mc(max_len = 16)]
+    pub locale: String,
+    /// The client's render distance, in chunks.
+    pub view_distance: i8,
+    /// Which chat messages the client wants to see.
+    pub chat_mode: ChatMode,
+    /// Whether the client shows chat colors.
+    pub chat_colors: bool,
+    /// A bit mask of the skin parts shown, from the cape at bit 0 to the hat
+    /// at bit 6.
+    pub displayed_skin_parts: u8,
+    /// The player's main hand.
+    pub main_hand: MainHand,
+    /// Whether the client filters text on signs and written books.
+    pub enable_text_filtering: bool,
+    /// Whether the player may be listed in the server list sample.
+    pub allow_server_listings: bool,
+}
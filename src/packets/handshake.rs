@@ -0,0 +1,30 @@
+//! Packets in the handshake state.
+
+use crate::McProto;
+
+/// What a client wants to do after the handshake.
+#[derive(Clone, Copy, PartialEq, Debug, Eq, Hash, McProto)]
+#[mc(tag = varint)]
+pub enum Intent {
+    /// Ping the server for the server list.
+    Status = 1,
+    /// Log in.
+    Login = 2,
+    /// Log in after being transferred from another server.
+    Transfer = 3,
+}
+
+/// The first packet a client sends.
+#[derive(Clone, PartialEq, Debug, Eq, Hash, McProto)]
+pub struct Handshake {
+    /// The protocol version of the client.
+    #[mc(varint)]
+    pub protocol_version: u32,
+    /// The address the client used to connect.
+    #[mc(max_len = 255)]
+    pub address: String,
+    /// The port the client used to connect.
+    pub port: u16,
+    /// What the client wants to do next.
+    pub intent: Intent,
+}
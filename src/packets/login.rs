@@ -0,0 +1,99 @@
+//! Packets in the login state.
+
+use crate::{
+    McProto,
+    types::{ByteArray, Uuid},
+};
+
+/// Starts logging in.
+#[derive(Clone, PartialEq, Debug, Eq, Hash, McProto)]
+pub struct LoginStart {
+    /// The player's username.
+    #[mc(max_len = 16)]
+    pub name: String,
+    /// The player's UUID. Not trusted in either mode.
+    pub uuid: Uuid,
+}
+
+/// Acknowledges a [`LoginSuccess`], switching to the configuration state.
+#[derive(Clone, PartialEq, Debug, Eq, Hash, McProto)]
+pub struct LoginAcknowledged;
+
+/// Asks the client to enable encryption, and in online mode to authenticate
+/// with the session server.
+#[derive(Clone, PartialEq, Debug, Eq, Hash, McProto)]
+pub struct EncryptionRequest {
+    /// The server ID hashed into the session server request. Empty since 1.7.
+    #[mc(max_len = 20)]
+    pub server_id: String,
+    /// The server's public key, as a DER `SubjectPublicKeyInfo`.
+    #[mc(with = ByteArray, max_len = 1024)]
+    pub public_key: Vec<u8>,
+    /// A random token the client must send back encrypted.
+    #[mc(with = ByteArray, max_len = 1024)]
+    pub verify_token: Vec<u8>,
+    /// Whether the client should authenticate with the session server.
+    pub should_authenticate: bool,
+}
+
+/// Answers an [`EncryptionRequest`].
+#[derive(Clone, PartialEq, Debug, Eq, Hash, McProto)]
+pub struct EncryptionResponse {
+    /// The shared secret, encrypted with the server's public key.
+    #[mc(with = ByteArray, max_len = 1024)]
+    pub shared_secret: Vec<u8>,
+    /// The verify token, encrypted with the server's public key.
+    #[mc(with = ByteArray, max_len = 1024)]
+    pub verify_token: Vec<u8>,
+}
+
+/// Enables compression for every packet after this one.
+#[derive(Clone, PartialEq, Debug, Eq, Hash, McProto)]
+pub struct SetCompression {
+    /// The smallest packet to compress, in bytes. Negative disables
+    /// compression.
+    #[mc(varint)]
+    pub threshold: i32,
+}
+
+/// Disconnects the client while logging in.
+#[derive(Clone, PartialEq, Debug, Eq, Hash, McProto)]
+pub struct LoginDisconnect {
+    /// The reason, as a JSON text component.
+    #[mc(max_len = 262_144)]
+    pub reason: String,
+}
+
+/// A property of a player's profile, such as their skin.
+#[derive(Clone, PartialEq, Debug, Eq, Hash, McProto)]
+pub struct Property {
+    /// The property's name.
+    #[mc(max_len = 64)]
+    pub name: String,
+    /// The property's value.
+    #[mc(max_len = 32767)]
+    pub value: String,
+    /// The signature of the value, from the session server.
+    #[mc(max_len = 1024)]
+    pub signature: Option<String>,
+}
+
+/// Finishes logging in.
+#[derive(Clone, PartialEq, Debug, Eq, Hash, McProto)]
+pub struct LoginSuccess {
+    /// The player's UUID.
+    pub uuid: Uuid,
+    /// The player's username.
+    #[mc(max_len = 16)]
+    pub username: String,
+    /// The player's profile properties.
+    pub properties: Vec<Property>,
+    /// Whether the client should disconnect on packets it can't decode.
+    pub strict_error_handling: bool,
+}
+
+/// Whether `name` is a valid username: 3 to 16 letters, digits and underscores.
+#[must_use]
+pub fn is_valid_username(name: &str) -> bool {
+    (3..=16).contains(&name.len()) && name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_')
+}
@@ -0,0 +1,324 @@
+//! Typed packets, and the registry mapping them to and from packet IDs.
+
+pub mod configuration;
+pub mod handshake;
+pub mod login;
+pub mod play;
+pub mod status;
+
+use std::{collections::HashMap, sync::LazyLock};
+
+use crate::{ProtocolError, packet::Packet};
+
+/// The protocol version the registered packet IDs are for.
+pub const PROTOCOL_VERSION: u32 = 767;
+/// The game version matching [`PROTOCOL_VERSION`].
+pub const GAME_VERSION: &str = "1.21.1";
+
+/// The protocol state of a connection.
+#[derive(Clone, Copy, PartialEq, Debug, Eq, Hash)]
+pub enum State {
+    /// The initial state, where the client says what it wants.
+    Handshake,
+    /// Server list ping.
+    Status,
+    /// Logging in.
+    Login,
+    /// Configuring the client before it enters the world.
+    Configuration,
+    /// In game.
+    Play,
+}
+
+/// Which way a packet is sent.
+#[derive(Clone, Copy, PartialEq, Debug, Eq, Hash)]
+pub enum Direction {
+    /// From the client to the server.
+    Serverbound,
+    /// From the server to the client.
+    Clientbound,
+}
+
+/// Decodes a frame into a typed packet.
+pub type Decoder<P> = fn(&Packet) -> Result<P, ProtocolError>;
+
+/// The packet IDs of a [`PacketSet`] for a single protocol version.
+#[derive(Debug)]
+pub struct Table<P> {
+    /// The decoder for each packet ID in each state.
+    decoders: HashMap<(State, u32), Decoder<P>>,
+    /// The packet ID of each packet name in each state.
+    ids: HashMap<(State, &'static str), u32>,
+}
+
+impl<P: PacketSet> Table<P> {
+    /// Build the table from the entries of `P`.
+    fn new() -> Self {
+        let mut table = Self {
+            decoders: HashMap::new(),
+            ids: HashMap::new(),
+        };
+        for (state, id, name, decoder) in P::entries() {
+            table.decoders.insert((state, id), decoder);
+            table.ids.insert((state, name), id);
+        }
+        table
+    }
+}
+
+/// The typed packets sent in one direction.
+pub trait PacketSet: Sized {
+    /// The direction these packets are sent in.
+    const DIRECTION: Direction;
+    /// Every registered packet, as its state, ID, name and decoder.
+    fn entries() -> Vec<(State, u32, &'static str, Decoder<Self>)>;
+    /// The table for these packets in `registry`.
+    fn table(registry: &Registry) -> &Table<Self>;
+    /// Wrap a frame that has no registered decoder.
+    fn unknown(packet: Packet) -> Self;
+    /// The name of this packet.
+    fn name(&self) -> &'static str;
+    /// Encode this packet, looking up its ID by name with `id`.
+    ///
+    /// # Errors
+    /// If `id` or encoding the body fails, return an error.
+    fn encode(
+        self,
+        id: &dyn Fn(&'static str) -> Result<u32, ProtocolError>,
+    ) -> Result<Packet, ProtocolError>;
+}
+
+/// Declare the typed packets in each direction and their IDs in each state.
+macro_rules! packets {
+    ($(
+        $(#[$meta:meta])*
+        $set:ident: $direction:ident {
+            $($state:ident {
+                $($id:literal => $variant:ident($ty:ty),)*
+            })*
+        }
+    )*) => {$(
+        $(#[$meta])*
+        #[derive(Clone, PartialEq, Debug)]
+        #[allow(
+            clippy::large_enum_variant,
+            reason = "packets are moved once, from decoding to handling"
+        )]
+        pub enum $set {
+            $($(
+                #[doc = concat!("A [`", stringify!($ty), "`].")]
+                $variant($ty),
+            )*)*
+            /// A packet with no registered decoder.
+            Unknown(Packet),
+        }
+
+        impl PacketSet for $set {
+            const DIRECTION: Direction = Direction::$direction;
+
+            fn entries() -> Vec<(State, u32, &'static str, Decoder<Self>)> {
+                vec![$($(
+                    (
+                        State::$state,
+                        $id,
+                        stringify!($variant),
+                        (|packet: &Packet| packet.decode::<$ty, $ty>(()).map(Self::$variant))
+                            as Decoder<Self>,
+                    ),
+                )*)*]
+            }
+
+            fn table(registry: &Registry) -> &Table<Self> {
+                &registry.$set
+            }
+
+            fn unknown(packet: Packet) -> Self {
+                Self::Unknown(packet)
+            }
+
+            fn name(&self) -> &'static str {
+                match self {
+                    $($(Self::$variant(_) => stringify!($variant),)*)*
+                    Self::Unknown(_) => "Unknown",
+                }
+            }
+
+            #[allow(unused_variables, reason = "sets with no packets never look up an ID")]
+            fn encode(
+                self,
+                id: &dyn Fn(&'static str) -> Result<u32, ProtocolError>,
+            ) -> Result<Packet, ProtocolError> {
+                match self {
+                    $($(Self::$variant(packet) => {
+                        Packet::encode::<$ty, $ty>(id(stringify!($variant))?, packet)
+                    })*)*
+                    Self::Unknown(packet) => Ok(packet),
+                }
+            }
+        }
+    )*};
+}
+
+packets! {
+    /// Packets sent by the client.
+    Serverbound: Serverbound {
+        Handshake {
+            0x00 => Handshake(handshake::Handshake),
+        }
+        Status {
+            0x00 => StatusRequest(status::StatusRequest),
+            0x01 => PingRequest(status::PingRequest),
+        }
+        Login {
+            0x00 => LoginStart(login::LoginStart),
+            0x01 => EncryptionResponse(login::EncryptionResponse),
+            0x03 => LoginAcknowledged(login::LoginAcknowledged),
+        }
+        Configuration {
+            0x00 => ClientInformation(configuration::ClientInformation),
+            0x03 => AcknowledgeFinishConfiguration(configuration::AcknowledgeFinishConfiguration),
+            0x04 => ConfigurationKeepAlive(configuration::ConfigurationKeepAlive),
+            0x07 => ServerboundKnownPacks(configuration::ServerboundKnownPacks),
+        }
+        Play {
+            0x00 => ConfirmTeleportation(play::ConfirmTeleportation),
+            0x08 => ChunkBatchReceived(play::ChunkBatchReceived),
+            0x18 => PlayKeepAlive(play::PlayKeepAlive),
+            0x1A => SetPlayerPosition(play::SetPlayerPosition),
+            0x1B => SetPlayerPositionAndRotation(play::SetPlayerPositionAndRotation),
+            0x1C => SetPlayerRotation(play::SetPlayerRotation),
+            0x1D => SetPlayerOnGround(play::SetPlayerOnGround),
+        }
+    }
+    /// Packets sent by the server.
+    Clientbound: Clientbound {
+        Status {
+            0x00 => StatusResponse(status::StatusResponse),
+            0x01 => PongResponse(status::PongResponse),
+        }
+        Login {
+            0x00 => LoginDisconnect(login::LoginDisconnect),
+            0x01 => EncryptionRequest(login::EncryptionRequest),
+            0x02 => LoginSuccess(login::LoginSuccess),
+            0x03 => SetCompression(login::SetCompression),
+        }
+        Configuration {
+            0x02 => ConfigurationDisconnect(configuration::ConfigurationDisconnect),
+            0x03 => FinishConfiguration(configuration::FinishConfiguration),
+            0x04 => ConfigurationKeepAlive(configuration::ConfigurationKeepAlive),
+            0x07 => RegistryData(configuration::RegistryData),
+            0x0C => FeatureFlags(configuration::FeatureFlags),
+            0x0D => UpdateTags(configuration::UpdateTags),
+            0x0E => ClientboundKnownPacks(configuration::ClientboundKnownPacks),
+        }
+        Play {
+            0x0B => ChangeDifficulty(play::ChangeDifficulty),
+            0x0C => ChunkBatchFinished(play::ChunkBatchFinished),
+            0x0D => ChunkBatchStart(play::ChunkBatchStart),
+            0x1D => PlayDisconnect(play::PlayDisconnect),
+            0x22 => GameEvent(play::GameEvent),
+            0x26 => PlayKeepAlive(play::PlayKeepAlive),
+            0x27 => ChunkData(play::ChunkData),
+            0x2A => UpdateLight(play::UpdateLight),
+            0x2B => LoginPlay(play::LoginPlay),
+            0x40 => SynchronizePlayerPosition(play::SynchronizePlayerPosition),
+            0x54 => SetCenterChunk(play::SetCenterChunk),
+            0x56 => SetDefaultSpawnPosition(play::SetDefaultSpawnPosition),
+        }
+    }
+}
+
+impl Serverbound {
+    /// Whether the packet moves or turns the player, which the server ignores
+    /// while a teleport is waiting to be confirmed.
+    #[must_use]
+    pub fn is_movement(&self) -> bool {
+        matches!(
+            self,
+            Self::SetPlayerPosition(_)
+                | Self::SetPlayerPositionAndRotation(_)
+                | Self::SetPlayerRotation(_)
+                | Self::SetPlayerOnGround(_)
+        )
+    }
+}
+
+/// Maps packet IDs to typed packets and back for a single protocol version.
+///
+/// The server speaks exactly one version, [`PROTOCOL_VERSION`], so there is
+/// one registry, built from the IDs in the `packets!` table above. Supporting
+/// another version means giving it its own table of IDs and a registry built
+/// from it, returned from [`for_version`](Self::for_version).
+#[derive(Debug)]
+#[allow(non_snake_case, reason = "fields are named after their packet sets")]
+pub struct Registry {
+    /// The protocol version the IDs are for.
+    protocol_version: u32,
+    /// Packets sent by the client.
+    Serverbound: Table<Serverbound>,
+    /// Packets sent by the server.
+    Clientbound: Table<Clientbound>,
+}
+
+/// The registry for [`PROTOCOL_VERSION`].
+static LATEST: LazyLock<Registry> = LazyLock::new(|| Registry {
+    protocol_version: PROTOCOL_VERSION,
+    Serverbound: Table::new(),
+    Clientbound: Table::new(),
+});
+
+impl Registry {
+    /// The registry for `protocol_version`, if it's supported. Only
+    /// [`PROTOCOL_VERSION`] is, and clients on any other version are turned
+    /// away at login.
+    #[must_use]
+    pub fn for_version(protocol_version: u32) -> Option<&'static Self> {
+        (protocol_version == PROTOCOL_VERSION).then(Self::latest)
+    }
+
+    /// The registry for [`PROTOCOL_VERSION`].
+    #[must_use]
+    pub fn latest() -> &'static Self {
+        &LATEST
+    }
+
+    /// The protocol version the IDs are for.
+    #[must_use]
+    pub fn protocol_version(&self) -> u32 {
+        self.protocol_version
+    }
+
+    /// Decode a frame received in `state`. Frames with no registered decoder are
+    /// returned as the set's unknown packet.
+    ///
+    /// # Errors
+    /// If the frame has a registered decoder and it fails, return an error.
+    pub fn decode<P: PacketSet>(&self, state: State, packet: Packet) -> Result<P, ProtocolError> {
+        match P::table(self).decoders.get(&(state, packet.id)) {
+            Some(decoder) => decoder(&packet),
+            None => Ok(P::unknown(packet)),
+        }
+    }
+
+    /// Encode a packet to be sent in `state`. Unknown packets are passed through
+    /// unchanged.
+    ///
+    /// # Errors
+    /// If the packet isn't registered in `state`, return
+    /// [`ProtocolError::UnregisteredPacket`]. If encoding it fails, return
+    /// that error.
+    pub fn encode<P: PacketSet>(&self, state: State, packet: P) -> Result<Packet, ProtocolError> {
+        let table = P::table(self);
+        packet.encode(&|name| {
+            table
+                .ids
+                .get(&(state, name))
+                .copied()
+                .ok_or(ProtocolError::UnregisteredPacket {
+                    direction: P::DIRECTION,
+                    state,
+                    name,
+                })
+        })
+    }
+}
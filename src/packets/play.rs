@@ -0,0 +1,334 @@
+//! Packets in the play state.
+
+use simdnbt::owned::Nbt;
+
+use crate::{
+    McProto,
+    chunk::{ChunkSection, Sections},
+    text::TextComponent,
+    types::{BitSet, Identifier, NetworkNbt, Position},
+};
+
+/// Disconnects the client while it's in game.
+#[derive(Clone, PartialEq, Debug, McProto)]
+pub struct PlayDisconnect {
+    /// The reason.
+    pub reason: TextComponent,
+}
+
+/// Checks the connection is alive. The other side echoes the ID back.
+#[derive(Clone, PartialEq, Debug, Eq, Hash, McProto)]
+pub struct PlayKeepAlive {
+    /// An ID to echo back.
+    pub id: i64,
+}
+
+/// A player's game mode.
+#[derive(Clone, Copy, PartialEq, Debug, Eq, Hash, McProto)]
+#[mc(tag = u8)]
+pub enum GameMode {
+    /// Survival mode.
+    Survival = 0,
+    /// Creative mode.
+    Creative = 1,
+    /// Adventure mode.
+    Adventure = 2,
+    /// Spectator mode.
+    Spectator = 3,
+}
+
+/// How hard the game is.
+#[derive(Clone, Copy, PartialEq, Debug, Default, Eq, Hash, McProto)]
+#[mc(tag = u8)]
+pub enum Difficulty {
+    /// No hostile mobs, and health regenerates.
+    Peaceful = 0,
+    /// Easy.
+    #[default]
+    Easy = 1,
+    /// Normal.
+    Normal = 2,
+    /// Hard.
+    Hard = 3,
+}
+
+impl Difficulty {
+    /// The difficulty with the ID `id`, if there is one.
+    #[must_use]
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(Self::Peaceful),
+            1 => Some(Self::Easy),
+            2 => Some(Self::Normal),
+            3 => Some(Self::Hard),
+            _ => None,
+        }
+    }
+}
+
+/// Where a player last died.
+#[derive(Clone, PartialEq, Debug, Eq, Hash, McProto)]
+pub struct DeathLocation {
+    /// The dimension they died in.
+    pub dimension: Identifier,
+    /// The block they died at.
+    pub position: Position,
+}
+
+/// Puts the client in the world, after configuration.
+#[derive(Clone, PartialEq, Debug, Eq, Hash, McProto)]
+#[allow(
+    clippy::struct_excessive_bools,
+    reason = "the fields are the packet's, in order"
+)]
+pub struct LoginPlay {
+    /// The player's entity ID.
+    pub entity_id: i32,
+    /// Whether the world is hardcore.
+    pub is_hardcore: bool,
+    /// Every dimension on the server.
+    pub dimension_names: Vec<Identifier>,
+    /// The most players that can be online, which is unused by the client.
+    #[mc(varint)]
+    pub max_players: u32,
+    /// The server's render distance, in chunks.
+    #[mc(varint)]
+    pub view_distance: u32,
+    /// The distance entities are ticked at, in chunks.
+    #[mc(varint)]
+    pub simulation_distance: u32,
+    /// Whether the debug screen hides coordinates.
+    pub reduced_debug_info: bool,
+    /// Whether to show the death screen instead of respawning immediately.
+    pub enable_respawn_screen: bool,
+    /// Whether players can only craft recipes they've unlocked.
+    pub do_limited_crafting: bool,
+    /// The ID of the spawn dimension's type in the `minecraft:dimension_type`
+    /// registry.
+    #[mc(varint)]
+    pub dimension_type: u32,
+    /// The dimension being spawned into.
+    pub dimension_name: Identifier,
+    /// The first 8 bytes of the SHA-256 of the world seed, for biome noise.
+    pub hashed_seed: i64,
+    /// The player's game mode.
+    pub game_mode: GameMode,
+    /// The player's previous game mode, or -1 if there isn't one.
+    pub previous_game_mode: i8,
+    /// Whether the dimension is a debug world.
+    pub is_debug: bool,
+    /// Whether the dimension is superflat, which lowers the horizon.
+    pub is_flat: bool,
+    /// Where the player last died, for recovery compasses.
+    pub death_location: Option<DeathLocation>,
+    /// Ticks until the player can use a portal again.
+    #[mc(varint)]
+    pub portal_cooldown: u32,
+    /// Whether the server only accepts signed chat.
+    pub enforces_secure_chat: bool,
+}
+
+/// Sets the difficulty shown in the client's options.
+#[derive(Clone, PartialEq, Debug, Eq, Hash, McProto)]
+pub struct ChangeDifficulty {
+    /// The difficulty.
+    pub difficulty: Difficulty,
+    /// Whether the difficulty can't be changed.
+    pub locked: bool,
+}
+
+/// Sets where compasses point, and where the client spawns.
+#[derive(Clone, PartialEq, Debug, McProto)]
+pub struct SetDefaultSpawnPosition {
+    /// The spawn block.
+    pub location: Position,
+    /// The yaw to spawn facing, in degrees.
+    pub angle: f32,
+}
+
+/// Moves the player. The client confirms it with a [`ConfirmTeleportation`]
+/// carrying the same ID.
+#[derive(Clone, PartialEq, Debug, McProto)]
+pub struct SynchronizePlayerPosition {
+    /// The X coordinate, or the change in it if relative.
+    pub x: f64,
+    /// The Y coordinate, or the change in it if relative.
+    pub y: f64,
+    /// The Z coordinate, or the change in it if relative.
+    pub z: f64,
+    /// The yaw in degrees, or the change in it if relative.
+    pub yaw: f32,
+    /// The pitch in degrees, or the change in it if relative.
+    pub pitch: f32,
+    /// Which fields are relative: X, Y, Z, pitch and yaw from bit 0.
+    pub flags: u8,
+    /// The ID to confirm the teleport with.
+    #[mc(varint)]
+    pub teleport_id: u32,
+}
+
+/// Confirms a [`SynchronizePlayerPosition`].
+#[derive(Clone, PartialEq, Debug, Eq, Hash, McProto)]
+pub struct ConfirmTeleportation {
+    /// The ID of the teleport.
+    #[mc(varint)]
+    pub teleport_id: u32,
+}
+
+/// Moves the player, sent by the client as they walk.
+#[derive(Clone, PartialEq, Debug, McProto)]
+pub struct SetPlayerPosition {
+    /// The X coordinate.
+    pub x: f64,
+    /// The Y coordinate of the player's feet.
+    pub y: f64,
+    /// The Z coordinate.
+    pub z: f64,
+    /// Whether the player is on the ground.
+    pub on_ground: bool,
+}
+
+/// Moves and turns the player.
+#[derive(Clone, PartialEq, Debug, McProto)]
+pub struct SetPlayerPositionAndRotation {
+    /// The X coordinate.
+    pub x: f64,
+    /// The Y coordinate of the player's feet.
+    pub y: f64,
+    /// The Z coordinate.
+    pub z: f64,
+    /// The yaw in degrees.
+    pub yaw: f32,
+    /// The pitch in degrees.
+    pub pitch: f32,
+    /// Whether the player is on the ground.
+    pub on_ground: bool,
+}
+
+/// Turns the player.
+#[derive(Clone, PartialEq, Debug, McProto)]
+pub struct SetPlayerRotation {
+    /// The yaw in degrees.
+    pub yaw: f32,
+    /// The pitch in degrees.
+    pub pitch: f32,
+    /// Whether the player is on the ground.
+    pub on_ground: bool,
+}
+
+/// Tells the server whether the player is on the ground, when they haven't
+/// moved or turned.
+#[derive(Clone, PartialEq, Debug, Eq, Hash, McProto)]
+pub struct SetPlayerOnGround {
+    /// Whether the player is on the ground.
+    pub on_ground: bool,
+}
+
+/// A change to the game state, such as the weather or game mode.
+#[derive(Clone, PartialEq, Debug, McProto)]
+pub struct GameEvent {
+    /// Which event happened.
+    pub event: u8,
+    /// The event's value, if it has one.
+    pub value: f32,
+}
+
+impl GameEvent {
+    /// Tells the client to show the world once the chunk it's in arrives.
+    pub const START_WAITING_FOR_CHUNKS: u8 = 13;
+}
+
+/// Sets the chunk the client loads chunks around.
+#[derive(Clone, PartialEq, Debug, Eq, Hash, McProto)]
+pub struct SetCenterChunk {
+    /// The chunk's X coordinate.
+    #[mc(varint)]
+    pub x: i32,
+    /// The chunk's Z coordinate.
+    #[mc(varint)]
+    pub z: i32,
+}
+
+/// Starts a batch of chunks.
+#[derive(Clone, PartialEq, Debug, Eq, Hash, McProto)]
+pub struct ChunkBatchStart;
+
+/// Ends a batch of chunks. The client answers with a [`ChunkBatchReceived`].
+#[derive(Clone, PartialEq, Debug, Eq, Hash, McProto)]
+pub struct ChunkBatchFinished {
+    /// How many chunks were in the batch.
+    #[mc(varint)]
+    pub batch_size: u32,
+}
+
+/// Acknowledges a [`ChunkBatchFinished`].
+#[derive(Clone, PartialEq, Debug, McProto)]
+pub struct ChunkBatchReceived {
+    /// How many chunks per tick the client would like to receive.
+    pub chunks_per_tick: f32,
+}
+
+/// A block entity in a chunk, such as a chest or sign.
+#[derive(Clone, PartialEq, Debug, McProto)]
+pub struct BlockEntity {
+    /// The X coordinate within the chunk in the high nibble, and Z in the low.
+    pub packed_xz: u8,
+    /// The Y coordinate.
+    pub y: i16,
+    /// The ID of the block entity type.
+    #[mc(varint)]
+    pub kind: u32,
+    /// The block entity's data, without its position and ID.
+    #[mc(with = NetworkNbt)]
+    pub data: Nbt,
+}
+
+/// The light of a chunk's sections, including one below and one above the
+/// world.
+#[derive(Clone, PartialEq, Debug, Default, McProto)]
+pub struct LightData {
+    /// The sections with sky light data.
+    pub sky_light_mask: BitSet,
+    /// The sections with block light data.
+    pub block_light_mask: BitSet,
+    /// The sections whose sky light is all zero.
+    pub empty_sky_light_mask: BitSet,
+    /// The sections whose block light is all zero.
+    pub empty_block_light_mask: BitSet,
+    /// The sky light of each section in the mask, as 2048 bytes of nibbles.
+    pub sky_light: Vec<Vec<u8>>,
+    /// The block light of each section in the mask, as 2048 bytes of nibbles.
+    pub block_light: Vec<Vec<u8>>,
+}
+
+/// A chunk's blocks, biomes and light.
+#[derive(Clone, PartialEq, Debug, McProto)]
+pub struct ChunkData {
+    /// The chunk's X coordinate.
+    pub x: i32,
+    /// The chunk's Z coordinate.
+    pub z: i32,
+    /// The chunk's heightmaps, as a compound of packed long arrays.
+    #[mc(with = NetworkNbt)]
+    pub heightmaps: Nbt,
+    /// Every section from the bottom up.
+    #[mc(with = Sections)]
+    pub sections: Vec<ChunkSection>,
+    /// The chunk's block entities.
+    pub block_entities: Vec<BlockEntity>,
+    /// The chunk's light.
+    pub light: LightData,
+}
+
+/// Updates the light of a chunk the client already has.
+#[derive(Clone, PartialEq, Debug, McProto)]
+pub struct UpdateLight {
+    /// The chunk's X coordinate.
+    #[mc(varint)]
+    pub x: i32,
+    /// The chunk's Z coordinate.
+    #[mc(varint)]
+    pub z: i32,
+    /// The chunk's light.
+    pub light: LightData,
+}
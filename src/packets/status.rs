@@ -0,0 +1,29 @@
+//! Packets in the status state.
+
+use crate::McProto;
+
+/// Asks for the server's status.
+#[derive(Clone, PartialEq, Debug, Eq, Hash, McProto)]
+pub struct StatusRequest;
+
+/// The server's status, as JSON.
+#[derive(Clone, PartialEq, Debug, Eq, Hash, McProto)]
+pub struct StatusResponse {
+    /// The status JSON.
+    #[mc(max_len = 32767)]
+    pub json: String,
+}
+
+/// Asks the server to echo a payload, to measure latency.
+#[derive(Clone, PartialEq, Debug, Eq, Hash, McProto)]
+pub struct PingRequest {
+    /// An arbitrary payload.
+    pub payload: i64,
+}
+
+/// The payload of a [`PingRequest`], echoed back.
+#[derive(Clone, PartialEq, Debug, Eq, Hash, McProto)]
+pub struct PongResponse {
+    /// The payload from the request.
+    pub payload: i64,
+}
@@ -0,0 +1,202 @@
+//! The data-driven registries and tags sent to clients while configuring them,
+//! such as dimension types, biomes and damage types.
+
+use std::sync::LazyLock;
+
+use anyhow::{Context, anyhow, bail};
+use serde_json::{Map, Value};
+use simdnbt::owned::{BaseNbt, Nbt};
+
+use crate::{
+    json,
+    packets::{
+        GAME_VERSION,
+        configuration::{
+            FeatureFlags, KnownPack, RegistryData, RegistryEntry, RegistryTags, Tag, UpdateTags,
+        },
+    },
+    types::Identifier,
+};
+
+/// The registries the server sends, in the order they're sent.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Registries {
+    /// The enabled feature flags.
+    feature_flags: Vec<Identifier>,
+    /// Each registry and its entries with their data. Entries are numbered
+    /// in order.
+    data: Vec<RegistryData>,
+    /// The tags of each registry that has them.
+    tags: UpdateTags,
+}
+
+/// The vanilla registries, bundled with the server.
+static VANILLA: LazyLock<Registries> = LazyLock::new(|| {
+    serde_json::from_str(include_str!("../data/registries.json"))
+        .map_err(anyhow::Error::from)
+        .and_then(|json| Registries::from_json(&json))
+        .expect("bundled registry data is valid")
+});
+
+impl Registries {
+    /// The vanilla registries for [`GAME_VERSION`], bundled with the server.
+    #[must_use]
+    pub fn vanilla() -> &'static Self {
+        &VANILLA
+    }
+
+    /// The pack the vanilla registries come from. Clients that have it
+    /// already don't need the entries' data.
+    #[must_use]
+    pub fn core_pack() -> KnownPack {
+        KnownPack {
+            namespace: Identifier::DEFAULT_NAMESPACE.to_owned(),
+            id: "core".to_owned(),
+            version: GAME_VERSION.to_owned(),
+        }
+    }
+
+    /// Load registries from JSON, with the enabled `feature_flags`, each
+    /// registry's entries under `registries`, and each registry's tags under
+    /// `tags`. Entries are written the way they are in a data pack, and tags
+    /// list their entries by identifier.
+    ///
+    /// # Errors
+    /// If the JSON isn't in that shape, or a tag names an entry that doesn't
+    /// exist, return an error.
+    pub fn from_json(json: &Value) -> Result<Self, anyhow::Error> {
+        let feature_flags = json
+            .get("feature_flags")
+            .and_then(Value::as_array)
+            .ok_or_else(|| anyhow!("missing feature_flags"))?
+            .iter()
+            .map(parse_identifier)
+            .collect::<Result<_, _>>()?;
+
+        let mut data = Vec::new();
+        for (registry, entries) in object(json, "registries")? {
+            let entries = entries
+                .as_object()
+                .ok_or_else(|| anyhow!("registry {registry} isn't an object"))?
+                .iter()
+                .map(|(id, data)| {
+                    let data = json::to_nbt(data)
+                        .into_compound()
+                        .ok_or_else(|| anyhow!("entry {id} of {registry} isn't an object"))?;
+                    Ok(RegistryEntry {
+                        id: id.parse()?,
+                        data: Some(Nbt::Some(BaseNbt::new("", data))),
+                    })
+                })
+                .collect::<Result<_, anyhow::Error>>()?;
+            data.push(RegistryData {
+                registry: registry.parse()?,
+                entries,
+            });
+        }
+
+        let mut out = Self {
+            feature_flags,
+            data,
+            tags: UpdateTags {
+                registries: Vec::new(),
+            },
+        };
+        for (registry, tags) in object(json, "tags")? {
+            let registry: Identifier = registry.parse()?;
+            let tags = tags
+                .as_object()
+                .ok_or_else(|| anyhow!("tags of {registry} aren't an object"))?
+                .iter()
+                .map(|(name, entries)| {
+                    let entries = entries
+                        .as_array()
+                        .ok_or_else(|| anyhow!("tag {name} isn't an array"))?
+                        .iter()
+                        .map(|entry| {
+                            let entry = parse_identifier(entry)?;
+                            out.id(&registry, &entry)
+                                .with_context(|| format!("tag {name} has unknown entry {entry}"))
+                        })
+                        .collect::<Result<_, _>>()?;
+                    Ok(Tag {
+                        name: name.parse()?,
+                        entries,
+                    })
+                })
+                .collect::<Result<_, anyhow::Error>>()?;
+            out.tags.registries.push(RegistryTags { registry, tags });
+        }
+        Ok(out)
+    }
+
+    /// The numeric ID of `entry` in `registry`, if both exist.
+    #[must_use]
+    pub fn id(&self, registry: &Identifier, entry: &Identifier) -> Option<u32> {
+        let data = self.data.iter().find(|data| data.registry == *registry)?;
+        let index = data.entries.iter().position(|e| e.id == *entry)?;
+        Some(index as u32)
+    }
+
+    /// The number of entries in `registry`, or zero if it doesn't exist.
+    #[must_use]
+    pub fn count(&self, registry: &Identifier) -> usize {
+        self.data
+            .iter()
+            .find(|data| data.registry == *registry)
+            .map_or(0, |data| data.entries.len())
+    }
+
+    /// The data of `entry` in `registry`, if both exist.
+    #[must_use]
+    pub fn get(&self, registry: &Identifier, entry: &Identifier) -> Option<&BaseNbt> {
+        let data = self.data.iter().find(|data| data.registry == *registry)?;
+        match &data.entries.iter().find(|e| e.id == *entry)?.data {
+            Some(Nbt::Some(nbt)) => Some(nbt),
+            _ => None,
+        }
+    }
+
+    /// The Feature Flags packet.
+    #[must_use]
+    pub fn feature_flags(&self) -> FeatureFlags {
+        FeatureFlags {
+            flags: self.feature_flags.clone(),
+        }
+    }
+
+    /// A Registry Data packet for each registry. Entries only have their data
+    /// if `with_data` is set, which is needed unless the client knows the
+    /// [core pack](Self::core_pack).
+    #[must_use]
+    pub fn registry_data(&self, with_data: bool) -> Vec<RegistryData> {
+        let mut data = self.data.clone();
+        if !with_data {
+            for entry in data.iter_mut().flat_map(|data| &mut data.entries) {
+                entry.data = None;
+            }
+        }
+        data
+    }
+
+    /// The Update Tags packet.
+    #[must_use]
+    pub fn update_tags(&self) -> UpdateTags {
+        self.tags.clone()
+    }
+}
+
+/// The entries of the object under `key`.
+fn object<'a>(json: &'a Value, key: &str) -> Result<&'a Map<String, Value>, anyhow::Error> {
+    json.get(key)
+        .and_then(Value::as_object)
+        .ok_or_else(|| anyhow!("missing {key}"))
+}
+
+/// Parse an identifier from a JSON string.
+fn parse_identifier(json: &Value) -> Result<Identifier, anyhow::Error> {
+    match json.as_str() {
+        Some(s) => Ok(s.parse()?),
+        None => bail!("expected an identifier, found {json}"),
+    }
+}
@@ -0,0 +1,403 @@
+//! The TCP listener, which spawns a thread per client.
+
+use std::{
+    io::ErrorKind,
+    net::{Shutdown as NetShutdown, SocketAddr, TcpListener, TcpStream},
+    sync::{
+        Arc, Mutex, MutexGuard, PoisonError,
+        atomic::{AtomicBool, AtomicI32, Ordering},
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use anyhow::bail;
+
+use crate::{
+    anvil::ChunkStorage,
+    auth::SessionService,
+    blocks::Blocks,
+    config::Config,
+    connection::Connection,
+    crypto::{self, rsa::RsaPrivateKey},
+    game::{Game, Inbound},
+    level::LevelData,
+    packets::play::ChunkData,
+    registries::Registries,
+    status,
+    tick::{TickLoop, TickStats},
+    types::Uuid,
+    world::World,
+};
+
+/// A player that's online.
+#[derive(Clone, PartialEq, Debug, Eq, Hash)]
+pub struct Player {
+    /// The player's username.
+    pub name: String,
+    /// The player's UUID.
+    pub id: Uuid,
+    /// The player's smoothed round-trip time, for the ping shown in the tab
+    /// list. Zero until they answer a keep-alive.
+    pub latency: Duration,
+}
+
+/// State shared between the listener and every connection.
+#[derive(Debug)]
+pub struct Shared {
+    /// The server configuration.
+    pub config: Config,
+    /// The server list icon, as a `data:` URL.
+    pub favicon: Option<String>,
+    /// The key pair that encrypts connections, in online mode.
+    pub key: Option<RsaPrivateKey>,
+    /// The session server that authenticates players, in online mode.
+    pub session: Option<Box<dyn SessionService>>,
+    /// The world players spawn into.
+    pub world: World,
+    /// The world's metadata, as it was when the server started.
+    pub level: LevelData,
+    /// The chunks saved in the world's region files.
+    chunks: ChunkStorage,
+    /// The players that are online.
+    players: Mutex<Vec<Player>>,
+    /// The entity ID to give the next entity.
+    next_entity_id: AtomicI32,
+    /// Packets from players in game, waiting for the next tick.
+    inbound: Mutex<Vec<Inbound>>,
+    /// How well the tick loop is keeping up, as of the last tick.
+    tick_stats: Mutex<TickStats>,
+}
+
+impl Shared {
+    /// The size of the RSA key generated for online mode, matching vanilla.
+    const KEY_BITS: usize = 1024;
+
+    /// Load everything the configuration refers to, set up the world from its
+    /// `level.dat` if it has one, and generate a key pair if it's in online
+    /// mode.
+    ///
+    /// # Errors
+    /// If online mode is enabled without a session service, or the world,
+    /// its `level.dat` or the key pair can't be set up, return an error.
+    pub fn new(
+        config: Config,
+        session: Option<Box<dyn SessionService>>,
+    ) -> Result<Self, anyhow::Error> {
+        let favicon = status::load_favicon(&config.icon).unwrap_or_else(|e| {
+            eprintln!("not using server icon: {e:#}");
+            None
+        });
+        let key = if config.online_mode {
+            if session.is_none() {
+                bail!("online-mode needs a session service, and none was provided");
+            }
+            Some(RsaPrivateKey::generate(Self::KEY_BITS)?)
+        } else {
+            None
+        };
+        let mut world = World::flat(Registries::vanilla())?;
+        let level = load_level(&config, &world)?;
+        world.spawn = level.spawn;
+        let chunks = ChunkStorage::new(config.region_dir());
+        Ok(Self {
+            config,
+            favicon,
+            key,
+            session,
+            world,
+            level,
+            chunks,
+            players: Mutex::new(Vec::new()),
+            next_entity_id: AtomicI32::new(0),
+            inbound: Mutex::new(Vec::new()),
+            tick_stats: Mutex::new(TickStats::default()),
+        })
+    }
+
+    /// A new entity ID, unique until it wraps around.
+    pub fn next_entity_id(&self) -> i32 {
+        self.next_entity_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Record the latency of the player with UUID `id`, if they're online.
+    pub fn set_latency(&self, id: Uuid, latency: Duration) {
+        if let Some(player) = self.players().iter_mut().find(|player| player.id == id) {
+            player.latency = latency;
+        }
+    }
+
+    /// Queue a packet from a player for the next tick.
+    pub fn queue(&self, inbound: Inbound) {
+        self.inbound
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(inbound);
+    }
+
+    /// Take the packets queued since this was last called, oldest first.
+    pub fn take_inbound(&self) -> Vec<Inbound> {
+        std::mem::take(&mut *self.inbound.lock().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    /// How well the tick loop is keeping up, as of the last tick.
+    pub fn tick_stats(&self) -> TickStats {
+        *self
+            .tick_stats
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Record how well the tick loop is keeping up.
+    pub fn set_tick_stats(&self, stats: TickStats) {
+        *self
+            .tick_stats
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = stats;
+    }
+
+    /// The players that are online.
+    pub fn players(&self) -> MutexGuard<'_, Vec<Player>> {
+        self.players.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// The chunk at `x`, `z`: the one saved in the world's region files if
+    /// there is one, and otherwise the flat world's.
+    pub fn chunk(&self, x: i32, z: i32) -> ChunkData {
+        let saved = self.chunks.chunk(x, z).and_then(|chunk| {
+            chunk
+                .map(|chunk| {
+                    self.world
+                        .load_chunk(&chunk, Registries::vanilla(), Blocks::vanilla())
+                })
+                .transpose()
+        });
+        match saved {
+            Ok(Some(chunk)) => chunk,
+            Ok(None) => self.world.chunk(x, z),
+            Err(e) => {
+                eprintln!("failed to load chunk {x}, {z}: {e:#}");
+                self.world.chunk(x, z)
+            }
+        }
+    }
+
+    /// Save the world's metadata, with the time from `game`.
+    ///
+    /// # Errors
+    /// If `level.dat` can't be written, return an error.
+    pub fn save_level(&self, game: &Game) -> Result<(), anyhow::Error> {
+        let mut level = self.level.clone();
+        game.write_level(&mut level);
+        level.save(self.config.level_path())
+    }
+
+    /// Save the chunks that have changed back to the world's region files.
+    ///
+    /// # Errors
+    /// If a chunk can't be written, return an error.
+    pub fn save_chunks(&self) -> Result<(), anyhow::Error> {
+        self.chunks.save()
+    }
+
+    /// Stop keeping the saved chunks that are out of view of every player,
+    /// given the chunks the players are in.
+    pub fn unload_chunks(&self, players: &[(i32, i32)]) {
+        let distance = self.config.view_distance.cast_signed();
+        self.chunks.unload(|x, z| {
+            players
+                .iter()
+                .any(|&(px, pz)| (x - px).abs() <= distance && (z - pz).abs() <= distance)
+        });
+    }
+}
+
+/// Load the world's `level.dat`, or start a new one with a random seed if it
+/// doesn't have one yet.
+fn load_level(config: &Config, world: &World) -> Result<LevelData, anyhow::Error> {
+    let Some(level) = LevelData::load(config.level_path())? else {
+        let mut seed = [0; 8];
+        crypto::random_bytes(&mut seed)?;
+        return Ok(LevelData::new(world.spawn, i64::from_le_bytes(seed)));
+    };
+    if level.data_version > LevelData::DATA_VERSION {
+        eprintln!(
+            "{} was saved by a newer version of the game (data version {})",
+            config.level_path().display(),
+            level.data_version
+        );
+    }
+    for pack in &level.enabled_data_packs {
+        if pack != "vanilla" {
+            eprintln!("data pack {pack} is enabled, but only vanilla is supported");
+        }
+    }
+    Ok(level)
+}
+
+/// A handle used to stop a running [`Server`].
+#[derive(Clone, Debug, Default)]
+pub struct Shutdown(Arc<AtomicBool>);
+
+impl Shutdown {
+    /// Ask the server to stop.
+    pub fn trigger(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    /// Whether the server has been asked to stop.
+    #[must_use]
+    pub fn is_triggered(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// A bound server, ready to accept clients.
+#[derive(Debug)]
+pub struct Server {
+    /// The listening socket.
+    listener: TcpListener,
+    /// Set when the server should stop.
+    shutdown: Shutdown,
+    /// State shared with every connection.
+    shared: Arc<Shared>,
+}
+
+impl Server {
+    /// How long to wait between checking for new clients and shutdown.
+    const POLL_INTERVAL: Duration = Duration::from_millis(50);
+    /// How many ticks apart the world is saved, matching vanilla's five
+    /// minutes.
+    const AUTOSAVE_TICKS: u64 = 6000;
+
+    /// Bind to the address in `config`.
+    ///
+    /// # Errors
+    /// If the address can't be bound, or `config` enables online mode, return
+    /// an error.
+    pub fn bind(config: Config) -> Result<Self, anyhow::Error> {
+        Self::bind_shared(Shared::new(config, None)?)
+    }
+
+    /// Bind to the address in `config`, authenticating online-mode players
+    /// with `session`.
+    ///
+    /// # Errors
+    /// If the address can't be bound, return an error.
+    pub fn bind_with_session(
+        config: Config,
+        session: Box<dyn SessionService>,
+    ) -> Result<Self, anyhow::Error> {
+        Self::bind_shared(Shared::new(config, Some(session))?)
+    }
+
+    /// Bind to the address in the configuration of `shared`.
+    fn bind_shared(shared: Shared) -> Result<Self, anyhow::Error> {
+        let listener = TcpListener::bind(shared.config.address())?;
+        listener.set_nonblocking(true)?;
+        Ok(Self {
+            listener,
+            shutdown: Shutdown::default(),
+            shared: Arc::new(shared),
+        })
+    }
+
+    /// The address the server is actually listening on.
+    ///
+    /// # Errors
+    /// If the address can't be retrieved, return an error.
+    pub fn local_addr(&self) -> Result<SocketAddr, anyhow::Error> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// A handle that can stop the server from another thread.
+    #[must_use]
+    pub fn shutdown_handle(&self) -> Shutdown {
+        self.shutdown.clone()
+    }
+
+    /// Run the game on a tick thread and accept clients until shut down, then
+    /// disconnect every client and wait for every thread to finish. A client
+    /// that can't be set up is dropped, and the server carries on.
+    ///
+    /// # Errors
+    /// If the tick thread can't be started, return an error.
+    pub fn run(self) -> Result<(), anyhow::Error> {
+        let tick = self.spawn_tick()?;
+        let mut clients: Vec<(JoinHandle<()>, TcpStream)> = Vec::new();
+        while !self.shutdown.is_triggered() {
+            match self.listener.accept() {
+                Ok((stream, addr)) => match self.spawn(stream, addr) {
+                    Ok(client) => clients.push(client),
+                    Err(e) => eprintln!("failed to set up client {addr}: {e:#}"),
+                },
+                Err(e) if e.kind() == ErrorKind::WouldBlock => thread::sleep(Self::POLL_INTERVAL),
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => eprintln!("failed to accept client: {e}"),
+            }
+            clients.retain(|(handle, _)| !handle.is_finished());
+        }
+
+        for (_, stream) in &clients {
+            let _ = stream.shutdown(NetShutdown::Both);
+        }
+        for (handle, _) in clients {
+            let _ = handle.join();
+        }
+        let _ = tick.join();
+        Ok(())
+    }
+
+    /// Spawn the thread that runs the game at [`TickLoop::TPS`] until shut
+    /// down, saving the world every [`AUTOSAVE_TICKS`](Self::AUTOSAVE_TICKS)
+    /// and once more at the end.
+    fn spawn_tick(&self) -> Result<JoinHandle<()>, anyhow::Error> {
+        let shared = Arc::clone(&self.shared);
+        let shutdown = self.shutdown.clone();
+        let handle = thread::Builder::new()
+            .name("tick".to_owned())
+            .spawn(move || {
+                let mut game = Game::from_level(&shared.level);
+                let save = |game: &Game| {
+                    if let Err(e) = shared.save_level(game) {
+                        eprintln!("failed to save the world: {e:#}");
+                    }
+                    if let Err(e) = shared.save_chunks() {
+                        eprintln!("failed to save chunks: {e:#}");
+                    }
+                };
+                let mut ticks = 0u64;
+                TickLoop::new(Instant::now()).run(&shutdown, |metrics| {
+                    shared.set_tick_stats(metrics.stats(Instant::now()));
+                    game.tick(&shared);
+                    ticks += 1;
+                    if ticks.is_multiple_of(Self::AUTOSAVE_TICKS) {
+                        save(&game);
+                    }
+                });
+                save(&game);
+            })?;
+        Ok(handle)
+    }
+
+    /// Spawn the thread for a newly accepted client, returning its handle and a
+    /// clone of its socket to close it with.
+    fn spawn(
+        &self,
+        stream: TcpStream,
+        addr: SocketAddr,
+    ) -> Result<(JoinHandle<()>, TcpStream), anyhow::Error> {
+        stream.set_nonblocking(false)?;
+        stream.set_nodelay(true)?;
+        let closer = stream.try_clone()?;
+        let shared = Arc::clone(&self.shared);
+        let handle = thread::Builder::new()
+            .name(format!("client {addr}"))
+            .spawn(move || {
+                if let Err(e) = Connection::new(stream, shared).and_then(Connection::run) {
+                    eprintln!("{addr}: {e:#}");
+                }
+            })?;
+        Ok((handle, closer))
+    }
+}
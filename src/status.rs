@@ -0,0 +1,140 @@
+//! Server list ping responses.
+
+use std::{fs, io::ErrorKind, path::Path};
+
+use anyhow::{Context, bail};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use serde_json::{Value, json};
+
+use crate::{
+    packets::{GAME_VERSION, PROTOCOL_VERSION},
+    server::Shared,
+    text::TextComponent,
+};
+
+/// The most players listed in the sample shown when hovering over the player count.
+pub const SAMPLE_SIZE: usize = 12;
+
+/// The first byte of a pre-1.7 server list ping.
+pub const LEGACY_PING: u8 = 0xFE;
+/// The protocol version reported to pre-1.7 clients. It's newer than any of them,
+/// so they show the version name as incompatible, matching vanilla.
+pub const LEGACY_PROTOCOL_VERSION: u32 = 127;
+
+/// Which pre-1.7 server list ping a client sent.
+#[derive(Clone, Copy, PartialEq, Debug, Eq, Hash)]
+pub enum LegacyPing {
+    /// Beta 1.8 to 1.3, which sends `0xFE` on its own.
+    Beta,
+    /// 1.4 to 1.6, which sends `0xFE 0x01`, optionally followed by a
+    /// `MC|PingHost` plugin message.
+    Extended,
+}
+
+/// Build the kick packet sent in response to a pre-1.7 server list ping: `0xFF`,
+/// then a UTF-16BE string prefixed with its length in code units. The MOTD is
+/// the [`description`] as legacy text, without its formatting codes for Beta
+/// clients, which split the response on `§`.
+#[must_use]
+pub fn legacy_response(shared: &Shared, ping: LegacyPing) -> Vec<u8> {
+    let motd = description(&shared.config.motd).to_legacy();
+    let online = shared.players().len();
+    let max = shared.config.max_players;
+    let text = match ping {
+        LegacyPing::Beta => {
+            let motd = strip_codes(&motd);
+            format!("{motd}\u{a7}{online}\u{a7}{max}")
+        }
+        LegacyPing::Extended => {
+            format!("\u{a7}1\0{LEGACY_PROTOCOL_VERSION}\0{GAME_VERSION}\0{motd}\0{online}\0{max}")
+        }
+    };
+    let units: Vec<u16> = text.encode_utf16().collect();
+    let mut out = Vec::with_capacity(3 + units.len() * 2);
+    out.push(0xFF);
+    out.extend_from_slice(&(units.len() as u16).to_be_bytes());
+    for unit in units {
+        out.extend_from_slice(&unit.to_be_bytes());
+    }
+    out
+}
+
+/// `text` without its `§` formatting codes.
+fn strip_codes(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\u{a7}' {
+            chars.next();
+        } else {
+            stripped.push(c);
+        }
+    }
+    stripped
+}
+
+/// The MOTD as a text component. A MOTD that's a JSON object or array, such
+/// as `{"text":"hi","color":"gold"}`, is parsed as a component, and anything
+/// else is literal text, where `§` formatting codes still work.
+#[must_use]
+pub fn description(motd: &str) -> TextComponent {
+    serde_json::from_str(motd)
+        .ok()
+        .filter(|json: &Value| json.is_object() || json.is_array())
+        .and_then(|json| TextComponent::from_json(&json).ok())
+        .unwrap_or_else(|| TextComponent::text(motd))
+}
+
+/// Build the status JSON sent in response to a status request.
+#[must_use]
+pub fn response(shared: &Shared) -> Value {
+    let players = shared.players();
+    let sample: Vec<Value> = players
+        .iter()
+        .take(SAMPLE_SIZE)
+        .map(|player| json!({"name": player.name, "id": player.id.to_string()}))
+        .collect();
+
+    let mut json = json!({
+        "version": {"name": GAME_VERSION, "protocol": PROTOCOL_VERSION},
+        "players": {
+            "max": shared.config.max_players,
+            "online": players.len(),
+            "sample": sample,
+        },
+        "description": description(&shared.config.motd).to_json(),
+        "enforcesSecureChat": false,
+    });
+    if let Some(favicon) = &shared.favicon {
+        json["favicon"] = favicon.as_str().into();
+    }
+    json
+}
+
+/// Load the server icon at `path` as a `data:` URL, or `None` if there isn't one.
+///
+/// # Errors
+/// If the file can't be read or isn't a 64x64 PNG, return an error.
+pub fn load_favicon(path: &Path) -> Result<Option<String>, anyhow::Error> {
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("reading {}", path.display())),
+    };
+    // The signature, then the IHDR chunk's length and type, then its width and height.
+    let Some(header) = bytes.get(..24) else {
+        bail!("{} is too short to be a PNG", path.display());
+    };
+    if header[..8] != *b"\x89PNG\r\n\x1a\n" || header[12..16] != *b"IHDR" {
+        bail!("{} isn't a PNG", path.display());
+    }
+    let width = u32::from_be_bytes([header[16], header[17], header[18], header[19]]);
+    let height = u32::from_be_bytes([header[20], header[21], header[22], header[23]]);
+    if (width, height) != (64, 64) {
+        bail!("{} is {width}x{height}, not 64x64", path.display());
+    }
+    Ok(Some(format!(
+        "data:image/png;base64,{}",
+        STANDARD.encode(&bytes)
+    )))
+}
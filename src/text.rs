@@ -0,0 +1,794 @@
+//! Text components: the styled, translatable text used for chat, disconnect
+//! reasons, titles, item names and the MOTD.
+//!
+//! Components are sent as network NBT since 1.20.3, and as JSON before that
+//! and in the status response and login disconnects. Both go through the same
+//! JSON [`Value`], which is converted to NBT the way vanilla does.
+
+use core::{
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+
+use serde_json::{Map, Value, json};
+use simdnbt::owned::NbtTag;
+
+use crate::{
+    McProto, McProtoSelf, McReader, McWriter, ProtocolError, json,
+    types::{Identifier, NetworkNbt, Uuid},
+};
+
+/// A piece of text, with a style and children that inherit it.
+#[derive(Clone, PartialEq, Debug)]
+pub struct TextComponent {
+    /// What this component shows.
+    pub content: Content,
+    /// How it's styled. Unset parts are inherited from the parent.
+    pub style: Style,
+    /// Components shown after this one, which inherit its style.
+    pub extra: Vec<TextComponent>,
+}
+
+/// What a [`TextComponent`] shows.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Content {
+    /// Literal text.
+    Text(String),
+    /// A translation key, looked up in the client's language.
+    Translatable {
+        /// The translation key.
+        key: String,
+        /// What to show if the key doesn't exist.
+        fallback: Option<String>,
+        /// The arguments substituted into the translation.
+        with: Vec<TextComponent>,
+    },
+    /// The score of an entity on an objective.
+    Score {
+        /// The name of the score holder, or a selector.
+        name: String,
+        /// The objective.
+        objective: String,
+    },
+    /// The names of the entities matching a selector.
+    Selector {
+        /// The selector.
+        pattern: String,
+        /// What goes between names, `, ` if unset.
+        separator: Option<Box<TextComponent>>,
+    },
+    /// The key bound to a control, such as `key.jump`.
+    Keybind(String),
+    /// Values read from NBT.
+    Nbt {
+        /// The NBT path to read.
+        path: String,
+        /// Whether to parse the values as text components.
+        interpret: Option<bool>,
+        /// What goes between values, `, ` if unset.
+        separator: Option<Box<TextComponent>>,
+        /// Where the NBT comes from.
+        source: NbtSource,
+    },
+}
+
+/// Where an NBT text component reads its NBT from.
+#[derive(Clone, PartialEq, Debug)]
+pub enum NbtSource {
+    /// The block entity at a position, as coordinates.
+    Block(String),
+    /// The entities matching a selector.
+    Entity(String),
+    /// Command storage.
+    Storage(Identifier),
+}
+
+/// The style of a [`TextComponent`]. Every part is optional, and unset parts
+/// are inherited.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Style {
+    /// The color.
+    pub color: Option<Color>,
+    /// Whether the text is bold.
+    pub bold: Option<bool>,
+    /// Whether the text is italic.
+    pub italic: Option<bool>,
+    /// Whether the text is underlined.
+    pub underlined: Option<bool>,
+    /// Whether the text is struck through.
+    pub strikethrough: Option<bool>,
+    /// Whether the text is scrambled.
+    pub obfuscated: Option<bool>,
+    /// The font, such as `minecraft:uniform`.
+    pub font: Option<Identifier>,
+    /// Text inserted into the chat box when the component is shift-clicked.
+    pub insertion: Option<String>,
+    /// What happens when the component is clicked.
+    pub click_event: Option<ClickEvent>,
+    /// What's shown when the component is hovered over.
+    pub hover_event: Option<HoverEvent>,
+}
+
+impl Style {
+    /// Whether nothing is set.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// A text color: one of the sixteen named colors, or any RGB color.
+#[derive(Clone, Copy, PartialEq, Debug, Eq, Hash)]
+pub enum Color {
+    /// `#000000`.
+    Black,
+    /// `#0000AA`.
+    DarkBlue,
+    /// `#00AA00`.
+    DarkGreen,
+    /// `#00AAAA`.
+    DarkAqua,
+    /// `#AA0000`.
+    DarkRed,
+    /// `#AA00AA`.
+    DarkPurple,
+    /// `#FFAA00`.
+    Gold,
+    /// `#AAAAAA`.
+    Gray,
+    /// `#555555`.
+    DarkGray,
+    /// `#5555FF`.
+    Blue,
+    /// `#55FF55`.
+    Green,
+    /// `#55FFFF`.
+    Aqua,
+    /// `#FF5555`.
+    Red,
+    /// `#FF55FF`.
+    LightPurple,
+    /// `#FFFF55`.
+    Yellow,
+    /// `#FFFFFF`.
+    White,
+    /// A 24-bit RGB color.
+    Rgb(u32),
+}
+
+impl Color {
+    /// The named colors, in the order of their legacy formatting codes.
+    const NAMED: [(Self, &str); 16] = [
+        (Self::Black, "black"),
+        (Self::DarkBlue, "dark_blue"),
+        (Self::DarkGreen, "dark_green"),
+        (Self::DarkAqua, "dark_aqua"),
+        (Self::DarkRed, "dark_red"),
+        (Self::DarkPurple, "dark_purple"),
+        (Self::Gold, "gold"),
+        (Self::Gray, "gray"),
+        (Self::DarkGray, "dark_gray"),
+        (Self::Blue, "blue"),
+        (Self::Green, "green"),
+        (Self::Aqua, "aqua"),
+        (Self::Red, "red"),
+        (Self::LightPurple, "light_purple"),
+        (Self::Yellow, "yellow"),
+        (Self::White, "white"),
+    ];
+}
+
+impl Display for Color {
+    /// Format as the color's name, or `#RRGGBB`.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Rgb(rgb) => write!(f, "#{:06X}", rgb & 0xFF_FFFF),
+            named => {
+                let (_, name) = Self::NAMED
+                    .iter()
+                    .find(|(color, _)| color == named)
+                    .unwrap_or(&(Self::White, "white"));
+                f.write_str(name)
+            }
+        }
+    }
+}
+
+impl FromStr for Color {
+    type Err = ProtocolError;
+    /// Parse a color's name, or `#RRGGBB`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(hex) = s.strip_prefix('#')
+            && hex.len() == 6
+            && let Ok(rgb) = u32::from_str_radix(hex, 16)
+        {
+            return Ok(Self::Rgb(rgb));
+        }
+        Self::NAMED
+            .iter()
+            .find(|(_, name)| *name == s)
+            .map(|(color, _)| *color)
+            .ok_or_else(|| invalid(format!("unknown color {s:?}")))
+    }
+}
+
+/// What happens when a component is clicked.
+#[derive(Clone, PartialEq, Debug)]
+pub enum ClickEvent {
+    /// Open a URL, after asking.
+    OpenUrl(String),
+    /// Run a command, or send a chat message.
+    RunCommand(String),
+    /// Put text in the chat box.
+    SuggestCommand(String),
+    /// Turn to a page of the open book.
+    ChangePage(u32),
+    /// Copy text to the clipboard.
+    CopyToClipboard(String),
+}
+
+/// What's shown when a component is hovered over.
+#[derive(Clone, PartialEq, Debug)]
+pub enum HoverEvent {
+    /// Another component.
+    ShowText(Box<TextComponent>),
+    /// An item's tooltip.
+    ShowItem {
+        /// The item.
+        id: Identifier,
+        /// How many there are.
+        count: i32,
+    },
+    /// An entity's name, type and UUID.
+    ShowEntity {
+        /// The entity type.
+        kind: Identifier,
+        /// The entity's UUID.
+        id: Uuid,
+        /// The entity's name.
+        name: Option<Box<TextComponent>>,
+    },
+}
+
+/// An error about a malformed component.
+fn invalid(message: impl Into<String>) -> ProtocolError {
+    ProtocolError::invalid(None, message)
+}
+
+/// Read a required string field of a component.
+fn string_field(json: &Value, key: &str) -> Result<String, ProtocolError> {
+    json.get(key)
+        .and_then(Value::as_str)
+        .map(str::to_owned)
+        .ok_or_else(|| invalid(format!("text component is missing {key:?}")))
+}
+
+/// Read an optional field of a component with `parse`, failing if it's
+/// present but malformed.
+fn optional_field<T>(
+    json: &Value,
+    key: &str,
+    parse: impl FnOnce(&Value) -> Option<T>,
+) -> Result<Option<T>, ProtocolError> {
+    json.get(key)
+        .map(|value| {
+            parse(value).ok_or_else(|| invalid(format!("invalid {key:?} in text component")))
+        })
+        .transpose()
+}
+
+/// Read an optional component field, such as a separator.
+fn component_field(json: &Value, key: &str) -> Result<Option<Box<TextComponent>>, ProtocolError> {
+    json.get(key)
+        .map(|value| TextComponent::from_json(value).map(Box::new))
+        .transpose()
+}
+
+impl TextComponent {
+    /// Literal text with no style.
+    #[must_use]
+    pub fn text(text: impl Into<String>) -> Self {
+        Content::Text(text.into()).into()
+    }
+
+    /// A translation key with no arguments and no style.
+    #[must_use]
+    pub fn translatable(key: impl Into<String>) -> Self {
+        Content::Translatable {
+            key: key.into(),
+            fallback: None,
+            with: Vec::new(),
+        }
+        .into()
+    }
+
+    /// Add an argument to a translatable component.
+    ///
+    /// # Panics
+    /// If the component isn't translatable.
+    #[must_use]
+    pub fn with_arg(mut self, arg: impl Into<TextComponent>) -> Self {
+        let Content::Translatable { with, .. } = &mut self.content else {
+            panic!("added an argument to a component that isn't translatable");
+        };
+        with.push(arg.into());
+        self
+    }
+
+    /// Set the color.
+    #[must_use]
+    pub fn color(mut self, color: Color) -> Self {
+        self.style.color = Some(color);
+        self
+    }
+
+    /// Set whether the text is bold.
+    #[must_use]
+    pub fn bold(mut self, bold: bool) -> Self {
+        self.style.bold = Some(bold);
+        self
+    }
+
+    /// Set whether the text is italic.
+    #[must_use]
+    pub fn italic(mut self, italic: bool) -> Self {
+        self.style.italic = Some(italic);
+        self
+    }
+
+    /// Set the click event.
+    #[must_use]
+    pub fn on_click(mut self, event: ClickEvent) -> Self {
+        self.style.click_event = Some(event);
+        self
+    }
+
+    /// Set the hover event.
+    #[must_use]
+    pub fn on_hover(mut self, event: HoverEvent) -> Self {
+        self.style.hover_event = Some(event);
+        self
+    }
+
+    /// Add a child component.
+    #[must_use]
+    pub fn append(mut self, child: impl Into<TextComponent>) -> Self {
+        self.extra.push(child.into());
+        self
+    }
+
+    /// Whether this is just literal text, which is sent as a bare string.
+    fn is_plain(&self) -> bool {
+        matches!(self.content, Content::Text(_)) && self.style.is_empty() && self.extra.is_empty()
+    }
+
+    /// This component as JSON, the way vanilla writes it: literal text with no
+    /// style or children is a bare string, and everything else is an object.
+    #[must_use]
+    pub fn to_json(&self) -> Value {
+        if let Content::Text(text) = &self.content
+            && self.is_plain()
+        {
+            return text.as_str().into();
+        }
+        let mut json = Value::Object(Map::new());
+        match &self.content {
+            Content::Text(text) => json["text"] = text.as_str().into(),
+            Content::Translatable {
+                key,
+                fallback,
+                with,
+            } => {
+                json["translate"] = key.as_str().into();
+                if let Some(fallback) = fallback {
+                    json["fallback"] = fallback.as_str().into();
+                }
+                if !with.is_empty() {
+                    json["with"] = with.iter().map(Self::to_json).collect();
+                }
+            }
+            Content::Score { name, objective } => {
+                json["score"] = json!({"name": name, "objective": objective});
+            }
+            Content::Selector { pattern, separator } => {
+                json["selector"] = pattern.as_str().into();
+                if let Some(separator) = separator {
+                    json["separator"] = separator.to_json();
+                }
+            }
+            Content::Keybind(key) => json["keybind"] = key.as_str().into(),
+            Content::Nbt {
+                path,
+                interpret,
+                separator,
+                source,
+            } => {
+                json["nbt"] = path.as_str().into();
+                if let Some(interpret) = interpret {
+                    json["interpret"] = (*interpret).into();
+                }
+                if let Some(separator) = separator {
+                    json["separator"] = separator.to_json();
+                }
+                match source {
+                    NbtSource::Block(pos) => json["block"] = pos.as_str().into(),
+                    NbtSource::Entity(selector) => json["entity"] = selector.as_str().into(),
+                    NbtSource::Storage(id) => json["storage"] = id.to_string().into(),
+                }
+            }
+        }
+        self.style.write_json(&mut json);
+        if !self.extra.is_empty() {
+            json["extra"] = self.extra.iter().map(Self::to_json).collect();
+        }
+        json
+    }
+
+    /// Parse a component from JSON. Bare strings are literal text, and arrays
+    /// are their first element with the rest as children.
+    ///
+    /// # Errors
+    /// If the JSON isn't a valid component, return an error.
+    pub fn from_json(json: &Value) -> Result<Self, ProtocolError> {
+        let object = match json {
+            Value::String(text) => return Ok(Self::text(text.as_str())),
+            Value::Array(values) => {
+                let Some((first, rest)) = values.split_first() else {
+                    return Err(invalid("empty text component list"));
+                };
+                let mut component = Self::from_json(first)?;
+                for value in rest {
+                    component.extra.push(Self::from_json(value)?);
+                }
+                return Ok(component);
+            }
+            // Numbers and booleans are allowed as translation arguments.
+            Value::Number(_) | Value::Bool(_) => {
+                return Ok(Self::text(json.to_string()));
+            }
+            Value::Object(_) => json,
+            Value::Null => return Err(invalid("null text component")),
+        };
+
+        let content = if let Some(text) = object.get("text") {
+            Content::Text(match text {
+                Value::String(text) => text.clone(),
+                other => other.to_string(),
+            })
+        } else if object.get("translate").is_some() {
+            Content::Translatable {
+                key: string_field(object, "translate")?,
+                fallback: optional_field(object, "fallback", |v| v.as_str().map(str::to_owned))?,
+                with: match object.get("with") {
+                    Some(with) => with
+                        .as_array()
+                        .ok_or_else(|| invalid("translation arguments aren't a list"))?
+                        .iter()
+                        .map(Self::from_json)
+                        .collect::<Result<_, _>>()?,
+                    None => Vec::new(),
+                },
+            }
+        } else if let Some(score) = object.get("score") {
+            Content::Score {
+                name: string_field(score, "name")?,
+                objective: string_field(score, "objective")?,
+            }
+        } else if object.get("selector").is_some() {
+            Content::Selector {
+                pattern: string_field(object, "selector")?,
+                separator: component_field(object, "separator")?,
+            }
+        } else if object.get("keybind").is_some() {
+            Content::Keybind(string_field(object, "keybind")?)
+        } else if object.get("nbt").is_some() {
+            let source = if object.get("block").is_some() {
+                NbtSource::Block(string_field(object, "block")?)
+            } else if object.get("entity").is_some() {
+                NbtSource::Entity(string_field(object, "entity")?)
+            } else if object.get("storage").is_some() {
+                NbtSource::Storage(string_field(object, "storage")?.parse()?)
+            } else {
+                return Err(invalid("NBT text component has no source"));
+            };
+            Content::Nbt {
+                path: string_field(object, "nbt")?,
+                interpret: optional_field(object, "interpret", json::as_bool)?,
+                separator: component_field(object, "separator")?,
+                source,
+            }
+        } else {
+            return Err(invalid("text component has no content"));
+        };
+
+        Ok(Self {
+            content,
+            style: Style::from_json(object)?,
+            extra: match object.get("extra") {
+                Some(extra) => extra
+                    .as_array()
+                    .ok_or_else(|| invalid("extra isn't a list"))?
+                    .iter()
+                    .map(Self::from_json)
+                    .collect::<Result<_, _>>()?,
+                None => Vec::new(),
+            },
+        })
+    }
+
+    /// This component as NBT, as sent since 1.20.3.
+    #[must_use]
+    pub fn to_nbt(&self) -> NbtTag {
+        json::to_nbt(&self.to_json())
+    }
+
+    /// Parse a component from NBT.
+    ///
+    /// # Errors
+    /// If the NBT isn't a valid component, return an error.
+    pub fn from_nbt(tag: &NbtTag) -> Result<Self, ProtocolError> {
+        Self::from_json(&json::from_nbt(tag))
+    }
+
+    /// This component as legacy text with `§` formatting codes, for clients
+    /// from before components. Literal text, translation fallbacks or keys
+    /// and keybinds are shown, and the rest is dropped, as are RGB colors.
+    #[must_use]
+    pub fn to_legacy(&self) -> String {
+        let mut legacy = String::new();
+        self.write_legacy(&Style::default(), &mut String::new(), &mut legacy);
+        legacy
+    }
+
+    /// Append this component and its children to `legacy`, with the style
+    /// inherited from `parent`. `codes` are the codes the last text was
+    /// written with, which are only repeated when they change.
+    fn write_legacy(&self, parent: &Style, codes: &mut String, legacy: &mut String) {
+        let style = self.style.inherit(parent);
+        let text = match &self.content {
+            Content::Text(text) => text,
+            Content::Translatable { key, fallback, .. } => fallback.as_ref().unwrap_or(key),
+            Content::Keybind(key) => key,
+            _ => "",
+        };
+        if !text.is_empty() {
+            let style_codes = style.legacy_codes();
+            if style_codes != *codes {
+                if !codes.is_empty() {
+                    legacy.push_str("\u{a7}r");
+                }
+                legacy.push_str(&style_codes);
+                *codes = style_codes;
+            }
+            legacy.push_str(text);
+        }
+        for child in &self.extra {
+            child.write_legacy(&style, codes, legacy);
+        }
+    }
+}
+
+impl Style {
+    /// This style, with the parts it doesn't set taken from `parent`.
+    fn inherit(&self, parent: &Self) -> Self {
+        Self {
+            color: self.color.or(parent.color),
+            bold: self.bold.or(parent.bold),
+            italic: self.italic.or(parent.italic),
+            underlined: self.underlined.or(parent.underlined),
+            strikethrough: self.strikethrough.or(parent.strikethrough),
+            obfuscated: self.obfuscated.or(parent.obfuscated),
+            font: self.font.clone().or_else(|| parent.font.clone()),
+            insertion: self.insertion.clone().or_else(|| parent.insertion.clone()),
+            click_event: self
+                .click_event
+                .clone()
+                .or_else(|| parent.click_event.clone()),
+            hover_event: self
+                .hover_event
+                .clone()
+                .or_else(|| parent.hover_event.clone()),
+        }
+    }
+
+    /// The `§` codes for the color and formats of this style. RGB colors have
+    /// no code.
+    fn legacy_codes(&self) -> String {
+        let mut codes = String::new();
+        if let Some(index) = Color::NAMED
+            .iter()
+            .position(|(color, _)| Some(*color) == self.color)
+        {
+            codes.push('\u{a7}');
+            codes.push(char::from(b"0123456789abcdef"[index]));
+        }
+        for (code, flag) in [
+            ('k', self.obfuscated),
+            ('l', self.bold),
+            ('m', self.strikethrough),
+            ('n', self.underlined),
+            ('o', self.italic),
+        ] {
+            if flag == Some(true) {
+                codes.push('\u{a7}');
+                codes.push(code);
+            }
+        }
+        codes
+    }
+
+    /// Add the parts that are set to a component's JSON object.
+    fn write_json(&self, json: &mut Value) {
+        if let Some(color) = self.color {
+            json["color"] = color.to_string().into();
+        }
+        for (key, flag) in [
+            ("bold", self.bold),
+            ("italic", self.italic),
+            ("underlined", self.underlined),
+            ("strikethrough", self.strikethrough),
+            ("obfuscated", self.obfuscated),
+        ] {
+            if let Some(flag) = flag {
+                json[key] = flag.into();
+            }
+        }
+        if let Some(font) = &self.font {
+            json["font"] = font.to_string().into();
+        }
+        if let Some(insertion) = &self.insertion {
+            json["insertion"] = insertion.as_str().into();
+        }
+        if let Some(event) = &self.click_event {
+            let (action, value) = match event {
+                ClickEvent::OpenUrl(url) => ("open_url", url.clone()),
+                ClickEvent::RunCommand(command) => ("run_command", command.clone()),
+                ClickEvent::SuggestCommand(command) => ("suggest_command", command.clone()),
+                ClickEvent::ChangePage(page) => ("change_page", page.to_string()),
+                ClickEvent::CopyToClipboard(text) => ("copy_to_clipboard", text.clone()),
+            };
+            json["clickEvent"] = json!({"action": action, "value": value});
+        }
+        if let Some(event) = &self.hover_event {
+            let (action, contents) = match event {
+                HoverEvent::ShowText(text) => ("show_text", text.to_json()),
+                HoverEvent::ShowItem { id, count } => {
+                    ("show_item", json!({"id": id.to_string(), "count": count}))
+                }
+                HoverEvent::ShowEntity { kind, id, name } => {
+                    let mut contents = json!({"type": kind.to_string(), "id": id.to_string()});
+                    if let Some(name) = name {
+                        contents["name"] = name.to_json();
+                    }
+                    ("show_entity", contents)
+                }
+            };
+            json["hoverEvent"] = json!({"action": action, "contents": contents});
+        }
+    }
+
+    /// Read the style keys of a component's JSON object.
+    fn from_json(json: &Value) -> Result<Self, ProtocolError> {
+        let flag = |key| optional_field(json, key, json::as_bool);
+        Ok(Self {
+            color: optional_field(json, "color", |v| v.as_str()?.parse().ok())?,
+            bold: flag("bold")?,
+            italic: flag("italic")?,
+            underlined: flag("underlined")?,
+            strikethrough: flag("strikethrough")?,
+            obfuscated: flag("obfuscated")?,
+            font: optional_field(json, "font", |v| v.as_str()?.parse().ok())?,
+            insertion: optional_field(json, "insertion", |v| v.as_str().map(str::to_owned))?,
+            click_event: json.get("clickEvent").map(click_event).transpose()?,
+            hover_event: json.get("hoverEvent").map(hover_event).transpose()?,
+        })
+    }
+}
+
+/// Parse a click event.
+fn click_event(json: &Value) -> Result<ClickEvent, ProtocolError> {
+    let action = string_field(json, "action")?;
+    let value = string_field(json, "value")?;
+    Ok(match action.as_str() {
+        "open_url" => ClickEvent::OpenUrl(value),
+        "run_command" => ClickEvent::RunCommand(value),
+        "suggest_command" => ClickEvent::SuggestCommand(value),
+        "change_page" => ClickEvent::ChangePage(
+            value
+                .parse()
+                .map_err(|_| invalid(format!("invalid page {value:?}")))?,
+        ),
+        "copy_to_clipboard" => ClickEvent::CopyToClipboard(value),
+        _ => return Err(invalid(format!("unknown click event {action:?}"))),
+    })
+}
+
+/// Parse a hover event.
+fn hover_event(json: &Value) -> Result<HoverEvent, ProtocolError> {
+    let action = string_field(json, "action")?;
+    let contents = json
+        .get("contents")
+        .ok_or_else(|| invalid("hover event has no contents"))?;
+    Ok(match action.as_str() {
+        "show_text" => HoverEvent::ShowText(Box::new(TextComponent::from_json(contents)?)),
+        "show_item" => HoverEvent::ShowItem {
+            id: string_field(contents, "id")?.parse()?,
+            count: optional_field(contents, "count", |v| i32::try_from(v.as_i64()?).ok())?
+                .unwrap_or(1),
+        },
+        "show_entity" => HoverEvent::ShowEntity {
+            kind: string_field(contents, "type")?.parse()?,
+            id: contents
+                .get("id")
+                .and_then(parse_uuid)
+                .ok_or_else(|| invalid("show_entity has no valid id"))?,
+            name: component_field(contents, "name")?,
+        },
+        _ => return Err(invalid(format!("unknown hover event {action:?}"))),
+    })
+}
+
+/// Parse a UUID as hyphenated hex, or as four integers, most significant first.
+fn parse_uuid(json: &Value) -> Option<Uuid> {
+    match json {
+        Value::String(s) => {
+            let hex: String = s.chars().filter(|&c| c != '-').collect();
+            if hex.len() != 32 {
+                return None;
+            }
+            u128::from_str_radix(&hex, 16).ok().map(Uuid)
+        }
+        Value::Array(ints) if ints.len() == 4 => ints
+            .iter()
+            .try_fold(0u128, |uuid, int| {
+                let int = i32::try_from(int.as_i64()?).ok()?;
+                Some(uuid << 32 | u128::from(int.cast_unsigned()))
+            })
+            .map(Uuid),
+        _ => None,
+    }
+}
+
+impl From<Content> for TextComponent {
+    fn from(content: Content) -> Self {
+        Self {
+            content,
+            style: Style::default(),
+            extra: Vec::new(),
+        }
+    }
+}
+
+impl From<&str> for TextComponent {
+    fn from(text: &str) -> Self {
+        Self::text(text)
+    }
+}
+
+impl From<String> for TextComponent {
+    fn from(text: String) -> Self {
+        Self::text(text)
+    }
+}
+
+impl From<&TextComponent> for Value {
+    fn from(component: &TextComponent) -> Self {
+        component.to_json()
+    }
+}
+
+/// Components are sent as network NBT, whose root may be a bare string.
+impl McProtoSelf for TextComponent {
+    type Meta = ();
+    fn write(self, writer: &mut dyn McWriter) -> Result<(), ProtocolError> {
+        NetworkNbt::write(self.to_nbt(), writer)
+    }
+    fn read(reader: &mut dyn McReader, (): ()) -> Result<Self, ProtocolError> {
+        let offset = reader.position();
+        let tag: NbtTag = NetworkNbt::read(reader, ())?;
+        Self::from_nbt(&tag).map_err(|error| match error {
+            ProtocolError::Invalid { message, .. } => ProtocolError::Invalid { offset, message },
+            error => error,
+        })
+    }
+}
@@ -0,0 +1,198 @@
+//! The fixed-rate game tick loop, and metrics on how well it keeps up.
+
+use std::{
+    collections::VecDeque,
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::server::Shutdown;
+
+/// What the tick loop should do next.
+#[derive(Clone, Copy, PartialEq, Debug, Eq, Hash)]
+pub enum Schedule {
+    /// Run a tick now.
+    Tick,
+    /// Sleep this long, until the next tick is due.
+    Sleep(Duration),
+}
+
+/// Schedules ticks at a fixed rate of [`TPS`](Self::TPS).
+///
+/// Ticks that overrun delay the ones after them, which then run back to back
+/// until the loop has caught up. If it falls more than
+/// [`MAX_CATCH_UP`](Self::MAX_CATCH_UP) ticks behind, the missed ticks are
+/// skipped instead, like vanilla's "Can't keep up!".
+#[derive(Clone, Debug)]
+pub struct TickLoop {
+    /// When the next tick is due.
+    next: Instant,
+    /// Timing of the ticks so far.
+    metrics: TickMetrics,
+}
+
+impl TickLoop {
+    /// The target ticks per second.
+    pub const TPS: u32 = 20;
+    /// The time between ticks.
+    pub const INTERVAL: Duration = Duration::from_millis(1000 / Self::TPS as u64);
+    /// The most ticks to catch up on, two seconds' worth like vanilla.
+    pub const MAX_CATCH_UP: u32 = 2 * Self::TPS;
+
+    /// Start with the first tick due at `now`.
+    #[must_use]
+    pub fn new(now: Instant) -> Self {
+        Self {
+            next: now,
+            metrics: TickMetrics::default(),
+        }
+    }
+
+    /// What to do at `now`.
+    pub fn schedule(&mut self, now: Instant) -> Schedule {
+        if now < self.next {
+            return Schedule::Sleep(self.next - now);
+        }
+        let behind = now - self.next;
+        if behind > Self::INTERVAL * Self::MAX_CATCH_UP {
+            eprintln!(
+                "can't keep up: {}ms or {} ticks behind, skipping them",
+                behind.as_millis(),
+                behind.as_millis() / Self::INTERVAL.as_millis()
+            );
+            self.next = now;
+        }
+        Schedule::Tick
+    }
+
+    /// Record a tick that ran from `start` to `end`, and schedule the next.
+    pub fn finish(&mut self, start: Instant, end: Instant) {
+        self.next += Self::INTERVAL;
+        self.metrics
+            .record(start, end.saturating_duration_since(start));
+    }
+
+    /// Timing of the ticks so far.
+    #[must_use]
+    pub fn metrics(&self) -> &TickMetrics {
+        &self.metrics
+    }
+
+    /// Run `tick` at the fixed rate until `shutdown` is triggered, passing it
+    /// the metrics as of the tick before.
+    pub fn run(mut self, shutdown: &Shutdown, mut tick: impl FnMut(&TickMetrics)) {
+        while !shutdown.is_triggered() {
+            match self.schedule(Instant::now()) {
+                Schedule::Sleep(duration) => thread::sleep(duration),
+                Schedule::Tick => {
+                    let start = Instant::now();
+                    tick(&self.metrics);
+                    self.finish(start, Instant::now());
+                }
+            }
+        }
+    }
+}
+
+/// A summary of [`TickMetrics`] at one moment.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct TickStats {
+    /// How long the last tick took.
+    pub last_tick: Duration,
+    /// The mean milliseconds per tick over the last
+    /// [`MSPT_SAMPLES`](TickMetrics::MSPT_SAMPLES) ticks.
+    pub mean_mspt: f64,
+    /// The ticks per second over the last 1, 5 and 15 minutes.
+    pub tps: [f64; 3],
+}
+
+/// How long recent ticks took, and how many ran in the last 15 minutes.
+#[derive(Clone, Debug, Default)]
+pub struct TickMetrics {
+    /// How long each of the most recent ticks took, oldest first.
+    durations: VecDeque<Duration>,
+    /// When each tick in the longest window started, oldest first.
+    starts: VecDeque<Instant>,
+    /// When the first tick started.
+    first: Option<Instant>,
+}
+
+impl TickMetrics {
+    /// How many ticks the mean MSPT is over, matching vanilla.
+    pub const MSPT_SAMPLES: usize = 100;
+    /// The windows TPS is measured over.
+    pub const WINDOWS: [Duration; 3] = [
+        Duration::from_mins(1),
+        Duration::from_mins(5),
+        Duration::from_mins(15),
+    ];
+
+    /// Record a tick that started at `start` and took `duration`.
+    pub fn record(&mut self, start: Instant, duration: Duration) {
+        if self.durations.len() == Self::MSPT_SAMPLES {
+            self.durations.pop_front();
+        }
+        self.durations.push_back(duration);
+        self.first.get_or_insert(start);
+        self.starts.push_back(start);
+        let longest = Self::WINDOWS[Self::WINDOWS.len() - 1];
+        while self
+            .starts
+            .front()
+            .is_some_and(|&oldest| start.saturating_duration_since(oldest) >= longest)
+        {
+            self.starts.pop_front();
+        }
+    }
+
+    /// How long the last tick took, if there's been one.
+    #[must_use]
+    pub fn last_tick(&self) -> Option<Duration> {
+        self.durations.back().copied()
+    }
+
+    /// The mean milliseconds per tick over the last
+    /// [`MSPT_SAMPLES`](Self::MSPT_SAMPLES) ticks, or zero if there haven't
+    /// been any.
+    #[must_use]
+    pub fn mean_mspt(&self) -> f64 {
+        if self.durations.is_empty() {
+            return 0.0;
+        }
+        let total: Duration = self.durations.iter().sum();
+        let count = u32::try_from(self.durations.len()).unwrap_or(u32::MAX);
+        (total / count).as_secs_f64() * 1000.0
+    }
+
+    /// The ticks per second over the `window` up to `now`, or since the first
+    /// tick if that was more recent. Capped at [`TickLoop::TPS`], as a loop
+    /// that's caught up can't run faster.
+    #[must_use]
+    pub fn tps(&self, window: Duration, now: Instant) -> f64 {
+        let Some(first) = self.first else {
+            return 0.0;
+        };
+        let elapsed = now.saturating_duration_since(first).min(window);
+        if elapsed.is_zero() {
+            return 0.0;
+        }
+        let ticks = self
+            .starts
+            .iter()
+            .rev()
+            .take_while(|&&start| now.saturating_duration_since(start) <= window)
+            .count();
+        let ticks = f64::from(u32::try_from(ticks).unwrap_or(u32::MAX));
+        (ticks / elapsed.as_secs_f64()).min(f64::from(TickLoop::TPS))
+    }
+
+    /// A summary of the metrics at `now`.
+    #[must_use]
+    pub fn stats(&self, now: Instant) -> TickStats {
+        TickStats {
+            last_tick: self.last_tick().unwrap_or_default(),
+            mean_mspt: self.mean_mspt(),
+            tps: Self::WINDOWS.map(|window| self.tps(window, now)),
+        }
+    }
+}
@@ -1,14 +1,24 @@
 //! Minecraft protocol data types.
 
 use core::{
+    fmt::{self, Display, Formatter},
     num::NonZero,
     ops::{Deref, DerefMut},
+    str::FromStr,
 };
 
-use anyhow::bail;
-use simdnbt::{borrow::BaseNbtCompound, Mutf8Str};
+use std::io::Cursor;
 
-use crate::{McProto, McProtoSelf, McReader, McWriter};
+use md5::{Digest, Md5};
+use simdnbt::{
+    borrow::BaseNbtCompound,
+    owned::{Nbt, NbtTag},
+};
+
+use crate::{
+    McProto, McProtoBorrow, McProtoSelf, McReader, McWriter, ProtocolError,
+    io::{CountingWriter, SliceReader},
+};
 
 /// Macro for generating a `McProto` implemetation for number types (or any type with
 /// a `.to_be_bytes` and `::from_be_bytes` function).
@@ -16,10 +26,10 @@ macro_rules! int_impl {
     ($ty:ty, $bits:expr) => {
         impl McProtoSelf for $ty {
             type Meta = ();
-            fn write(self, writer: &mut dyn McWriter) -> Result<(), anyhow::Error> {
+            fn write(self, writer: &mut dyn McWriter) -> Result<(), ProtocolError> {
                 writer.write(&self.to_be_bytes())
             }
-            fn read(reader: &mut dyn McReader, (): ()) -> Result<Self, anyhow::Error> {
+            fn read(reader: &mut dyn McReader, (): ()) -> Result<Self, ProtocolError> {
                 let mut bytes = [0x00u8; ($bits) as usize / 8];
                 reader.read(&mut bytes)?;
                 Ok(<$ty>::from_be_bytes(bytes))
@@ -30,26 +40,29 @@ macro_rules! int_impl {
 
 impl McProtoSelf for bool {
     type Meta = ();
-    fn write(self, writer: &mut dyn McWriter) -> Result<(), anyhow::Error> {
-        writer.write(&[u8::from(!self)])
+    fn write(self, writer: &mut dyn McWriter) -> Result<(), ProtocolError> {
+        writer.write(&[u8::from(self)])
     }
-    fn read(reader: &mut dyn McReader, (): ()) -> Result<Self, anyhow::Error> {
-        let mut bytes = [0xFFu8];
-        reader.read(&mut bytes)?;
-        Ok(match bytes[0] {
-            0x00 => false,
-            0x01 => true,
-            _ => bail!("bad boolean value"),
-        })
+    fn read(reader: &mut dyn McReader, (): ()) -> Result<Self, ProtocolError> {
+        let offset = reader.position();
+        match reader.read_byte()? {
+            0x00 => Ok(false),
+            0x01 => Ok(true),
+            value => Err(ProtocolError::InvalidEnum {
+                offset,
+                name: "bool",
+                value: value.into(),
+            }),
+        }
     }
 }
 
 impl McProtoSelf for u8 {
     type Meta = ();
-    fn write(self, writer: &mut dyn McWriter) -> Result<(), anyhow::Error> {
+    fn write(self, writer: &mut dyn McWriter) -> Result<(), ProtocolError> {
         writer.write(&[self])
     }
-    fn read(reader: &mut dyn McReader, (): ()) -> Result<Self, anyhow::Error> {
+    fn read(reader: &mut dyn McReader, (): ()) -> Result<Self, ProtocolError> {
         let mut bytes = [0x00u8];
         reader.read(&mut bytes)?;
         Ok(bytes[0])
@@ -70,13 +83,356 @@ int_impl!(i64, 64);
 int_impl!(f32, 32);
 int_impl!(f64, 64);
 
+/// A 128-bit UUID.
+#[derive(Clone, Copy, PartialEq, Debug, Default, Eq, Hash, PartialOrd, Ord)]
+pub struct Uuid(pub u128);
+
+impl Uuid {
+    /// The UUID vanilla gives a player called `name` in offline mode: a version 3
+    /// UUID of `OfflinePlayer:<name>`.
+    #[must_use]
+    pub fn offline(name: &str) -> Self {
+        let mut bytes: [u8; 16] = Md5::digest(format!("OfflinePlayer:{name}")).into();
+        bytes[6] = (bytes[6] & 0x0F) | 0x30;
+        bytes[8] = (bytes[8] & 0x3F) | 0x80;
+        Self(u128::from_be_bytes(bytes))
+    }
+}
+
+impl Display for Uuid {
+    /// Format as the usual hyphenated hex.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let n = self.0;
+        write!(
+            f,
+            "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+            n >> 96,
+            (n >> 80) & 0xFFFF,
+            (n >> 64) & 0xFFFF,
+            (n >> 48) & 0xFFFF,
+            n & 0xFFFF_FFFF_FFFF
+        )
+    }
+}
+
+impl McProtoSelf for Uuid {
+    type Meta = ();
+    fn write(self, writer: &mut dyn McWriter) -> Result<(), ProtocolError> {
+        writer.write(&self.0.to_be_bytes())
+    }
+    fn read(reader: &mut dyn McReader, (): ()) -> Result<Self, ProtocolError> {
+        let mut bytes = [0x00u8; 16];
+        reader.read(&mut bytes)?;
+        Ok(Self(u128::from_be_bytes(bytes)))
+    }
+}
+
+/// A block position, packed into a long as 26 bits of X, 26 bits of Z and 12
+/// bits of Y.
+#[derive(Clone, Copy, PartialEq, Debug, Default, Eq, Hash)]
+pub struct Position {
+    /// The X coordinate, from -2^25 to 2^25 - 1.
+    pub x: i32,
+    /// The Y coordinate, from -2048 to 2047.
+    pub y: i32,
+    /// The Z coordinate, from -2^25 to 2^25 - 1.
+    pub z: i32,
+}
+
+impl Position {
+    /// A position at the given coordinates.
+    #[must_use]
+    pub const fn new(x: i32, y: i32, z: i32) -> Self {
+        Self { x, y, z }
+    }
+}
+
+impl McProtoSelf for Position {
+    type Meta = ();
+    fn write(self, writer: &mut dyn McWriter) -> Result<(), ProtocolError> {
+        if !(-(1 << 25)..1 << 25).contains(&self.x)
+            || !(-(1 << 25)..1 << 25).contains(&self.z)
+            || !(-(1 << 11)..1 << 11).contains(&self.y)
+        {
+            return Err(ProtocolError::invalid(
+                None,
+                format!("position {self:?} is out of range"),
+            ));
+        }
+        let packed = (i64::from(self.x) & 0x3FF_FFFF) << 38
+            | (i64::from(self.z) & 0x3FF_FFFF) << 12
+            | i64::from(self.y) & 0xFFF;
+        packed.write(writer)
+    }
+    fn read(reader: &mut dyn McReader, (): ()) -> Result<Self, ProtocolError> {
+        let packed = <i64 as McProtoSelf>::read(reader, ())?;
+        // Shifting left then arithmetically right sign-extends each field.
+        Ok(Self {
+            x: (packed >> 38) as i32,
+            y: (packed << 52 >> 52) as i32,
+            z: (packed << 26 >> 38) as i32,
+        })
+    }
+}
+
+/// A rotation in steps of 1/256 of a full turn.
+#[derive(Clone, Copy, PartialEq, Debug, Default, Eq, Hash)]
+pub struct Angle(pub u8);
+
+impl Angle {
+    /// The nearest angle to `degrees`.
+    #[must_use]
+    #[allow(
+        clippy::cast_sign_loss,
+        reason = "the angle wraps, so only the low byte matters"
+    )]
+    pub fn from_degrees(degrees: f32) -> Self {
+        Self((degrees / 360.0 * 256.0).round() as i32 as u8)
+    }
+
+    /// This angle in degrees, from 0 to 360.
+    #[must_use]
+    pub fn to_degrees(self) -> f32 {
+        f32::from(self.0) * 360.0 / 256.0
+    }
+}
+
+impl McProtoSelf for Angle {
+    type Meta = ();
+    fn write(self, writer: &mut dyn McWriter) -> Result<(), ProtocolError> {
+        self.0.write(writer)
+    }
+    fn read(reader: &mut dyn McReader, (): ()) -> Result<Self, ProtocolError> {
+        Ok(Self(reader.read_byte()?))
+    }
+}
+
+/// A set of bits of any length, sent as a `VarInt`-prefixed array of longs.
+/// Bit `i` is bit `i % 64` of long `i / 64`.
+#[derive(Clone, PartialEq, Debug, Default, Eq, Hash)]
+pub struct BitSet(pub Vec<u64>);
+
+impl BitSet {
+    /// Whether bit `i` is set.
+    #[must_use]
+    pub fn get(&self, i: usize) -> bool {
+        self.0
+            .get(i / 64)
+            .is_some_and(|long| long >> (i % 64) & 1 == 1)
+    }
+
+    /// Set bit `i` to `value`, growing the set if needed.
+    pub fn set(&mut self, i: usize, value: bool) {
+        if self.0.len() <= i / 64 {
+            if !value {
+                return;
+            }
+            self.0.resize(i / 64 + 1, 0);
+        }
+        if value {
+            self.0[i / 64] |= 1 << (i % 64);
+        } else {
+            self.0[i / 64] &= !(1 << (i % 64));
+        }
+    }
+}
+
+impl McProtoSelf for BitSet {
+    type Meta = ();
+    fn write(self, writer: &mut dyn McWriter) -> Result<(), ProtocolError> {
+        self.0.write(writer)
+    }
+    fn read(reader: &mut dyn McReader, (): ()) -> Result<Self, ProtocolError> {
+        Ok(Self(McProtoSelf::read(reader, ())?))
+    }
+}
+
+/// A set of exactly `BITS` bits, sent as `ceil(BITS / 8)` bytes with no
+/// prefix. Bit `i` is bit `i % 8` of byte `i / 8`.
+#[derive(Clone, PartialEq, Debug, Eq, Hash)]
+pub struct FixedBitSet<const BITS: usize>(Vec<u8>);
+
+impl<const BITS: usize> FixedBitSet<BITS> {
+    /// The number of bytes the set takes up.
+    const BYTES: usize = BITS.div_ceil(8);
+
+    /// An empty set.
+    #[must_use]
+    pub fn new() -> Self {
+        Self(vec![0; Self::BYTES])
+    }
+
+    /// Whether bit `i` is set.
+    ///
+    /// # Panics
+    /// If `i` is out of range.
+    #[must_use]
+    pub fn get(&self, i: usize) -> bool {
+        assert!(i < BITS, "bit {i} is out of range");
+        self.0[i / 8] >> (i % 8) & 1 == 1
+    }
+
+    /// Set bit `i` to `value`.
+    ///
+    /// # Panics
+    /// If `i` is out of range.
+    pub fn set(&mut self, i: usize, value: bool) {
+        assert!(i < BITS, "bit {i} is out of range");
+        if value {
+            self.0[i / 8] |= 1 << (i % 8);
+        } else {
+            self.0[i / 8] &= !(1 << (i % 8));
+        }
+    }
+}
+
+impl<const BITS: usize> Default for FixedBitSet<BITS> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const BITS: usize> McProtoSelf for FixedBitSet<BITS> {
+    type Meta = ();
+    fn write(self, writer: &mut dyn McWriter) -> Result<(), ProtocolError> {
+        writer.write(&self.0)
+    }
+    fn read(reader: &mut dyn McReader, (): ()) -> Result<Self, ProtocolError> {
+        let mut set = Self::new();
+        reader.read(&mut set.0)?;
+        Ok(set)
+    }
+}
+
+/// A namespaced identifier, such as `minecraft:stone`.
+#[derive(Clone, PartialEq, Debug, Eq, Hash, PartialOrd, Ord)]
+pub struct Identifier {
+    /// The namespace, made of `a-z0-9.-_`.
+    namespace: String,
+    /// The path, made of `a-z0-9.-_/`.
+    path: String,
+}
+
+impl Identifier {
+    /// The namespace used when none is given.
+    pub const DEFAULT_NAMESPACE: &str = "minecraft";
+    /// The longest identifier accepted on the wire.
+    pub const MAX_LENGTH: Length = Length::new(32767);
+
+    /// An identifier from its parts.
+    ///
+    /// # Errors
+    /// If either part contains characters that aren't allowed, return an error.
+    pub fn new(namespace: &str, path: &str) -> Result<Self, ProtocolError> {
+        if namespace.is_empty()
+            || !namespace
+                .bytes()
+                .all(|b| matches!(b, b'a'..=b'z' | b'0'..=b'9' | b'.' | b'-' | b'_'))
+        {
+            return Err(ProtocolError::invalid(
+                None,
+                format!("invalid identifier namespace {namespace:?}"),
+            ));
+        }
+        if !path
+            .bytes()
+            .all(|b| matches!(b, b'a'..=b'z' | b'0'..=b'9' | b'.' | b'-' | b'_' | b'/'))
+        {
+            return Err(ProtocolError::invalid(
+                None,
+                format!("invalid identifier path {path:?}"),
+            ));
+        }
+        Ok(Self {
+            namespace: namespace.to_owned(),
+            path: path.to_owned(),
+        })
+    }
+
+    /// An identifier in the `minecraft` namespace.
+    ///
+    /// # Errors
+    /// If `path` contains characters that aren't allowed, return an error.
+    pub fn minecraft(path: &str) -> Result<Self, ProtocolError> {
+        Self::new(Self::DEFAULT_NAMESPACE, path)
+    }
+
+    /// The namespace.
+    #[must_use]
+    pub fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    /// The path.
+    #[must_use]
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+impl FromStr for Identifier {
+    type Err = ProtocolError;
+    /// Parse `namespace:path`, or just `path` in the `minecraft` namespace.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            Some((namespace, path)) => Self::new(namespace, path),
+            None => Self::minecraft(s),
+        }
+    }
+}
+
+impl Display for Identifier {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.namespace, self.path)
+    }
+}
+
+impl McProtoSelf for Identifier {
+    type Meta = ();
+    fn write(self, writer: &mut dyn McWriter) -> Result<(), ProtocolError> {
+        self.to_string().write(writer)
+    }
+    fn read(reader: &mut dyn McReader, (): ()) -> Result<Self, ProtocolError> {
+        let offset = reader.position();
+        <String as McProtoSelf>::read(reader, Self::MAX_LENGTH)?
+            .parse()
+            .map_err(|error| match error {
+                ProtocolError::Invalid { message, .. } => {
+                    ProtocolError::Invalid { offset, message }
+                }
+                error => error,
+            })
+    }
+}
+
 /// A non-zero length.
 #[derive(Clone, Copy, PartialEq, Debug, Eq, Hash, PartialOrd, Ord)]
 pub struct Length(pub NonZero<usize>);
 
+impl Length {
+    /// A length of `len`.
+    ///
+    /// # Panics
+    /// If `len` is zero.
+    #[must_use]
+    pub const fn new(len: usize) -> Self {
+        match NonZero::new(len) {
+            Some(len) => Self(len),
+            None => panic!("zero length"),
+        }
+    }
+}
+
+impl Length {
+    /// The longest string vanilla accepts where it doesn't set a limit.
+    pub const MAX_STRING: Self = Self::new(32767);
+}
+
 impl Default for Length {
+    /// [`MAX_STRING`](Self::MAX_STRING), so strings without an explicit limit
+    /// get vanilla's.
     fn default() -> Self {
-        Self(unsafe { NonZero::new_unchecked(1) })
+        Self::MAX_STRING
     }
 }
 
@@ -105,17 +461,18 @@ impl VarNum {
 }
 
 impl McProto<u32> for VarNum {
-    fn write(mut value: u32, writer: &mut dyn McWriter) -> Result<(), anyhow::Error> {
+    fn write(mut value: u32, writer: &mut dyn McWriter) -> Result<(), ProtocolError> {
         loop {
             if (value & !u32::from(Self::SEGMENT_BITS)) == 0 {
-                return value.write(writer);
+                return (value as u8).write(writer);
             }
             ((value as u8 & Self::SEGMENT_BITS) | Self::CONTINUE_BIT).write(writer)?;
 
             value >>= 7;
         }
     }
-    fn read(reader: &mut dyn McReader, (): Self::Meta) -> Result<u32, anyhow::Error> {
+    fn read(reader: &mut dyn McReader, (): Self::Meta) -> Result<u32, ProtocolError> {
+        let offset = reader.position();
         let mut value = 0u32;
         let mut position = 0u8;
 
@@ -129,7 +486,7 @@ impl McProto<u32> for VarNum {
             position += 7;
 
             if position >= 32 {
-                bail!("VarInt is too large");
+                return Err(ProtocolError::VarIntTooLong { offset, max: 5 });
             }
         }
     }
@@ -137,17 +494,18 @@ impl McProto<u32> for VarNum {
 }
 
 impl McProto<u64> for VarNum {
-    fn write(mut value: u64, writer: &mut dyn McWriter) -> Result<(), anyhow::Error> {
+    fn write(mut value: u64, writer: &mut dyn McWriter) -> Result<(), ProtocolError> {
         loop {
             if (value & !u64::from(Self::SEGMENT_BITS)) == 0 {
-                return value.write(writer);
+                return (value as u8).write(writer);
             }
             ((value as u8 & Self::SEGMENT_BITS) | Self::CONTINUE_BIT).write(writer)?;
 
             value >>= 7;
         }
     }
-    fn read(reader: &mut dyn McReader, (): Self::Meta) -> Result<u64, anyhow::Error> {
+    fn read(reader: &mut dyn McReader, (): Self::Meta) -> Result<u64, ProtocolError> {
+        let offset = reader.position();
         let mut value = 0u64;
         let mut position = 0u8;
 
@@ -161,44 +519,613 @@ impl McProto<u64> for VarNum {
             position += 7;
 
             if position >= 64 {
-                bail!("VarLong is too large");
+                return Err(ProtocolError::VarIntTooLong { offset, max: 10 });
             }
         }
     }
     type Meta = ();
 }
 
+/// Signed `VarInt`s are the two's complement bits of the value encoded as
+/// unsigned, so negative values always take five bytes.
+impl McProto<i32> for VarNum {
+    type Meta = ();
+    fn write(value: i32, writer: &mut dyn McWriter) -> Result<(), ProtocolError> {
+        Self::write(value.cast_unsigned(), writer)
+    }
+    fn read(reader: &mut dyn McReader, (): Self::Meta) -> Result<i32, ProtocolError> {
+        let value: u32 = Self::read(reader, ())?;
+        Ok(value.cast_signed())
+    }
+}
+
+/// Signed `VarLong`s are the two's complement bits of the value encoded as
+/// unsigned, so negative values always take ten bytes.
+impl McProto<i64> for VarNum {
+    type Meta = ();
+    fn write(value: i64, writer: &mut dyn McWriter) -> Result<(), ProtocolError> {
+        Self::write(value.cast_unsigned(), writer)
+    }
+    fn read(reader: &mut dyn McReader, (): Self::Meta) -> Result<i64, ProtocolError> {
+        let value: u64 = Self::read(reader, ())?;
+        Ok(value.cast_signed())
+    }
+}
+
+/// An array of `VarInt`s prefixed with its length, such as the registry IDs
+/// in a tag.
+impl McProto<Vec<u32>> for VarNum {
+    type Meta = ();
+    fn write(value: Vec<u32>, writer: &mut dyn McWriter) -> Result<(), ProtocolError> {
+        Self::write(value.len() as u32, writer)?;
+        for value in value {
+            Self::write(value, writer)?;
+        }
+        Ok(())
+    }
+    fn read(reader: &mut dyn McReader, (): Self::Meta) -> Result<Vec<u32>, ProtocolError> {
+        let len: u32 = Self::read(reader, ())?;
+        let mut out = Vec::with_capacity((len as usize).min(1024));
+        for _ in 0..len {
+            out.push(Self::read(reader, ())?);
+        }
+        Ok(out)
+    }
+}
+
+impl VarNum {
+    /// The number of bytes `value` takes up when written through `VarNum`.
+    #[must_use]
+    pub fn encoded_len<T>(value: T) -> usize
+    where
+        Self: McProto<T>,
+    {
+        let mut counter = CountingWriter::default();
+        // Writing to a counter can't fail.
+        let _ = <Self as McProto<T>>::write(value, &mut counter);
+        counter.0
+    }
+}
+
+/// A signed `VarInt` or `VarLong` with zigzag encoding, which maps small negative
+/// numbers to small unsigned ones so they stay short. Vanilla doesn't use it,
+/// but custom channels can.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ZigZag;
+
+impl McProto<i32> for ZigZag {
+    type Meta = ();
+    fn write(value: i32, writer: &mut dyn McWriter) -> Result<(), ProtocolError> {
+        VarNum::write(((value << 1) ^ (value >> 31)).cast_unsigned(), writer)
+    }
+    fn read(reader: &mut dyn McReader, (): Self::Meta) -> Result<i32, ProtocolError> {
+        let value: u32 = VarNum::read(reader, ())?;
+        Ok((value >> 1).cast_signed() ^ -(value & 1).cast_signed())
+    }
+}
+
+impl McProto<i64> for ZigZag {
+    type Meta = ();
+    fn write(value: i64, writer: &mut dyn McWriter) -> Result<(), ProtocolError> {
+        VarNum::write(((value << 1) ^ (value >> 63)).cast_unsigned(), writer)
+    }
+    fn read(reader: &mut dyn McReader, (): Self::Meta) -> Result<i64, ProtocolError> {
+        let value: u64 = VarNum::read(reader, ())?;
+        Ok((value >> 1).cast_signed() ^ -(value & 1).cast_signed())
+    }
+}
+
+/// A byte array prefixed with its length as a `VarInt`. The meta is the most
+/// bytes to accept when reading.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ByteArray;
+
+impl McProto<Vec<u8>> for ByteArray {
+    type Meta = Length;
+    fn write(value: Vec<u8>, writer: &mut dyn McWriter) -> Result<(), ProtocolError> {
+        VarNum::write(value.len() as u32, writer)?;
+        writer.write(&value)
+    }
+    fn read(reader: &mut dyn McReader, max_len: Self::Meta) -> Result<Vec<u8>, ProtocolError> {
+        let offset = reader.position();
+        let len: u32 = VarNum::read(reader, ())?;
+        if len as usize > max_len.get() {
+            return Err(ProtocolError::StringTooLong {
+                offset,
+                what: "byte array",
+                len: len as usize,
+                max: max_len.get(),
+            });
+        }
+        let mut bytes = vec![0u8; len as usize];
+        reader.read(&mut bytes)?;
+        Ok(bytes)
+    }
+}
+
+/// Borrowed byte arrays point into the frame instead of being copied out.
+impl<'a> McProtoBorrow<'a, &'a [u8]> for ByteArray {
+    type Meta = Length;
+    fn read_borrowed(
+        reader: &mut SliceReader<'a>,
+        max_len: Self::Meta,
+    ) -> Result<&'a [u8], ProtocolError> {
+        let offset = McReader::position(reader);
+        let len: u32 = VarNum::read(reader, ())?;
+        if len as usize > max_len.get() {
+            return Err(ProtocolError::StringTooLong {
+                offset,
+                what: "byte array",
+                len: len as usize,
+                max: max_len.get(),
+            });
+        }
+        reader.take(len as usize)
+    }
+}
+
+/// A string prefixed with its length in bytes as a `VarInt`. The meta is the
+/// most UTF-16 code units to accept when reading, as vanilla counts them.
 impl McProtoSelf for String {
     type Meta = Length;
-    fn write(self, writer: &mut dyn McWriter) -> Result<(), anyhow::Error> {
-        VarNum::write(self.encode_utf16().count() as u32, writer)?;
-        for byte in self.bytes() {
-            byte.write(writer)?;
+    fn write(self, writer: &mut dyn McWriter) -> Result<(), ProtocolError> {
+        VarNum::write(self.len() as u32, writer)?;
+        writer.write(self.as_bytes())
+    }
+    fn read(reader: &mut dyn McReader, max_len: Self::Meta) -> Result<Self, ProtocolError> {
+        let offset = reader.position();
+        let len = read_string_len(reader, offset, max_len)?;
+        let mut bytes = vec![0u8; len];
+        reader.read(&mut bytes)?;
+        let string = String::from_utf8(bytes).map_err(|error| ProtocolError::InvalidUtf8 {
+            offset,
+            source: error.utf8_error(),
+        })?;
+        check_str_len(&string, offset, max_len)?;
+        Ok(string)
+    }
+}
+
+/// Borrowed strings point into the frame instead of being copied out, with the
+/// same limits as [`String`].
+impl<'a> McProtoBorrow<'a> for &'a str {
+    type Meta = Length;
+    fn read_borrowed(
+        reader: &mut SliceReader<'a>,
+        max_len: Self::Meta,
+    ) -> Result<Self, ProtocolError> {
+        let offset = McReader::position(reader);
+        let len = read_string_len(reader, offset, max_len)?;
+        let string = str::from_utf8(reader.take(len)?)
+            .map_err(|source| ProtocolError::InvalidUtf8 { offset, source })?;
+        check_str_len(string, offset, max_len)?;
+        Ok(string)
+    }
+}
+
+/// Read the byte length prefix of a string that started at `offset`,
+/// rejecting lengths that `max_len` characters can't take up.
+fn read_string_len(
+    reader: &mut dyn McReader,
+    offset: Option<usize>,
+    max_len: Length,
+) -> Result<usize, ProtocolError> {
+    let len: u32 = VarNum::read(reader, ())?;
+    let len = len as usize;
+    // A UTF-16 code unit takes at most three bytes of UTF-8.
+    if len > max_len.get() * 3 {
+        return Err(ProtocolError::StringTooLong {
+            offset,
+            what: "string of bytes",
+            len,
+            max: max_len.get() * 3,
+        });
+    }
+    Ok(len)
+}
+
+/// Check that `string`, which started at `offset`, is at most `max_len` UTF-16
+/// code units long.
+fn check_str_len(
+    string: &str,
+    offset: Option<usize>,
+    max_len: Length,
+) -> Result<(), ProtocolError> {
+    let len = string.encode_utf16().count();
+    if len > max_len.get() {
+        return Err(ProtocolError::StringTooLong {
+            offset,
+            what: "string",
+            len,
+            max: max_len.get(),
+        });
+    }
+    Ok(())
+}
+
+/// A value whose length can be checked against a [`Length`] before it's
+/// written, so oversized values fail locally instead of at the other end.
+pub trait Bounded {
+    /// Check that this value is no longer than `max_len`.
+    ///
+    /// # Errors
+    /// If it's longer, return an error.
+    fn check_len(&self, max_len: Length) -> Result<(), ProtocolError>;
+}
+
+impl Bounded for String {
+    /// Strings are measured in UTF-16 code units.
+    fn check_len(&self, max_len: Length) -> Result<(), ProtocolError> {
+        check_str_len(self, None, max_len)
+    }
+}
+
+impl Bounded for Vec<u8> {
+    /// Byte arrays are measured in bytes.
+    fn check_len(&self, max_len: Length) -> Result<(), ProtocolError> {
+        if self.len() > max_len.get() {
+            return Err(ProtocolError::StringTooLong {
+                offset: None,
+                what: "byte array",
+                len: self.len(),
+                max: max_len.get(),
+            });
         }
         Ok(())
     }
-    fn read(reader: &mut dyn McReader, _: Self::Meta) -> Result<Self, anyhow::Error> {
-        let length: u32 = VarNum::read(reader, ())?;
-        let mut out = Vec::<u8>::new();
-        let mut curr_length = 0u32;
-        while curr_length < length {
-            out.push(reader.read_byte()?);
-            if let Ok(s) = str::from_utf8(out.as_slice()) {
-                curr_length = s.encode_utf16().count() as u32;
+}
+
+impl<T: Bounded> Bounded for Option<T> {
+    /// The value is checked if it's present, as the meta of an `Option` is
+    /// passed on to it.
+    fn check_len(&self, max_len: Length) -> Result<(), ProtocolError> {
+        self.as_ref()
+            .map_or(Ok(()), |value| value.check_len(max_len))
+    }
+}
+
+/// An optional value, prefixed with whether it's present. The meta is passed
+/// on to the value.
+impl<T: McProtoSelf> McProtoSelf for Option<T> {
+    type Meta = T::Meta;
+    fn write(self, writer: &mut dyn McWriter) -> Result<(), ProtocolError> {
+        self.is_some().write(writer)?;
+        match self {
+            Some(value) => value.write(writer),
+            None => Ok(()),
+        }
+    }
+    fn read(reader: &mut dyn McReader, meta: Self::Meta) -> Result<Self, ProtocolError> {
+        if <bool as McProtoSelf>::read(reader, ())? {
+            Ok(Some(T::read(reader, meta)?))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// An array prefixed with its length as a `VarInt`. The meta is passed on to
+/// every element.
+impl<T: McProtoSelf> McProtoSelf for Vec<T>
+where
+    T::Meta: Clone,
+{
+    type Meta = T::Meta;
+    fn write(self, writer: &mut dyn McWriter) -> Result<(), ProtocolError> {
+        VarNum::write(self.len() as u32, writer)?;
+        for value in self {
+            value.write(writer)?;
+        }
+        Ok(())
+    }
+    fn read(reader: &mut dyn McReader, meta: Self::Meta) -> Result<Self, ProtocolError> {
+        let len: u32 = VarNum::read(reader, ())?;
+        // Don't trust the length with a huge allocation up front: the reader
+        // runs dry first if it's a lie.
+        let mut out = Vec::with_capacity((len as usize).min(1024));
+        for _ in 0..len {
+            out.push(T::read(reader, meta.clone())?);
+        }
+        Ok(out)
+    }
+}
+
+/// The format of the root tag of an NBT blob.
+#[derive(Clone, Copy, PartialEq, Debug, Default, Eq, Hash)]
+pub enum NbtFormat {
+    /// The classic format, where the root compound has a name.
+    #[default]
+    Named,
+    /// The network format used since 1.20.2, where the root compound has no name.
+    Network,
+}
+
+/// Metadata for reading NBT.
+#[derive(Clone, Copy, PartialEq, Debug, Eq, Hash)]
+pub struct NbtMeta {
+    /// The format of the root tag.
+    pub format: NbtFormat,
+    /// The maximum nesting depth of compounds and lists.
+    pub max_depth: usize,
+    /// The maximum number of bytes the whole blob may take up.
+    pub max_size: usize,
+}
+
+impl NbtMeta {
+    /// The default maximum nesting depth, matching vanilla.
+    pub const DEFAULT_MAX_DEPTH: usize = 512;
+    /// The default maximum size, matching vanilla's network limit of 2 MiB.
+    pub const DEFAULT_MAX_SIZE: usize = 2 * 1024 * 1024;
+    /// Metadata for reading network (nameless root) NBT with the default limits.
+    pub const NETWORK: Self = Self {
+        format: NbtFormat::Network,
+        max_depth: Self::DEFAULT_MAX_DEPTH,
+        max_size: Self::DEFAULT_MAX_SIZE,
+    };
+}
+
+impl Default for NbtMeta {
+    fn default() -> Self {
+        Self {
+            format: NbtFormat::Named,
+            max_depth: Self::DEFAULT_MAX_DEPTH,
+            max_size: Self::DEFAULT_MAX_SIZE,
+        }
+    }
+}
+
+/// Copies a single NBT blob out of a reader without knowing its length up front,
+/// enforcing the limits in an [`NbtMeta`] along the way.
+struct NbtScanner<'a> {
+    /// The reader to pull bytes from.
+    reader: &'a mut dyn McReader,
+    /// Every byte read so far.
+    buf: Vec<u8>,
+    /// The limits to enforce.
+    meta: NbtMeta,
+    /// Where the blob started in the reader, for errors.
+    offset: Option<usize>,
+}
+
+impl NbtScanner<'_> {
+    /// The tag ID of an end tag.
+    const END_ID: u8 = 0;
+    /// The tag ID of a list tag.
+    const LIST_ID: u8 = 9;
+    /// The tag ID of a compound tag.
+    const COMPOUND_ID: u8 = 10;
+
+    /// An error about the blob being scanned.
+    fn error(&self, message: impl Into<String>) -> ProtocolError {
+        ProtocolError::NbtError {
+            offset: self.offset,
+            message: message.into(),
+        }
+    }
+
+    /// Read `len` bytes into the buffer and return them.
+    fn take(&mut self, len: usize) -> Result<&[u8], ProtocolError> {
+        let start = self.buf.len();
+        if len > self.meta.max_size - start {
+            return Err(self.error(format!("larger than {} bytes", self.meta.max_size)));
+        }
+        self.buf.resize(start + len, 0);
+        self.reader.read(&mut self.buf[start..])?;
+        Ok(&self.buf[start..])
+    }
+
+    /// Read a single byte.
+    fn take_u8(&mut self) -> Result<u8, ProtocolError> {
+        Ok(self.take(1)?[0])
+    }
+
+    /// Read a string length.
+    fn take_u16(&mut self) -> Result<usize, ProtocolError> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]).into())
+    }
+
+    /// Read an array or list length, rejecting negative lengths.
+    fn take_len(&mut self) -> Result<usize, ProtocolError> {
+        let bytes = self.take(4)?;
+        let len = i32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        let Ok(len) = usize::try_from(len) else {
+            return Err(self.error(format!("negative length {len}")));
+        };
+        Ok(len)
+    }
+
+    /// Read an array of `len` elements that are `size` bytes each.
+    fn take_array(&mut self, size: usize) -> Result<(), ProtocolError> {
+        let len = self.take_len()?;
+        let Some(bytes) = len.checked_mul(size) else {
+            return Err(self.error(format!("array of {len} elements is too large")));
+        };
+        self.take(bytes)?;
+        Ok(())
+    }
+
+    /// Read the payload of a tag with the provided ID, nested `depth` levels deep.
+    fn payload(&mut self, id: u8, depth: usize) -> Result<(), ProtocolError> {
+        match id {
+            1 => drop(self.take(1)?),
+            2 => drop(self.take(2)?),
+            3 | 5 => drop(self.take(4)?),
+            4 | 6 => drop(self.take(8)?),
+            7 => self.take_array(1)?,
+            8 => {
+                let len = self.take_u16()?;
+                self.take(len)?;
+            }
+            Self::LIST_ID => {
+                self.enter(depth)?;
+                let elem = self.take_u8()?;
+                let len = self.take_len()?;
+                if elem == Self::END_ID && len != 0 {
+                    return Err(self.error("non-empty list of end tags"));
+                }
+                for _ in 0..len {
+                    self.payload(elem, depth + 1)?;
+                }
             }
+            Self::COMPOUND_ID => {
+                self.enter(depth)?;
+                loop {
+                    let id = self.take_u8()?;
+                    if id == Self::END_ID {
+                        break;
+                    }
+                    let len = self.take_u16()?;
+                    self.take(len)?;
+                    self.payload(id, depth + 1)?;
+                }
+            }
+            11 => self.take_array(4)?,
+            12 => self.take_array(8)?,
+            _ => return Err(self.error(format!("unknown tag id {id}"))),
         }
+        Ok(())
+    }
 
-        Ok(String::from_utf8(out).unwrap())
+    /// Check that a list or compound at `depth` doesn't exceed the depth limit.
+    fn enter(&self, depth: usize) -> Result<(), ProtocolError> {
+        if depth >= self.meta.max_depth {
+            return Err(self.error(format!("nested deeper than {} levels", self.meta.max_depth)));
+        }
+        Ok(())
     }
 }
 
-impl McProtoSelf for simdnbt::owned::Nbt {
+impl McProtoSelf for Nbt {
+    type Meta = NbtMeta;
+    fn write(self, writer: &mut dyn McWriter) -> Result<(), ProtocolError> {
+        let mut vec = Vec::new();
+        Nbt::write(&self, &mut vec);
+        writer.write(vec.as_slice())
+    }
+    fn read(reader: &mut dyn McReader, meta: Self::Meta) -> Result<Self, ProtocolError> {
+        let offset = reader.position();
+        let mut scanner = NbtScanner {
+            reader,
+            buf: Vec::new(),
+            meta,
+            offset,
+        };
+        match scanner.take_u8()? {
+            NbtScanner::END_ID => return Ok(Nbt::None),
+            NbtScanner::COMPOUND_ID => {}
+            id => return Err(scanner.error(format!("invalid root tag id {id}"))),
+        }
+        if meta.format == NbtFormat::Named {
+            let len = scanner.take_u16()?;
+            scanner.take(len)?;
+        }
+        scanner.payload(NbtScanner::COMPOUND_ID, 0)?;
+
+        let mut cursor = Cursor::new(scanner.buf.as_slice());
+        match meta.format {
+            NbtFormat::Named => simdnbt::owned::read(&mut cursor),
+            NbtFormat::Network => simdnbt::owned::read_unnamed(&mut cursor),
+        }
+        .map_err(|error| scanner.error(error.to_string()))
+    }
+}
+
+/// Borrowed NBT is parsed in place, with strings and arrays pointing into the
+/// frame. The size limit in the meta is enforced, but the depth limit is
+/// simdnbt's own, which is the same as vanilla's.
+impl<'a> McProtoBorrow<'a> for simdnbt::borrow::Nbt<'a> {
+    type Meta = NbtMeta;
+    fn read_borrowed(
+        reader: &mut SliceReader<'a>,
+        meta: Self::Meta,
+    ) -> Result<Self, ProtocolError> {
+        read_borrowed_nbt(reader, meta.max_size, |cursor| match meta.format {
+            NbtFormat::Named => simdnbt::borrow::read(cursor),
+            NbtFormat::Network => simdnbt::borrow::read_unnamed(cursor),
+        })
+    }
+}
+
+/// A borrowed network NBT compound, which can't be empty like
+/// [`simdnbt::borrow::Nbt`] can.
+impl<'a> McProtoBorrow<'a> for BaseNbtCompound<'a> {
+    type Meta = ();
+    fn read_borrowed(reader: &mut SliceReader<'a>, (): ()) -> Result<Self, ProtocolError> {
+        read_borrowed_nbt(reader, NbtMeta::DEFAULT_MAX_SIZE, |cursor| {
+            match cursor.get_ref().first() {
+                Some(&NbtScanner::COMPOUND_ID) => {
+                    cursor.set_position(1);
+                    simdnbt::borrow::read_compound(cursor)
+                }
+                Some(&id) => Err(simdnbt::Error::InvalidRootType(id)),
+                None => Err(simdnbt::Error::UnexpectedEof),
+            }
+        })
+    }
+}
+
+/// Parse borrowed NBT from the rest of `reader` with `read`, looking at no
+/// more than `max_size` bytes, and skip past it.
+fn read_borrowed_nbt<'a, T>(
+    reader: &mut SliceReader<'a>,
+    max_size: usize,
+    read: impl FnOnce(&mut Cursor<&'a [u8]>) -> Result<T, simdnbt::Error>,
+) -> Result<T, ProtocolError> {
+    let offset = McReader::position(reader);
+    let rest = reader.rest();
+    let mut cursor = Cursor::new(&rest[..rest.len().min(max_size)]);
+    let value = read(&mut cursor).map_err(|error| ProtocolError::NbtError {
+        offset,
+        message: match error {
+            simdnbt::Error::UnexpectedEof if rest.len() > max_size => {
+                format!("larger than {max_size} bytes")
+            }
+            error => error.to_string(),
+        },
+    })?;
+    reader.take(cursor.position() as usize)?;
+    Ok(value)
+}
+
+/// Network NBT, where the root compound has no name. Used since 1.20.2.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NetworkNbt;
+
+impl McProto<Nbt> for NetworkNbt {
     type Meta = ();
-    fn write(self, writer: &mut dyn McWriter) -> Result<(), anyhow::Error> {
+    fn write(value: Nbt, writer: &mut dyn McWriter) -> Result<(), ProtocolError> {
         let mut vec = Vec::new();
-        simdnbt::owned::Nbt::write(&self, &mut vec);
+        value.write_unnamed(&mut vec);
         writer.write(vec.as_slice())
     }
-    fn read(reader: &mut dyn McReader, meta: Self::Meta) -> Result<Self, anyhow::Error> {
+    fn read(reader: &mut dyn McReader, (): Self::Meta) -> Result<Nbt, ProtocolError> {
+        <Nbt as McProtoSelf>::read(reader, NbtMeta::NETWORK)
+    }
+}
+
+/// A single network NBT tag of any type. Since 1.20.3 the root of network NBT
+/// may be any tag, such as the bare string of a plain text component.
+impl McProto<NbtTag> for NetworkNbt {
+    type Meta = ();
+    fn write(value: NbtTag, writer: &mut dyn McWriter) -> Result<(), ProtocolError> {
+        let mut vec = Vec::new();
+        value.write(&mut vec);
+        writer.write(vec.as_slice())
+    }
+    fn read(reader: &mut dyn McReader, (): Self::Meta) -> Result<NbtTag, ProtocolError> {
+        let offset = reader.position();
+        let mut scanner = NbtScanner {
+            reader,
+            buf: Vec::new(),
+            meta: NbtMeta::NETWORK,
+            offset,
+        };
+        match scanner.take_u8()? {
+            NbtScanner::END_ID => return Err(scanner.error("end tag at the root")),
+            id => scanner.payload(id, 0)?,
+        }
+        simdnbt::owned::read_tag(&mut Cursor::new(scanner.buf.as_slice()))
+            .map_err(|error| scanner.error(simdnbt::Error::from(error).to_string()))
     }
 }
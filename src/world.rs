@@ -0,0 +1,190 @@
+//! The world players spawn into.
+
+use anyhow::{Context, bail, ensure};
+
+use crate::{
+    anvil::chunk::SavedChunk,
+    blocks::Blocks,
+    chunk::{ChunkSection, Heightmap, Heightmaps, PalettedContainer, Strategy},
+    packets::play::{ChunkData, LightData},
+    registries::Registries,
+    types::{Identifier, Position},
+};
+
+/// A single dimension where every chunk is the same stack of sections, each
+/// filled with one block.
+#[derive(Clone, PartialEq, Debug)]
+pub struct World {
+    /// The dimension's name.
+    pub dimension: Identifier,
+    /// The ID of the dimension's type in the `minecraft:dimension_type`
+    /// registry.
+    pub dimension_type: u32,
+    /// The Y coordinate of the bottom of the world.
+    pub min_y: i32,
+    /// The block state filling each section, from the bottom up.
+    pub sections: Vec<u32>,
+    /// The ID of the biome everywhere, in the `minecraft:worldgen/biome`
+    /// registry.
+    pub biome: u32,
+    /// The block players spawn on top of.
+    pub spawn: Position,
+}
+
+impl World {
+    /// The block state ID of air.
+    pub const AIR: u32 = 0;
+    /// The block state ID of stone.
+    pub const STONE: u32 = 1;
+    /// The number of blocks in a section.
+    const SECTION_BLOCKS: i16 = 16 * 16 * 16;
+    /// The number of bytes of light data in a section: a nibble per block.
+    const SECTION_LIGHT_BYTES: usize = 2048;
+
+    /// An overworld of plains, with a floor of stone one section thick at the
+    /// bottom and air above it.
+    ///
+    /// # Errors
+    /// If `registries` is missing the overworld or plains, return an error.
+    pub fn flat(registries: &Registries) -> Result<Self, anyhow::Error> {
+        let dimension_types = Identifier::minecraft("dimension_type")?;
+        let overworld = Identifier::minecraft("overworld")?;
+        let dimension_type = registries
+            .id(&dimension_types, &overworld)
+            .context("no overworld dimension type")?;
+        let data = registries
+            .get(&dimension_types, &overworld)
+            .context("no overworld dimension type")?;
+        let (Some(min_y), Some(height)) = (data.int("min_y"), data.int("height")) else {
+            bail!("overworld dimension type has no min_y or height");
+        };
+        let biome = registries
+            .id(
+                &Identifier::minecraft("worldgen/biome")?,
+                &Identifier::minecraft("plains")?,
+            )
+            .context("no plains biome")?;
+
+        let mut sections = vec![Self::AIR; usize::try_from(height / 16)?];
+        sections[0] = Self::STONE;
+        Ok(Self {
+            dimension: overworld,
+            dimension_type,
+            min_y,
+            sections,
+            biome,
+            spawn: Position::new(0, min_y + 15, 0),
+        })
+    }
+
+    /// The chunk at `x`, `z`.
+    #[must_use]
+    pub fn chunk(&self, x: i32, z: i32) -> ChunkData {
+        let sections = self
+            .sections
+            .iter()
+            .map(|&state| ChunkSection {
+                block_count: if state == Self::AIR {
+                    0
+                } else {
+                    Self::SECTION_BLOCKS
+                },
+                block_states: PalettedContainer::single(Strategy::BlockStates, state),
+                biomes: PalettedContainer::single(Strategy::Biomes, self.biome),
+            })
+            .collect();
+        // Every column is as tall as the top of the highest solid section.
+        let top = self
+            .sections
+            .iter()
+            .rposition(|&state| state != Self::AIR)
+            .map_or(0, |top| top as u32 + 1);
+        let heightmaps = Heightmaps {
+            motion_blocking: Heightmap::flat(top * 16),
+            world_surface: Heightmap::flat(top * 16),
+        };
+        ChunkData {
+            x,
+            z,
+            heightmaps: heightmaps.to_nbt(self.sections.len() as u32 * 16),
+            sections,
+            block_entities: Vec::new(),
+            light: self.light(),
+        }
+    }
+
+    /// The chunk saved as `chunk`, with its block states, biomes and block
+    /// entities mapped to their IDs in `blocks` and `registries`. Block states
+    /// that don't exist are air.
+    ///
+    /// # Errors
+    /// If the chunk's sections don't start at the bottom of the world, one is
+    /// malformed, or it has a biome or block entity type that doesn't exist,
+    /// return an error.
+    pub fn load_chunk(
+        &self,
+        chunk: &SavedChunk,
+        registries: &Registries,
+        blocks: &Blocks,
+    ) -> Result<ChunkData, anyhow::Error> {
+        ensure!(
+            chunk.min_section == self.min_y >> 4,
+            "chunk {}, {} starts at section {}, not {}",
+            chunk.x,
+            chunk.z,
+            chunk.min_section,
+            self.min_y >> 4
+        );
+        let biomes = Identifier::minecraft("worldgen/biome")?;
+        let biome_bits = PalettedContainer::direct_bits(registries.count(&biomes));
+        let sections = chunk.to_sections(
+            self.sections.len(),
+            |state| Ok(blocks.id(state).unwrap_or(Self::AIR)),
+            |biome| {
+                registries
+                    .id(&biomes, biome)
+                    .with_context(|| format!("unknown biome {biome}"))
+            },
+            biome_bits,
+        )?;
+        let block_entities = chunk.block_entities(|kind| {
+            blocks
+                .block_entity_type(kind)
+                .with_context(|| format!("unknown block entity type {kind}"))
+        })?;
+        let height = self.sections.len() as u32 * 16;
+        let heightmaps = chunk.heightmaps(height).unwrap_or(Heightmaps {
+            motion_blocking: Heightmap::flat(0),
+            world_surface: Heightmap::flat(0),
+        });
+        Ok(ChunkData {
+            x: chunk.x,
+            z: chunk.z,
+            heightmaps: heightmaps.to_nbt(height),
+            sections,
+            block_entities,
+            light: chunk.light(self.sections.len()),
+        })
+    }
+
+    /// The light of every chunk. Light sections run from one below the world
+    /// to one above it. Sky light is full above the highest solid section and
+    /// dark below it, and there's no block light.
+    #[must_use]
+    pub fn light(&self) -> LightData {
+        let lit_from = self
+            .sections
+            .iter()
+            .rposition(|&state| state != Self::AIR)
+            .map_or(0, |top| top + 2);
+        let mut light = LightData::default();
+        for section in 0..self.sections.len() + 2 {
+            light.sky_light_mask.set(section, section >= lit_from);
+            light.empty_sky_light_mask.set(section, section < lit_from);
+            light.empty_block_light_mask.set(section, true);
+        }
+        let lit = self.sections.len() + 2 - lit_from;
+        light.sky_light = vec![vec![0xFF; Self::SECTION_LIGHT_BYTES]; lit];
+        light
+    }
+}
@@ -0,0 +1,357 @@
+//! Tests for region files, their compression and saved chunks.
+
+use std::fs;
+
+use minimc::{
+    anvil::{
+        ChunkCompression, ChunkStorage, Region,
+        chunk::{BlockState, SavedChunk, SavedContainer, SavedSection},
+    },
+    blocks::Blocks,
+    chunk::{Heightmap, Heightmaps, Palette, Strategy},
+    registries::Registries,
+    types::Identifier,
+    world::World,
+};
+use simdnbt::owned::{Nbt, NbtCompound, NbtList, NbtTag};
+use tempfile::TempDir;
+
+/// An empty directory for a test to put region files in, removed when it's
+/// dropped.
+fn temp_dir() -> TempDir {
+    TempDir::with_prefix("minimc-anvil-").unwrap()
+}
+
+/// Bytes that compress well but aren't all the same.
+fn sample(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i / 7 % 13) as u8).collect()
+}
+
+/// Bytes that barely compress at all.
+fn noise(len: usize) -> Vec<u8> {
+    let mut state = 0x1234_5678u32;
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as u8
+        })
+        .collect()
+}
+
+#[test]
+fn lz4_round_trip() {
+    let lz4 = ChunkCompression::Lz4;
+    for data in [
+        Vec::new(),
+        b"hello".to_vec(),
+        sample(200_000),
+        noise(70_000),
+    ] {
+        let compressed = lz4.compress(&data).unwrap();
+        assert_eq!(&compressed[..8], b"LZ4Block");
+        assert_eq!(lz4.decompress(&compressed).unwrap(), data);
+    }
+    // Blocks are no bigger than 64 KiB, and the stream ends with an empty one.
+    let compressed = lz4.compress(&sample(200_000)).unwrap();
+    assert!(compressed.len() < 20_000);
+    assert_eq!(
+        compressed[compressed.len() - 21..][8..],
+        [0x16, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]
+    );
+
+    let mut corrupt = lz4.compress(b"hello, hello, hello, hello").unwrap();
+    corrupt[21] ^= 1;
+    assert!(lz4.decompress(&corrupt).is_err());
+}
+
+#[test]
+fn compression_round_trip() {
+    let data = sample(10_000);
+    for compression in [
+        ChunkCompression::Gzip,
+        ChunkCompression::Zlib,
+        ChunkCompression::None,
+        ChunkCompression::Lz4,
+    ] {
+        assert_eq!(
+            ChunkCompression::from_id(compression as u8),
+            Some(compression)
+        );
+        let compressed = compression.compress(&data).unwrap();
+        assert_eq!(compression.decompress(&compressed).unwrap(), data);
+    }
+    assert_eq!(ChunkCompression::from_id(127), None);
+}
+
+#[test]
+fn region_read_write() {
+    let dir = temp_dir();
+    let path = dir.path().join(Region::file_name(-1, 33));
+    assert!(path.ends_with("r.-1.1.mca"));
+
+    let mut region = Region::open(&path).unwrap();
+    assert_eq!(fs::metadata(&path).unwrap().len(), 8192);
+    assert!(!region.contains(-1, 33));
+    assert_eq!(region.read(-1, 33).unwrap(), None);
+
+    region
+        .write(-1, 33, b"first", ChunkCompression::Zlib)
+        .unwrap();
+    region
+        .write(0, 32, &sample(10_000), ChunkCompression::None)
+        .unwrap();
+    assert!(region.timestamp(-1, 33).is_some());
+    drop(region);
+
+    // Everything is read back from the file.
+    let mut region = Region::open(&path).unwrap();
+    assert_eq!(region.read(-1, 33).unwrap().unwrap(), b"first");
+    assert_eq!(region.read(0, 32).unwrap().unwrap(), sample(10_000));
+    // The first chunk is in sector 2, and the second in the three after it.
+    assert_eq!(fs::metadata(&path).unwrap().len(), 6 * 4096);
+
+    // A chunk's old sectors are only freed once it's been written elsewhere,
+    // and then the next chunk reuses them.
+    region
+        .write(0, 32, b"smaller", ChunkCompression::Lz4)
+        .unwrap();
+    assert_eq!(fs::metadata(&path).unwrap().len(), 7 * 4096);
+    region
+        .write(1, 32, &sample(8_000), ChunkCompression::None)
+        .unwrap();
+    assert_eq!(fs::metadata(&path).unwrap().len(), 7 * 4096);
+    assert_eq!(region.read(0, 32).unwrap().unwrap(), b"smaller");
+    assert_eq!(region.read(1, 32).unwrap().unwrap(), sample(8_000));
+
+    region.remove(-1, 33).unwrap();
+    assert!(!region.contains(-1, 33));
+    drop(region);
+    let mut region = Region::open(&path).unwrap();
+    assert_eq!(region.read(-1, 33).unwrap(), None);
+    assert_eq!(region.read(0, 32).unwrap().unwrap(), b"smaller");
+}
+
+#[test]
+fn external_chunks() {
+    let dir = temp_dir();
+    let path = dir.path().join(Region::file_name(0, 0));
+    let mcc = dir.path().join("c.3.4.mcc");
+    let data = noise(1_100_000);
+
+    let mut region = Region::open(&path).unwrap();
+    region.write(3, 4, &data, ChunkCompression::None).unwrap();
+    assert_eq!(fs::read(&mcc).unwrap(), data);
+    // Only the header of the chunk is in the region file.
+    assert_eq!(fs::metadata(&path).unwrap().len(), 3 * 4096);
+    assert_eq!(region.read(3, 4).unwrap().unwrap(), data);
+
+    region
+        .write(3, 4, b"small", ChunkCompression::None)
+        .unwrap();
+    assert!(!mcc.exists());
+    assert_eq!(region.read(3, 4).unwrap().unwrap(), b"small");
+}
+
+#[test]
+fn bad_locations_are_ignored() {
+    let dir = temp_dir();
+    let path = dir.path().join(Region::file_name(0, 0));
+    let mut region = Region::open(&path).unwrap();
+    region
+        .write(0, 0, b"chunk", ChunkCompression::None)
+        .unwrap();
+    drop(region);
+
+    // Point a second chunk at the first's sector, and a third past the end.
+    let mut bytes = fs::read(&path).unwrap();
+    bytes.copy_within(..4, 4);
+    bytes[8..12].copy_from_slice(&[0, 0, 9, 1]);
+    fs::write(&path, bytes).unwrap();
+
+    let mut region = Region::open(&path).unwrap();
+    assert_eq!(region.read(0, 0).unwrap().unwrap(), b"chunk");
+    assert!(!region.contains(1, 0));
+    assert!(!region.contains(2, 0));
+}
+
+/// A chunk with stone in the bottom half of its lowest section, and a few
+/// other saved tags.
+fn saved_chunk() -> SavedChunk {
+    let stone = BlockState::new(Identifier::minecraft("stone").unwrap());
+    let mut log = BlockState::new(Identifier::minecraft("oak_log").unwrap());
+    log.properties.push(("axis".to_owned(), "y".to_owned()));
+    let air = BlockState::new(Identifier::minecraft("air").unwrap());
+    let states: Vec<BlockState> = (0..4096)
+        .map(|i| match i {
+            0 => log.clone(),
+            ..2048 => stone.clone(),
+            _ => air.clone(),
+        })
+        .collect();
+    let plains = Identifier::minecraft("plains").unwrap();
+
+    let mut furnace = NbtCompound::new();
+    furnace.insert("id", NbtTag::String("minecraft:furnace".into()));
+    furnace.insert("x", NbtTag::Int(83));
+    furnace.insert("y", NbtTag::Int(-63));
+    furnace.insert("z", NbtTag::Int(-94));
+    furnace.insert("keepPacked", NbtTag::Byte(0));
+    furnace.insert("BurnTime", NbtTag::Short(20));
+    let mut rest = NbtCompound::new();
+    rest.insert("LastUpdate", NbtTag::Long(1234));
+    rest.insert(
+        "block_entities",
+        NbtTag::List(NbtList::Compound(vec![furnace])),
+    );
+    SavedChunk {
+        data_version: 3955,
+        x: 5,
+        z: -6,
+        min_section: -4,
+        status: "minecraft:full".to_owned(),
+        sections: vec![
+            SavedSection {
+                y: -5,
+                block_states: None,
+                biomes: None,
+                block_light: None,
+                sky_light: Some(vec![0; 2048]),
+            },
+            SavedSection {
+                y: -4,
+                block_states: Some(SavedContainer::new(Strategy::BlockStates, &states)),
+                biomes: Some(SavedContainer::new(Strategy::Biomes, &vec![plains; 64])),
+                block_light: Some(vec![0x11; 2048]),
+                sky_light: None,
+            },
+        ],
+        rest,
+    }
+}
+
+#[test]
+fn saved_chunk_round_trip() {
+    let chunk = saved_chunk();
+    let states = chunk.sections[1].block_states.as_ref().unwrap();
+    assert_eq!(states.palette.len(), 3);
+    // Three entries still take the minimum of 4 bits, and one takes none.
+    assert_eq!(states.data.len(), 256);
+    assert!(chunk.sections[1].biomes.as_ref().unwrap().data.is_empty());
+
+    let nbt = chunk.to_nbt();
+    assert_eq!(nbt.int("yPos"), Some(-4));
+    assert_eq!(nbt.long("LastUpdate"), Some(1234));
+    assert_eq!(SavedChunk::from_nbt(nbt).unwrap(), chunk);
+
+    let mut old = NbtCompound::new();
+    old.insert("Level", NbtTag::Compound(chunk.to_nbt()));
+    assert!(SavedChunk::from_nbt(old).is_err());
+
+    let dir = temp_dir();
+    let mut region = Region::open(&dir.path().join(Region::file_name(5, -6))).unwrap();
+    region.write_chunk(&chunk, ChunkCompression::Lz4).unwrap();
+    assert_eq!(region.read_chunk(5, -6).unwrap(), Some(chunk));
+    assert_eq!(region.read_chunk(6, -6).unwrap(), None);
+}
+
+#[test]
+fn saved_chunk_to_sections() {
+    let chunk = saved_chunk();
+    let block_state = |state: &BlockState| match state.name.path() {
+        "air" => Ok(0),
+        "stone" => Ok(1),
+        _ => Ok(2),
+    };
+    let sections = chunk.to_sections(3, block_state, |_| Ok(7), 6).unwrap();
+    assert_eq!(sections.len(), 3);
+
+    assert_eq!(sections[0].block_count, 2048);
+    assert_eq!(sections[0].block_states.get(0), 2);
+    assert_eq!(sections[0].block_states.get(1), 1);
+    assert_eq!(sections[0].block_states.get(2048), 0);
+    assert_eq!(sections[0].biomes.palette(), &Palette::Single(7));
+    // Sections that weren't saved are air.
+    assert_eq!(sections[2].block_count, 0);
+    assert_eq!(sections[2].block_states.palette(), &Palette::Single(0));
+
+    let light = chunk.light(3);
+    assert!(light.sky_light_mask.get(0));
+    assert!(light.block_light_mask.get(1));
+    assert_eq!(light.sky_light.len(), 1);
+    assert_eq!(light.block_light, vec![vec![0x11; 2048]]);
+
+    // Block entities keep their data, without their position and type.
+    let block_entities = chunk.block_entities(|_| Ok(0)).unwrap();
+    assert_eq!(block_entities.len(), 1);
+    assert_eq!(block_entities[0].packed_xz, 0x32);
+    assert_eq!(block_entities[0].y, -63);
+    let Nbt::Some(data) = &block_entities[0].data else {
+        panic!("block entity has no data");
+    };
+    assert_eq!(data.short("BurnTime"), Some(20));
+    assert_eq!(data.len(), 1);
+    assert!(chunk.block_entities(|_| anyhow::bail!("unknown")).is_err());
+}
+
+#[test]
+fn vanilla_region() {
+    // Chunks 0..2, 0..2 of a world saved by vanilla 1.19.4, from fastanvil's
+    // test resources.
+    let dir = temp_dir();
+    let path = dir.path().join("r.0.0.mca");
+    fs::copy("tests/data/r.0.0.mca", &path).unwrap();
+    let storage = ChunkStorage::new(dir.path());
+    assert_eq!(storage.chunk(2, 0).unwrap(), None);
+    // Region files aren't created just to look for chunks.
+    assert_eq!(storage.chunk(-1, 0).unwrap(), None);
+    assert!(!dir.path().join("r.-1.0.mca").exists());
+
+    let saved = storage.chunk(1, 1).unwrap().unwrap();
+    assert_eq!((saved.data_version, saved.x, saved.z), (3337, 1, 1));
+    assert_eq!(saved.status, "full");
+    let world = World::flat(Registries::vanilla()).unwrap();
+    let chunk = world
+        .load_chunk(&saved, Registries::vanilla(), Blocks::vanilla())
+        .unwrap();
+    assert_eq!((chunk.x, chunk.z), (1, 1));
+    assert_eq!(chunk.sections.len(), 24);
+    // The bottom of the world is bedrock, and the top is air.
+    let bedrock = Blocks::vanilla()
+        .id(&BlockState::new(Identifier::minecraft("bedrock").unwrap()))
+        .unwrap();
+    assert_eq!(chunk.sections[0].block_states.get(0), bedrock);
+    assert_eq!(chunk.sections[23].block_count, 0);
+    let heightmaps = Heightmaps::from_nbt(&chunk.heightmaps, 384).unwrap();
+    assert_ne!(heightmaps.world_surface, Heightmap::flat(16));
+    assert!(!chunk.light.sky_light.is_empty());
+
+    // Chunks that haven't changed aren't saved again.
+    let timestamp = Region::open(&path).unwrap().timestamp(1, 1).unwrap();
+    storage.save().unwrap();
+    assert_eq!(
+        Region::open(&path).unwrap().timestamp(1, 1),
+        Some(timestamp)
+    );
+
+    // Changed chunks are kept until they're saved, and only they're written.
+    let mut changed = SavedChunk::clone(&saved);
+    changed.rest.insert("LastUpdate", NbtTag::Long(4321));
+    storage.insert(changed.clone());
+    storage.unload(|_, _| false);
+    assert!(storage.is_loaded(1, 1));
+    storage.save().unwrap();
+    let mut region = Region::open(&path).unwrap();
+    assert!(region.timestamp(1, 1).unwrap() > timestamp);
+    assert_eq!(region.timestamp(0, 0), Some(1_681_322_801));
+    assert_eq!(region.read_chunk(1, 1).unwrap(), Some(changed.clone()));
+    assert_eq!(storage.chunk(1, 1).unwrap().as_deref(), Some(&changed));
+
+    // Once saved, chunks out of view are dropped, and read again if needed.
+    assert!(storage.chunk(0, 0).unwrap().is_some());
+    storage.unload(|x, _| x == 0);
+    assert!(storage.is_loaded(0, 0));
+    assert!(!storage.is_loaded(1, 1));
+    assert_eq!(storage.chunk(1, 1).unwrap().as_deref(), Some(&changed));
+}
@@ -0,0 +1,122 @@
+//! Tests for the async framing path.
+#![cfg(feature = "async")]
+
+use core::{
+    pin::pin,
+    task::{Context, Poll, Waker},
+};
+
+use minimc::{
+    ProtocolError,
+    async_io::{AsyncMcReader, read_var_int},
+    io::SliceReader,
+    packet::{Compression, Packet},
+};
+
+/// Poll `future` to completion on this thread.
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let mut cx = Context::from_waker(Waker::noop());
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+    }
+}
+
+/// Hands out one byte per read call, yielding to the executor before each.
+struct Trickle<'a>(SliceReader<'a>);
+
+impl AsyncMcReader for Trickle<'_> {
+    async fn read(&mut self, bytes: &mut [u8]) -> Result<(), ProtocolError> {
+        for byte in bytes {
+            let mut yielded = false;
+            core::future::poll_fn(|_| {
+                if yielded {
+                    Poll::Ready(())
+                } else {
+                    yielded = true;
+                    Poll::Pending
+                }
+            })
+            .await;
+            *byte = self.0.read_byte().await?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn round_trip() {
+    for compression in [None, Some(Compression::new(16))] {
+        let packets = [
+            Packet {
+                id: 0x01,
+                body: vec![1, 2, 3],
+            },
+            Packet {
+                id: 0x27,
+                body: vec![9; 300],
+            },
+        ];
+        let mut bytes = Vec::new();
+        for packet in &packets {
+            block_on(packet.write_async(&mut bytes, compression)).unwrap();
+        }
+
+        let mut reader = Trickle(SliceReader::new(&bytes));
+        for packet in &packets {
+            let read = block_on(Packet::read_async(&mut reader, compression)).unwrap();
+            assert_eq!(&read, packet);
+        }
+        assert!(block_on(Packet::read_async(&mut reader, compression)).is_err());
+    }
+}
+
+#[test]
+fn decodes_values() {
+    let mut bytes = Vec::new();
+    block_on(
+        Packet::encode::<_, i64>(0x01, -42)
+            .unwrap()
+            .write_async(&mut bytes, None),
+    )
+    .unwrap();
+    let mut reader = SliceReader::new(&bytes);
+    let (id, value) =
+        block_on(Packet::read_value_async::<i64, i64>(&mut reader, None, ())).unwrap();
+    assert_eq!((id, value), (0x01, -42));
+}
+
+#[test]
+fn var_int_limits() {
+    let mut reader = SliceReader::new(&[0xDD, 0xC7, 0x01]);
+    assert_eq!(block_on(read_var_int(&mut reader)).unwrap(), 25565);
+    let mut reader = SliceReader::new(&[0x80; 6]);
+    assert!(matches!(
+        block_on(read_var_int(&mut reader)),
+        Err(ProtocolError::VarIntTooLong { max: 5, .. })
+    ));
+}
+
+#[tokio::test]
+async fn tokio_duplex() {
+    let (mut client, mut server) = tokio::io::duplex(64);
+    let packet = Packet {
+        id: 0x27,
+        body: vec![7; 1000],
+    };
+    // The pipe is smaller than the packet, so both ends have to take turns.
+    let (written, read) = tokio::join!(
+        packet.write_async(&mut client, None),
+        Packet::read_async(&mut server, None)
+    );
+    written.unwrap();
+    assert_eq!(read.unwrap(), packet);
+
+    drop(client);
+    assert!(matches!(
+        Packet::read_async(&mut server, None).await,
+        Err(ProtocolError::UnexpectedEof { offset: None })
+    ));
+}
@@ -0,0 +1,74 @@
+//! Tests for block state IDs.
+
+use minimc::{anvil::chunk::BlockState, blocks::Blocks, types::Identifier};
+use serde_json::json;
+
+/// The block state `name` with `properties`.
+fn state(name: &str, properties: &[(&str, &str)]) -> BlockState {
+    BlockState {
+        name: name.parse().unwrap(),
+        properties: properties
+            .iter()
+            .map(|&(key, value)| (key.to_owned(), value.to_owned()))
+            .collect(),
+    }
+}
+
+#[test]
+fn vanilla_block_states() {
+    let blocks = Blocks::vanilla();
+    assert_eq!(blocks.states(), 26_684);
+    assert_eq!(
+        blocks.id(&BlockState::new(Identifier::minecraft("air").unwrap())),
+        Some(0)
+    );
+    assert_eq!(blocks.id(&state("stone", &[])), Some(1));
+    assert_eq!(
+        blocks.id(&state("grass_block", &[("snowy", "true")])),
+        Some(8)
+    );
+    // Missing properties take their default value.
+    assert_eq!(blocks.id(&state("grass_block", &[])), Some(9));
+    assert_eq!(blocks.id(&state("oak_log", &[("axis", "z")])), Some(132));
+    assert_eq!(blocks.id(&state("heavy_core", &[])), Some(26_683));
+    // Properties the block doesn't have are ignored.
+    assert_eq!(blocks.id(&state("stone", &[("axis", "z")])), Some(1));
+
+    assert_eq!(blocks.id(&state("oak_log", &[("axis", "w")])), None);
+    // Renamed to short_grass in 1.20.3.
+    assert_eq!(blocks.id(&state("grass", &[])), None);
+
+    let block_entity_type = |kind| blocks.block_entity_type(&Identifier::minecraft(kind).unwrap());
+    assert_eq!(block_entity_type("furnace"), Some(0));
+    assert_eq!(block_entity_type("sign"), Some(7));
+    assert_eq!(block_entity_type("vault"), Some(43));
+    assert_eq!(block_entity_type("stone"), None);
+}
+
+#[test]
+fn last_property_changes_fastest() {
+    let blocks = Blocks::from_json(&json!({
+        "block_entity_types": [],
+        "blocks": {
+            "minecraft:air": {"id": 0},
+            "minecraft:lamp": {
+                "id": 1,
+                "default": 4,
+                "properties": {"lit": ["true", "false"], "level": ["0", "1", "2"]},
+            },
+        },
+    }))
+    .unwrap();
+    assert_eq!(blocks.states(), 7);
+    assert_eq!(blocks.id(&state("lamp", &[])), Some(4));
+    assert_eq!(
+        blocks.id(&state("lamp", &[("lit", "false"), ("level", "2")])),
+        Some(6)
+    );
+    assert_eq!(blocks.id(&state("lamp", &[("level", "2")])), Some(6));
+
+    // States must be numbered one block after another.
+    let blocks = |blocks| json!({"block_entity_types": [], "blocks": blocks});
+    assert!(Blocks::from_json(&blocks(json!({"minecraft:air": {"id": 1}}))).is_err());
+    assert!(Blocks::from_json(&blocks(json!({"minecraft:air": {"id": 0, "default": 1}}))).is_err());
+}
@@ -0,0 +1,143 @@
+//! Tests for borrowed decoding with `McProtoBorrow`.
+
+use minimc::{
+    McProto, McProtoBorrow, ProtocolError,
+    packet::Packet,
+    types::{ByteArray, NbtMeta},
+};
+use simdnbt::{
+    borrow::BaseNbtCompound,
+    owned::{BaseNbt, Nbt, NbtCompound, NbtTag},
+};
+
+/// A chat message, owned for encoding.
+#[derive(Clone, PartialEq, Debug, McProto)]
+struct Chat {
+    #[mc(max_len = 256)]
+    message: String,
+    timestamp: i64,
+    #[mc(with = ByteArray, max_len = 256)]
+    signature: Vec<u8>,
+}
+
+/// The same chat message, borrowed from the frame.
+#[derive(PartialEq, Debug, McProtoBorrow)]
+struct ChatRef<'a> {
+    #[mc(max_len = 256)]
+    message: &'a str,
+    timestamp: i64,
+    #[mc(with = ByteArray, max_len = 256)]
+    signature: &'a [u8],
+}
+
+/// A movement packet with nothing to borrow, which gets `McProtoBorrow` from
+/// its `McProto` impl.
+#[derive(Clone, PartialEq, Debug, McProto)]
+#[mc(tag = varint)]
+enum Movement {
+    Position { x: f64, y: f64, z: f64 },
+    OnGround(bool),
+}
+
+/// Whether `inner` lies within `outer`.
+fn points_into(inner: &[u8], outer: &[u8]) -> bool {
+    outer.as_ptr_range().contains(&inner.as_ptr())
+}
+
+#[test]
+fn borrowed_fields_point_into_the_frame() {
+    let chat = Chat {
+        message: "hello \u{1F600}".to_owned(),
+        timestamp: 1_700_000_000,
+        signature: vec![7; 32],
+    };
+    let packet = Packet::encode::<_, Chat>(0x06, chat.clone()).unwrap();
+    let borrowed = packet.decode_borrowed::<ChatRef, ChatRef>(()).unwrap();
+    assert_eq!(borrowed.message, chat.message);
+    assert_eq!(borrowed.timestamp, chat.timestamp);
+    assert_eq!(borrowed.signature, chat.signature);
+    assert!(points_into(borrowed.message.as_bytes(), &packet.body));
+    assert!(points_into(borrowed.signature, &packet.body));
+
+    let movement = Movement::Position {
+        x: 1.5,
+        y: 64.0,
+        z: -3.25,
+    };
+    let packet = Packet::encode::<_, Movement>(0x1A, movement.clone()).unwrap();
+    assert_eq!(
+        packet.decode_borrowed::<Movement, Movement>(()).unwrap(),
+        movement
+    );
+}
+
+#[test]
+fn borrowed_limits_match_owned() {
+    let decode = |body: Vec<u8>| {
+        Packet { id: 0x00, body }
+            .decode_borrowed::<&str, &str>(minimc::types::Length::new(2))
+            .map(str::len)
+    };
+    assert!(matches!(
+        decode(vec![3, b'a', b'b', b'c']),
+        Err(ProtocolError::StringTooLong { len: 3, max: 2, .. })
+    ));
+    assert!(matches!(
+        decode(vec![2, 0xC3, 0x28]),
+        Err(ProtocolError::InvalidUtf8 {
+            offset: Some(0),
+            ..
+        })
+    ));
+    assert!(matches!(
+        decode(vec![4, b'a']),
+        Err(ProtocolError::UnexpectedEof { offset: Some(1) })
+    ));
+    assert!(matches!(
+        decode(vec![1, b'a', b'b']),
+        Err(ProtocolError::Invalid {
+            offset: Some(2),
+            ..
+        })
+    ));
+}
+
+#[test]
+fn borrowed_nbt() {
+    let compound = NbtCompound::from_values(vec![
+        ("name".into(), NbtTag::String("stone".into())),
+        ("count".into(), NbtTag::Int(64)),
+    ]);
+    let mut body = Vec::new();
+    Nbt::Some(BaseNbt::new("", compound)).write_unnamed(&mut body);
+    let packet = Packet { id: 0x00, body };
+
+    let nbt = packet
+        .decode_borrowed::<simdnbt::borrow::Nbt, simdnbt::borrow::Nbt>(NbtMeta::NETWORK)
+        .unwrap();
+    let nbt = nbt.unwrap();
+    assert_eq!(nbt.int("count"), Some(64));
+    let name = nbt.string("name").unwrap();
+    assert_eq!(name.to_str(), "stone");
+    assert!(points_into(name.as_bytes(), &packet.body));
+
+    let compound = packet
+        .decode_borrowed::<BaseNbtCompound, BaseNbtCompound>(())
+        .unwrap();
+    assert_eq!(
+        simdnbt::borrow::NbtCompound::from(&compound).int("count"),
+        Some(64)
+    );
+
+    let strict = NbtMeta {
+        max_size: 8,
+        ..NbtMeta::NETWORK
+    };
+    assert!(matches!(
+        packet.decode_borrowed::<simdnbt::borrow::Nbt, simdnbt::borrow::Nbt>(strict),
+        Err(ProtocolError::NbtError {
+            offset: Some(0),
+            ..
+        })
+    ));
+}
@@ -0,0 +1,199 @@
+//! Tests for chunk sections, paletted containers and heightmaps, against
+//! bytes laid out by hand from the protocol and a chunk as vanilla sends it.
+
+use minimc::{
+    McProtoSelf, ProtocolError,
+    chunk::{ChunkSection, Heightmap, Heightmaps, Palette, PalettedContainer, Strategy},
+    io::SliceReader,
+    packet::Packet,
+    packets::{
+        Clientbound, Registry, State,
+        play::{LightData, UpdateLight},
+    },
+    types::BitSet,
+};
+
+/// Encode `value` on its own.
+fn encode<T: McProtoSelf>(value: T) -> Vec<u8> {
+    let mut out = Vec::new();
+    value.write(&mut out).unwrap();
+    out
+}
+
+/// Decode a `T` from all of `bytes`.
+fn decode<T: McProtoSelf>(bytes: &[u8], meta: T::Meta) -> Result<T, ProtocolError> {
+    let mut reader = SliceReader::new(bytes);
+    let value = T::read(&mut reader, meta)?;
+    assert!(reader.is_empty(), "trailing bytes");
+    Ok(value)
+}
+
+#[test]
+fn indirect_section() {
+    // Stone (1) in the bottom half, air (0) in the top half, in plains (0).
+    let states: Vec<u32> = (0..4096).map(|i| u32::from(i < 2048)).collect();
+    let section = ChunkSection {
+        block_count: 2048,
+        block_states: PalettedContainer::new(Strategy::BlockStates, &states, 15),
+        biomes: PalettedContainer::new(Strategy::Biomes, &[0; 64], 1),
+    };
+
+    // The block count, then two palette entries at the minimum of 4 bits, 16
+    // to a long. Stone comes first, as it's seen first.
+    let mut expected = vec![0x08, 0x00, 4, 2, 1, 0, 0x80, 0x02];
+    expected.extend([0x00; 128 * 8]);
+    expected.extend([0x11; 128 * 8]);
+    // A single-valued biome container still has an empty data array.
+    expected.extend([0, 0, 0]);
+
+    assert_eq!(section.encoded_len(), expected.len());
+    assert_eq!(encode(section.clone()), expected);
+    let decoded: ChunkSection = decode(&expected, ()).unwrap();
+    assert_eq!(decoded, section);
+    assert_eq!(decoded.block_states.values(), states);
+    assert_eq!(decoded.biomes.get(63), 0);
+}
+
+#[test]
+fn direct_and_biome_palettes() {
+    // Every block different is too many for an indirect palette: 15-bit
+    // global IDs, four to a long.
+    let states: Vec<u32> = (0..4096).collect();
+    let container = PalettedContainer::new(Strategy::BlockStates, &states, 15);
+    assert_eq!(container.palette(), &Palette::Direct);
+    let bytes = encode(container.clone());
+    assert_eq!(bytes[..3], [15, 0x80, 0x08]);
+    assert_eq!(
+        bytes[3..11],
+        [0x00, 0x00, 0x60, 0x00, 0x80, 0x00, 0x80, 0x00]
+    );
+    assert_eq!(bytes.len(), 3 + 1024 * 8);
+    assert_eq!(
+        decode::<PalettedContainer>(&bytes, Strategy::BlockStates).unwrap(),
+        container
+    );
+    assert_eq!(container.get(4095), 4095);
+
+    // Alternating biomes take one bit each, in a single long.
+    let biomes: Vec<u32> = (0..64).map(|i| [7, 3][i % 2]).collect();
+    let container = PalettedContainer::new(Strategy::Biomes, &biomes, 6);
+    let bytes = encode(container.clone());
+    assert_eq!(
+        bytes,
+        [
+            1, 2, 7, 3, 1, 0xAA, 0xAA, 0xAA, 0xAA, 0xAA, 0xAA, 0xAA, 0xAA
+        ]
+    );
+    assert_eq!(
+        decode::<PalettedContainer>(&bytes, Strategy::Biomes).unwrap(),
+        container
+    );
+}
+
+#[test]
+fn bits_per_entry_thresholds() {
+    let bits = |strategy: Strategy, distinct: u32| {
+        let values: Vec<u32> = (0..strategy.entries() as u32)
+            .map(|i| i % distinct)
+            .collect();
+        let container = PalettedContainer::new(strategy, &values, 9);
+        assert_eq!(container.values(), values);
+        (
+            container.bits(),
+            matches!(container.palette(), Palette::Direct),
+        )
+    };
+    assert_eq!(bits(Strategy::BlockStates, 1), (0, false));
+    assert_eq!(bits(Strategy::BlockStates, 2), (4, false));
+    assert_eq!(bits(Strategy::BlockStates, 16), (4, false));
+    assert_eq!(bits(Strategy::BlockStates, 17), (5, false));
+    assert_eq!(bits(Strategy::BlockStates, 256), (8, false));
+    assert_eq!(bits(Strategy::BlockStates, 257), (9, true));
+    assert_eq!(bits(Strategy::Biomes, 2), (1, false));
+    assert_eq!(bits(Strategy::Biomes, 3), (2, false));
+    assert_eq!(bits(Strategy::Biomes, 8), (3, false));
+    assert_eq!(bits(Strategy::Biomes, 9), (9, true));
+}
+
+#[test]
+fn rejects_bad_containers() {
+    // Too few bits for a block state palette.
+    assert!(decode::<PalettedContainer>(&[2, 1, 0, 0], Strategy::BlockStates).is_err());
+    // The wrong number of longs.
+    assert!(decode::<PalettedContainer>(&[1, 1, 0, 0], Strategy::Biomes).is_err());
+    // An index past the end of the palette.
+    let mut bytes = vec![1, 1, 0, 1];
+    bytes.extend([0xFF; 8]);
+    assert!(decode::<PalettedContainer>(&bytes, Strategy::Biomes).is_err());
+}
+
+#[test]
+fn heightmap_packing() {
+    // Heights up to 384 need 9 bits, seven to a long.
+    let heightmap = Heightmap::flat(16);
+    let longs = heightmap.to_longs(384);
+    assert_eq!(longs.len(), 37);
+    assert_eq!(longs[0], 0x0402_0100_8040_2010);
+    assert_eq!(longs[36], 0x8040_2010);
+    assert_eq!(Heightmap::from_longs(&longs, 384), Some(heightmap));
+    assert_eq!(Heightmap::from_longs(&longs[1..], 384), None);
+}
+
+#[test]
+fn update_light() {
+    // Chunk 1, -1 with full sky light in section 2 only.
+    let mut body = vec![0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0x0F];
+    body.extend([1, 0, 0, 0, 0, 0, 0, 0, 0x04, 0, 0, 0, 1, 0x80, 0x10]);
+    body.extend([0xFF; 2048]);
+    body.push(0);
+
+    let mut sky_light_mask = BitSet::default();
+    sky_light_mask.set(2, true);
+    let expected = Clientbound::UpdateLight(UpdateLight {
+        x: 1,
+        z: -1,
+        light: LightData {
+            sky_light_mask,
+            sky_light: vec![vec![0xFF; 2048]],
+            ..LightData::default()
+        },
+    });
+    let registry = Registry::latest();
+    let packet = Packet { id: 0x2A, body };
+    let decoded: Clientbound = registry.decode(State::Play, packet.clone()).unwrap();
+    assert_eq!(decoded, expected);
+    assert_eq!(registry.encode(State::Play, decoded).unwrap(), packet);
+}
+
+#[test]
+fn vanilla_chunk_data() {
+    // Chunk 1, 1 of tests/data/r.0.0.mca as vanilla 1.21.1 sends it once it's
+    // loaded: the palettes, packed longs, heightmaps and light are the ones
+    // vanilla saved, with block states and biomes as 1.21.1 numbers them.
+    let body = include_bytes!("data/chunk_data.bin").to_vec();
+    let registry = Registry::latest();
+    let packet = Packet { id: 0x27, body };
+    let decoded: Clientbound = registry.decode(State::Play, packet.clone()).unwrap();
+    let Clientbound::ChunkData(chunk) = &decoded else {
+        panic!("expected chunk data");
+    };
+    assert_eq!((chunk.x, chunk.z), (1, 1));
+    assert_eq!(chunk.sections.len(), 24);
+    // Bedrock (79) at the bottom, and nothing but air at the top.
+    assert_eq!(chunk.sections[0].block_states.get(0), 79);
+    assert_eq!(chunk.sections[23].block_count, 0);
+    assert_eq!(
+        chunk.sections[23].block_states.palette(),
+        &Palette::Single(0)
+    );
+    let heightmaps = Heightmaps::from_nbt(&chunk.heightmaps, 384).unwrap();
+    assert_ne!(heightmaps.motion_blocking, Heightmap::flat(0));
+    assert!(chunk.block_entities.is_empty());
+    // Light for the sections from the one below the world up, as saved.
+    assert_eq!(chunk.light.sky_light.len(), 4);
+    assert!(chunk.light.sky_light_mask.get(8));
+    assert_eq!(chunk.light.block_light.len(), 5);
+    assert!(chunk.light.block_light_mask.get(3));
+
+    assert_eq!(registry.encode(State::Play, decoded).unwrap(), packet);
+}
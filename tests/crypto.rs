@@ -0,0 +1,85 @@
+//! Tests for the cryptography and session authentication.
+
+use cfb8::cipher::KeyIvInit;
+use minimc::{
+    auth::{self, GameProfile},
+    crypto::{
+        Decryptor, Encryptor, random_bytes,
+        rsa::{RsaPrivateKey, RsaPublicKey},
+    },
+    types::Uuid,
+};
+use serde_json::json;
+
+/// Decode a hex string.
+fn hex(text: &str) -> Vec<u8> {
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&text[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+#[test]
+fn cfb8_streams() {
+    // SP 800-38A F.3.7, split across calls the way packets are.
+    let key: [u8; 16] = hex("2b7e151628aed2a6abf7158809cf4f3c").try_into().unwrap();
+    let iv: [u8; 16] = hex("000102030405060708090a0b0c0d0e0f").try_into().unwrap();
+    let plaintext = hex("6bc1bee22e409f96e93d7e117393172aae2d");
+    let mut data = plaintext.clone();
+    let mut encryptor = Encryptor::new(&key.into(), &iv.into());
+    let (first, second) = data.split_at_mut(5);
+    encryptor.encrypt(first);
+    encryptor.encrypt(second);
+    assert_eq!(data, hex("3b79424c9c0dd436bace9e0ed4586a4f32b9"));
+    Decryptor::new(&key.into(), &iv.into()).decrypt(&mut data);
+    assert_eq!(data, plaintext);
+}
+
+#[test]
+fn server_hashes() {
+    for (name, digest) in [
+        ("Notch", "4ed1f46bbe04bc756bcb17c0c7ce3e4632f06a48"),
+        ("jeb_", "-7c9d5b0044c130109a5d7b5fb5c317c02b4e28c1"),
+        ("simon", "88e16a1019277b15d58faf0541e11910eb756f6"),
+    ] {
+        assert_eq!(auth::server_hash(name, &[], &[]), digest);
+    }
+}
+
+#[test]
+fn rsa_round_trip() {
+    let key = RsaPrivateKey::generate(1024).unwrap();
+    let public = RsaPublicKey::from_der(&key.public_key().to_der()).unwrap();
+    assert_eq!(&public, key.public_key());
+    let ciphertext = public.encrypt(b"shared secret!!!").unwrap();
+    assert_eq!(ciphertext.len(), 128);
+    assert_eq!(key.decrypt(&ciphertext).unwrap(), b"shared secret!!!");
+    assert!(key.decrypt(&[0; 128]).is_err());
+    assert!(key.decrypt(&[0; 5]).is_err());
+
+    let mut bytes = [0; 32];
+    random_bytes(&mut bytes).unwrap();
+    assert_ne!(bytes, [0; 32]);
+}
+
+#[test]
+fn parses_session_profiles() {
+    let profile = GameProfile::from_json(&json!({
+        "id": "069a79f444e94726a5befca90e38aaf5",
+        "name": "Notch",
+        "properties": [{"name": "textures", "value": "e30=", "signature": "c2ln"}],
+    }))
+    .unwrap();
+    assert_eq!(profile.id, Uuid(0x069a_79f4_44e9_4726_a5be_fca9_0e38_aaf5));
+    assert_eq!(profile.name, "Notch");
+    assert_eq!(profile.properties[0].value, "e30=");
+    assert_eq!(profile.properties[0].signature.as_deref(), Some("c2ln"));
+
+    for json in [
+        json!({"name": "Notch"}),
+        json!({"id": "069a79f4-44e9-4726-a5be-fca90e38aaf5", "name": "Notch"}),
+        json!({"id": "069a79f444e94726a5befca90e38aaf5"}),
+    ] {
+        assert!(GameProfile::from_json(&json).is_err(), "{json}");
+    }
+}
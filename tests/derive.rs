@@ -0,0 +1,83 @@
+//! Tests for `#[derive(McProto)]`.
+
+use minimc::{McProto, packet::Packet};
+
+#[derive(Clone, PartialEq, Debug, McProto)]
+struct Login {
+    #[mc(max_len = 16)]
+    name: String,
+    #[mc(varint)]
+    id: u32,
+    flags: u8,
+}
+
+#[derive(Clone, PartialEq, Debug, McProto)]
+struct Wrapper(#[mc(varlong)] u64, bool);
+
+#[derive(Clone, PartialEq, Debug, McProto)]
+#[mc(tag = u8)]
+enum Action {
+    Idle,
+    Move {
+        x: f64,
+        z: f64,
+    },
+    #[mc(id = 0x10)]
+    Say(#[mc(max_len = 256)] String),
+    Wave,
+}
+
+/// Encode `value` into a packet body and decode it again.
+fn round_trip<T: minimc::McProtoSelf<Meta = ()>>(value: T) -> (Vec<u8>, T) {
+    let packet = Packet::encode::<_, T>(0x00, value).unwrap();
+    let decoded = packet.decode::<T, T>(()).unwrap();
+    (packet.body, decoded)
+}
+
+#[test]
+fn structs() {
+    let login = Login {
+        name: "abc".to_owned(),
+        id: 300,
+        flags: 7,
+    };
+    let (bytes, decoded) = round_trip(login.clone());
+    assert_eq!(bytes, [3, b'a', b'b', b'c', 0xAC, 0x02, 7]);
+    assert_eq!(decoded, login);
+
+    let wrapper = Wrapper(1, false);
+    let (bytes, decoded) = round_trip(wrapper.clone());
+    assert_eq!(bytes, [1, 0]);
+    assert_eq!(decoded, wrapper);
+}
+
+#[test]
+fn enums() {
+    let (bytes, decoded) = round_trip(Action::Idle);
+    assert_eq!(bytes, [0]);
+    assert_eq!(decoded, Action::Idle);
+
+    let action = Action::Move { x: 1.0, z: -2.0 };
+    let (bytes, decoded) = round_trip(action.clone());
+    assert_eq!(bytes[0], 1);
+    assert_eq!(decoded, action);
+
+    let (bytes, _) = round_trip(Action::Say("hi".to_owned()));
+    assert_eq!(bytes, [0x10, 2, b'h', b'i']);
+
+    let (bytes, _) = round_trip(Action::Wave);
+    assert_eq!(bytes, [0x11]);
+
+    let packet = Packet {
+        id: 0,
+        body: vec![0x05],
+    };
+    assert!(matches!(
+        packet.decode::<Action, Action>(()),
+        Err(minimc::ProtocolError::InvalidEnum {
+            offset: Some(0),
+            name: "Action",
+            value: 5,
+        })
+    ));
+}
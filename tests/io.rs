@@ -0,0 +1,63 @@
+//! Tests for the reader and writer adapters.
+
+use std::io::Cursor;
+
+use minimc::{
+    McProto, McProtoSelf, McReader, McWriter, ProtocolError,
+    io::{CountingWriter, IoReader, IoWriter, SliceReader},
+    types::VarNum,
+};
+
+#[test]
+fn slice_reader_tracks_position() {
+    let bytes = [0x00, 0x01, 0xAC, 0x02, 0xFF];
+    let mut reader = SliceReader::new(&bytes);
+    assert_eq!(<u16 as McProtoSelf>::read(&mut reader, ()).unwrap(), 1);
+    assert_eq!((reader.position(), reader.remaining()), (2, 3));
+    let value: u32 = VarNum::read(&mut reader, ()).unwrap();
+    assert_eq!(value, 300);
+    assert_eq!(reader.rest(), [0xFF]);
+
+    let error = reader.read(&mut [0; 2]).unwrap_err();
+    assert!(matches!(
+        error,
+        ProtocolError::UnexpectedEof { offset: Some(4) }
+    ));
+    assert_eq!(reader.read_byte().unwrap(), 0xFF);
+    assert!(reader.is_empty());
+}
+
+#[test]
+fn counting_writer_measures_without_storing() {
+    let mut counter = CountingWriter::default();
+    "hello".to_owned().write(&mut counter).unwrap();
+    VarNum::write(300u32, &mut counter).unwrap();
+    assert_eq!(counter.0, 1 + 5 + 2);
+}
+
+#[test]
+fn std_io_adapters() {
+    let mut writer = IoWriter(Vec::new());
+    0x0102_0304_i32.write(&mut writer).unwrap();
+    // Mutable references work anywhere a reader or writer does.
+    true.write(&mut &mut writer).unwrap();
+    assert_eq!(writer.0, [1, 2, 3, 4, 1]);
+
+    let mut reader = IoReader(Cursor::new(writer.0));
+    assert_eq!(
+        <i32 as McProtoSelf>::read(&mut &mut reader, ()).unwrap(),
+        0x0102_0304
+    );
+    assert!(<bool as McProtoSelf>::read(&mut reader, ()).unwrap());
+    // A stream that ends is reported as such, without an offset.
+    let error = reader.read_byte().unwrap_err();
+    assert!(matches!(
+        error,
+        ProtocolError::UnexpectedEof { offset: None }
+    ));
+    assert!(error.is_closed() && !error.is_malformed());
+
+    let mut vec = Vec::new();
+    McWriter::write(&mut vec, &[1, 2]).unwrap();
+    assert_eq!(vec, [1, 2]);
+}
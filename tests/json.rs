@@ -0,0 +1,59 @@
+//! Tests for converting between JSON and NBT.
+
+use minimc::json;
+use serde_json::json;
+use simdnbt::owned::{NbtCompound, NbtList, NbtTag};
+
+#[test]
+fn converts_to_nbt() {
+    let tag = json::to_nbt(&json!({
+        "text": "hi",
+        "bold": true,
+        "count": 3,
+        "big": 1_i64 << 40,
+        "scale": 0.5,
+        "missing": null,
+        "with": ["a", {"text": "b"}],
+        "ints": [1, 2, null],
+    }));
+    let compound = tag.compound().unwrap();
+    assert_eq!(compound.string("text").unwrap().to_str(), "hi");
+    assert_eq!(compound.byte("bold"), Some(1));
+    assert_eq!(compound.int("count"), Some(3));
+    assert_eq!(compound.long("big"), Some(1 << 40));
+    assert_eq!(compound.double("scale"), Some(0.5));
+    assert!(!compound.contains("missing"));
+    assert_eq!(compound.list("ints"), Some(&NbtList::Int(vec![1, 2])));
+    // Strings mixed with compounds are wrapped in a compound with an empty key.
+    let with = compound.list("with").unwrap().compounds().unwrap();
+    assert_eq!(with[0].string("").unwrap().to_str(), "a");
+    assert_eq!(with[1].string("text").unwrap().to_str(), "b");
+
+    assert_eq!(json::to_nbt(&json!([])), NbtTag::List(NbtList::Empty));
+    assert_eq!(
+        json::to_nbt(&json!(null)),
+        NbtTag::Compound(NbtCompound::new())
+    );
+}
+
+#[test]
+fn converts_from_nbt() {
+    let json = json!({"text": "a", "extra": ["b", {"text": "c", "bold": 1}], "n": 2.5});
+    // Booleans come back as integers, since NBT has no booleans.
+    let with_bool = json!({"text": "a", "extra": ["b", {"text": "c", "bold": true}], "n": 2.5});
+    assert_eq!(json::from_nbt(&json::to_nbt(&with_bool)), json);
+    assert_eq!(
+        json::from_nbt(&NbtTag::IntArray(vec![1, -2])),
+        json!([1, -2])
+    );
+    assert_eq!(json::from_nbt(&NbtTag::String("s".into())), json!("s"));
+}
+
+#[test]
+fn booleans_may_be_bytes() {
+    assert_eq!(json::as_bool(&json!(true)), Some(true));
+    assert_eq!(json::as_bool(&json!(0)), Some(false));
+    assert_eq!(json::as_bool(&json!(1)), Some(true));
+    assert_eq!(json::as_bool(&json!(2)), None);
+    assert_eq!(json::as_bool(&json!("true")), None);
+}
@@ -0,0 +1,53 @@
+//! Tests for keep-alive scheduling.
+
+use std::time::{Duration, Instant};
+
+use minimc::keep_alive::{Action, KeepAlive};
+
+#[test]
+fn schedule_and_latency() {
+    let start = Instant::now();
+    let secs = |n| start + Duration::from_secs(n);
+    let mut keep_alive = KeepAlive::new(start);
+    assert_eq!(keep_alive.deadline(), secs(15));
+    assert_eq!(keep_alive.poll(secs(14)), Action::Wait);
+
+    let Action::Send(id) = keep_alive.poll(secs(15)) else {
+        panic!("expected a keep-alive");
+    };
+    // Only one is pending at a time, and it must be answered within the timeout.
+    assert_eq!(keep_alive.poll(secs(30)), Action::Wait);
+    assert_eq!(keep_alive.deadline(), secs(45));
+    assert!(!keep_alive.answer(id + 1, secs(16)));
+    assert!(keep_alive.answer(id, secs(17)));
+    assert_eq!(keep_alive.latency(), Some(Duration::from_secs(2)));
+    assert!(!keep_alive.answer(id, secs(17)));
+
+    // The next one is due an interval after the answer, and later answers
+    // are smoothed in.
+    assert_eq!(keep_alive.deadline(), secs(32));
+    let Action::Send(next) = keep_alive.poll(secs(32)) else {
+        panic!("expected a keep-alive");
+    };
+    assert_ne!(next, id);
+    assert!(keep_alive.answer(next, secs(38)));
+    assert_eq!(keep_alive.latency(), Some(Duration::from_secs(3)));
+}
+
+#[test]
+fn times_out() {
+    let start = Instant::now();
+    let mut keep_alive =
+        KeepAlive::with_timing(start, Duration::from_millis(10), Duration::from_millis(20));
+    let Action::Send(_) = keep_alive.poll(start + Duration::from_millis(10)) else {
+        panic!("expected a keep-alive");
+    };
+    assert_eq!(
+        keep_alive.poll(start + Duration::from_millis(29)),
+        Action::Wait
+    );
+    assert_eq!(
+        keep_alive.poll(start + Duration::from_millis(30)),
+        Action::TimedOut
+    );
+}
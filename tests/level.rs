@@ -0,0 +1,124 @@
+//! Tests for loading and saving `level.dat`.
+
+use std::fs;
+
+use minimc::{game::Game, level::LevelData, packets::play::Difficulty, types::Position};
+use simdnbt::owned::{NbtCompound, NbtList, NbtTag};
+use tempfile::TempDir;
+
+/// The root compound of a `level.dat` shaped like one vanilla saves.
+fn vanilla() -> NbtCompound {
+    let mut settings = NbtCompound::new();
+    settings.insert("bonus_chest", NbtTag::Byte(0));
+    settings.insert("seed", NbtTag::Long(-4_172_144_997_902_289_642));
+    settings.insert("generate_features", NbtTag::Byte(1));
+    let mut rules = NbtCompound::new();
+    rules.insert("doDaylightCycle", NbtTag::String("true".into()));
+    rules.insert("reducedDebugInfo", NbtTag::String("true".into()));
+    rules.insert("randomTickSpeed", NbtTag::String("3".into()));
+    let mut packs = NbtCompound::new();
+    packs.insert(
+        "Enabled",
+        NbtTag::List(NbtList::String(vec!["vanilla".into(), "file/extra".into()])),
+    );
+    packs.insert("Disabled", NbtTag::List(NbtList::Empty));
+
+    let mut data = NbtCompound::new();
+    data.insert("DataVersion", NbtTag::Int(3955));
+    data.insert("LevelName", NbtTag::String("New World".into()));
+    data.insert("SpawnX", NbtTag::Int(-32));
+    data.insert("SpawnY", NbtTag::Int(71));
+    data.insert("SpawnZ", NbtTag::Int(48));
+    data.insert("SpawnAngle", NbtTag::Float(0.0));
+    data.insert("Time", NbtTag::Long(123_456));
+    data.insert("DayTime", NbtTag::Long(30_000));
+    data.insert("GameRules", NbtTag::Compound(rules));
+    data.insert("Difficulty", NbtTag::Byte(2));
+    data.insert("DifficultyLocked", NbtTag::Byte(0));
+    data.insert("WorldGenSettings", NbtTag::Compound(settings));
+    data.insert("DataPacks", NbtTag::Compound(packs));
+    data.insert("version", NbtTag::Int(19133));
+    let mut root = NbtCompound::new();
+    root.insert("Data", NbtTag::Compound(data));
+    root
+}
+
+#[test]
+fn reads_vanilla_level() {
+    let level = LevelData::from_nbt(vanilla()).unwrap();
+    assert_eq!(level.data_version, 3955);
+    assert_eq!(level.spawn, Position::new(-32, 71, 48));
+    assert_eq!((level.time, level.day_time), (123_456, 30_000));
+    assert_eq!(level.difficulty, Difficulty::Normal);
+    assert!(!level.difficulty_locked);
+    assert_eq!(level.seed, -4_172_144_997_902_289_642);
+    assert_eq!(level.enabled_data_packs, ["vanilla", "file/extra"]);
+    assert!(level.disabled_data_packs.is_empty());
+    assert_eq!(level.game_rules["randomTickSpeed"], "3");
+    assert!(level.game_rule("reducedDebugInfo"));
+    assert!(!level.game_rule("doImmediateRespawn"));
+
+    // Everything else is kept, and the seed stays with its settings.
+    let data = level.to_nbt();
+    let data = data.compound("Data").unwrap();
+    assert_eq!(data.string("LevelName").unwrap().to_str(), "New World");
+    let settings = data.compound("WorldGenSettings").unwrap();
+    assert_eq!(settings.long("seed"), Some(level.seed));
+    assert_eq!(settings.byte("generate_features"), Some(1));
+    assert_eq!(LevelData::from_nbt(level.to_nbt()).unwrap(), level);
+}
+
+#[test]
+fn hashed_seed() {
+    let level = LevelData::from_nbt(vanilla()).unwrap();
+    assert_eq!(level.hashed_seed(), 2_159_143_436_479_834_350);
+    assert_eq!(
+        LevelData::new(Position::new(0, 64, 0), 0).hashed_seed(),
+        8_794_265_229_978_523_055
+    );
+}
+
+#[test]
+fn rejects_malformed_levels() {
+    assert!(LevelData::from_nbt(NbtCompound::new()).is_err());
+    let mut root = vanilla();
+    root.compound_mut("Data").unwrap().remove("SpawnY");
+    assert!(LevelData::from_nbt(root).is_err());
+    let mut root = vanilla();
+    root.compound_mut("Data").unwrap().remove("Difficulty");
+    root.compound_mut("Data")
+        .unwrap()
+        .insert("Difficulty", NbtTag::Byte(7));
+    assert!(LevelData::from_nbt(root).is_err());
+    assert!(LevelData::from_bytes(b"not gzip").is_err());
+}
+
+#[test]
+fn saves_atomically() {
+    // The world's directory is created when it's first saved.
+    let temp = TempDir::with_prefix("minimc-level-").unwrap();
+    let dir = temp.path().join("world");
+    let path = dir.join("level.dat");
+    assert_eq!(LevelData::load(&path).unwrap(), None);
+
+    let mut level = LevelData::new(Position::new(0, 64, 0), 42);
+    level.save(&path).unwrap();
+    // Gzip's magic number.
+    assert_eq!(fs::read(&path).unwrap()[..2], [0x1F, 0x8B]);
+    assert_eq!(LevelData::load(&path).unwrap().as_ref(), Some(&level));
+
+    level.day_time = 6000;
+    level.save(&path).unwrap();
+    assert_eq!(LevelData::load(&path).unwrap(), Some(level));
+    assert!(!dir.join("level.dat_new").exists());
+}
+
+#[test]
+fn game_keeps_level_time() {
+    let mut level = LevelData::from_nbt(vanilla()).unwrap();
+    let game = Game::from_level(&level);
+    assert_eq!((game.age(), game.day_time()), (123_456, 30_000));
+    level.time = 0;
+    game.write_level(&mut level);
+    assert_eq!((level.time, level.day_time), (123_456, 30_000));
+}
@@ -0,0 +1,690 @@
+//! End-to-end tests against a server listening on loopback.
+
+use std::{
+    io::{Read, Write},
+    net::{Ipv4Addr, TcpStream},
+    sync::{Arc, Mutex},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use minimc::{
+    McProtoSelf, McWriter,
+    anvil::Region,
+    auth::{self, GameProfile, SessionService},
+    chunk::Palette,
+    config::Config,
+    crypto::{random_bytes, rsa::RsaPublicKey},
+    io::{CipherReader, CipherWriter, IoReader, IoWriter},
+    level::LevelData,
+    packet::{Compression, Packet},
+    packets::{
+        Clientbound, Registry, State,
+        configuration::{
+            AcknowledgeFinishConfiguration, ChatMode, ClientInformation, MainHand,
+            ServerboundKnownPacks,
+        },
+        handshake::{Handshake, Intent},
+        login::{EncryptionResponse, LoginAcknowledged, LoginStart, Property},
+        play::{ChunkData, ConfirmTeleportation, Difficulty, GameEvent},
+        status::{PingRequest, StatusRequest},
+    },
+    registries::Registries,
+    server::{Server, Shutdown},
+    types::{Identifier, Position, Uuid},
+};
+use tempfile::TempDir;
+
+/// The configuration every test server starts from, with a world directory
+/// of its own. The directory is removed when the guard returned with it is
+/// dropped, so the guard has to outlive the server.
+fn config() -> (Config, TempDir) {
+    let world = TempDir::with_prefix("minimc-loopback-").unwrap();
+    let config = Config {
+        ip: Ipv4Addr::LOCALHOST.into(),
+        port: 0,
+        motd: "loopback test".to_owned(),
+        compression_threshold: None,
+        level_name: world.path().to_owned(),
+        ..Config::default()
+    };
+    (config, world)
+}
+
+/// Start a server on an ephemeral loopback port, with the guard of its world
+/// directory.
+fn start() -> (TcpStream, Shutdown, JoinHandle<()>, TempDir) {
+    let (config, world) = config();
+    let (stream, shutdown, handle) = connect(Server::bind(config).unwrap());
+    (stream, shutdown, handle, world)
+}
+
+/// Run `server` and connect to it.
+fn connect(server: Server) -> (TcpStream, Shutdown, JoinHandle<()>) {
+    let addr = server.local_addr().unwrap();
+    let shutdown = server.shutdown_handle();
+    let handle = thread::spawn(move || server.run().unwrap());
+    let stream = TcpStream::connect(addr).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    (stream, shutdown, handle)
+}
+
+/// A framed handshake packet for protocol 767 to `localhost:25565`.
+fn handshake(intent: Intent) -> Vec<u8> {
+    handshake_version(767, intent)
+}
+
+/// A framed handshake packet for `protocol_version` to `localhost:25565`.
+fn handshake_version(protocol_version: u32, intent: Intent) -> Vec<u8> {
+    let handshake = Handshake {
+        protocol_version,
+        address: "localhost".to_owned(),
+        port: 25565,
+        intent,
+    };
+    let mut bytes = Vec::new();
+    Packet::encode::<_, Handshake>(0x00, handshake)
+        .unwrap()
+        .write(&mut bytes)
+        .unwrap();
+    bytes
+}
+
+/// Frame and send a packet.
+fn send<T: McProtoSelf>(stream: &mut TcpStream, id: u32, value: T) {
+    let mut bytes = Vec::new();
+    Packet::encode::<_, T>(id, value)
+        .unwrap()
+        .write(&mut bytes)
+        .unwrap();
+    stream.write_all(&bytes).unwrap();
+}
+
+/// Receive a packet in `state`.
+fn receive(stream: &mut TcpStream, state: State) -> Clientbound {
+    let packet = Packet::read(&mut IoReader(stream)).unwrap();
+    Registry::latest().decode(state, packet).unwrap()
+}
+
+/// Whether the server closed the connection.
+fn closed(stream: &mut TcpStream) -> bool {
+    matches!(stream.read(&mut [0u8; 16]), Ok(0) | Err(_))
+}
+
+#[test]
+fn status_and_ping() {
+    let (mut stream, shutdown, handle, _world) = start();
+    stream.write_all(&handshake(Intent::Status)).unwrap();
+
+    send(&mut stream, 0x00, StatusRequest);
+    let Clientbound::StatusResponse(response) = receive(&mut stream, State::Status) else {
+        panic!("expected a status response");
+    };
+    assert!(response.json.contains(r#""protocol":767"#));
+    assert!(response.json.contains(r#""description":"loopback test""#));
+
+    send(&mut stream, 0x01, PingRequest { payload: 42 });
+    let Clientbound::PongResponse(pong) = receive(&mut stream, State::Status) else {
+        panic!("expected a pong");
+    };
+    assert_eq!(pong.payload, 42);
+    assert!(closed(&mut stream));
+    shutdown.trigger();
+    handle.join().unwrap();
+}
+
+#[test]
+fn bad_next_state_closes_connection() {
+    let (mut stream, shutdown, handle, _world) = start();
+    let mut bytes = handshake(Intent::Status);
+    *bytes.last_mut().unwrap() = 7;
+    stream.write_all(&bytes).unwrap();
+    assert!(closed(&mut stream));
+    shutdown.trigger();
+    handle.join().unwrap();
+}
+
+#[test]
+fn shutdown_disconnects_idle_clients() {
+    let (mut stream, shutdown, handle, _world) = start();
+    thread::sleep(Duration::from_millis(100));
+    shutdown.trigger();
+    handle.join().unwrap();
+    assert!(closed(&mut stream));
+}
+
+/// Decode a legacy kick packet into its string.
+fn legacy_kick(stream: &mut TcpStream) -> String {
+    let mut bytes = Vec::new();
+    stream.read_to_end(&mut bytes).unwrap();
+    assert_eq!(bytes[0], 0xFF);
+    let len = usize::from(u16::from_be_bytes([bytes[1], bytes[2]]));
+    let units: Vec<u16> = bytes[3..]
+        .chunks(2)
+        .map(|unit| u16::from_be_bytes([unit[0], unit[1]]))
+        .collect();
+    assert_eq!(units.len(), len);
+    String::from_utf16(&units).unwrap()
+}
+
+#[test]
+fn legacy_ping() {
+    let (mut stream, shutdown, handle, _world) = start();
+    stream.write_all(&[0xFE, 0x01, 0xFA]).unwrap();
+    let host: Vec<u8> = "MC|PingHost"
+        .encode_utf16()
+        .flat_map(u16::to_be_bytes)
+        .collect();
+    stream.write_all(&11u16.to_be_bytes()).unwrap();
+    stream.write_all(&host).unwrap();
+    stream
+        .write_all(&[0x00, 0x07, 0x4A, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00])
+        .unwrap();
+    assert_eq!(
+        legacy_kick(&mut stream).split('\0').collect::<Vec<_>>(),
+        ["\u{a7}1", "127", "1.21.1", "loopback test", "0", "20"]
+    );
+    shutdown.trigger();
+    handle.join().unwrap();
+}
+
+#[test]
+fn beta_legacy_ping() {
+    let (mut stream, shutdown, handle, _world) = start();
+    stream.write_all(&[0xFE]).unwrap();
+    assert_eq!(legacy_kick(&mut stream), "loopback test\u{a7}0\u{a7}20");
+    shutdown.trigger();
+    handle.join().unwrap();
+}
+
+#[test]
+fn legacy_ping_with_json_motd() {
+    let (config, _world) = config();
+    let config = Config {
+        motd: r#"{"text":"Gold","color":"gold","extra":[{"text":" and bold","bold":true}]}"#
+            .to_owned(),
+        ..config
+    };
+    let server = Server::bind(config).unwrap();
+    let addr = server.local_addr().unwrap();
+    let (mut stream, shutdown, handle) = connect(server);
+    stream.write_all(&[0xFE, 0x01]).unwrap();
+    assert_eq!(
+        legacy_kick(&mut stream).split('\0').nth(3),
+        Some("\u{a7}6Gold\u{a7}r\u{a7}6\u{a7}l and bold")
+    );
+
+    // Beta clients split the response on the section sign, so they get none.
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream.write_all(&[0xFE]).unwrap();
+    assert_eq!(legacy_kick(&mut stream), "Gold and bold\u{a7}0\u{a7}20");
+    shutdown.trigger();
+    handle.join().unwrap();
+}
+
+#[test]
+fn offline_login() {
+    let (mut stream, shutdown, handle, _world) = start();
+    stream.write_all(&handshake(Intent::Login)).unwrap();
+    send(
+        &mut stream,
+        0x00,
+        LoginStart {
+            name: "Notch".to_owned(),
+            uuid: Uuid::default(),
+        },
+    );
+    let Clientbound::LoginSuccess(success) = receive(&mut stream, State::Login) else {
+        panic!("expected login success");
+    };
+    assert_eq!(success.username, "Notch");
+    assert_eq!(
+        success.uuid.to_string(),
+        "b50ad385-829d-3141-a216-7e7d7539ba7f"
+    );
+    send(&mut stream, 0x03, LoginAcknowledged);
+    shutdown.trigger();
+    handle.join().unwrap();
+}
+
+/// Log in offline as Notch and acknowledge it, entering the configuration
+/// state.
+fn log_in(stream: &mut TcpStream) {
+    stream.write_all(&handshake(Intent::Login)).unwrap();
+    send(
+        stream,
+        0x00,
+        LoginStart {
+            name: "Notch".to_owned(),
+            uuid: Uuid::default(),
+        },
+    );
+    let Clientbound::LoginSuccess(_) = receive(stream, State::Login) else {
+        panic!("expected login success");
+    };
+    send(stream, 0x03, LoginAcknowledged);
+}
+
+#[test]
+fn configuration() {
+    // Clients with the core pack only need the IDs of the entries.
+    for (known, with_data) in [(vec![Registries::core_pack()], false), (Vec::new(), true)] {
+        let (mut stream, shutdown, handle, _world) = start();
+        log_in(&mut stream);
+        let Clientbound::FeatureFlags(flags) = receive(&mut stream, State::Configuration) else {
+            panic!("expected feature flags");
+        };
+        assert_eq!(flags.flags, [Identifier::minecraft("vanilla").unwrap()]);
+        let Clientbound::ClientboundKnownPacks(packs) = receive(&mut stream, State::Configuration)
+        else {
+            panic!("expected known packs");
+        };
+        assert_eq!(packs.packs, [Registries::core_pack()]);
+
+        send(
+            &mut stream,
+            0x00,
+            ClientInformation {
+                locale: "en_us".to_owned(),
+                view_distance: 12,
+                chat_mode: ChatMode::Enabled,
+                chat_colors: true,
+                displayed_skin_parts: 0x7F,
+                main_hand: MainHand::Right,
+                enable_text_filtering: false,
+                allow_server_listings: true,
+            },
+        );
+        send(&mut stream, 0x07, ServerboundKnownPacks { packs: known });
+        let mut registries = Vec::new();
+        let tags = loop {
+            match receive(&mut stream, State::Configuration) {
+                Clientbound::RegistryData(data) => registries.push(data),
+                Clientbound::UpdateTags(tags) => break tags,
+                packet => panic!("unexpected {packet:?}"),
+            }
+        };
+        assert_eq!(registries, Registries::vanilla().registry_data(with_data));
+        assert!(
+            registries
+                .iter()
+                .flat_map(|registry| &registry.entries)
+                .all(|entry| entry.data.is_some() == with_data)
+        );
+        assert_eq!(tags, Registries::vanilla().update_tags());
+        let Clientbound::FinishConfiguration(_) = receive(&mut stream, State::Configuration) else {
+            panic!("expected finish configuration");
+        };
+        send(&mut stream, 0x03, AcknowledgeFinishConfiguration);
+        let Clientbound::LoginPlay(login) = receive(&mut stream, State::Play) else {
+            panic!("expected login (play)");
+        };
+        assert_eq!(
+            login.dimension_name,
+            Identifier::minecraft("overworld").unwrap()
+        );
+        shutdown.trigger();
+        handle.join().unwrap();
+    }
+}
+
+#[test]
+fn spawn() {
+    let (config, _world) = config();
+    let config = Config {
+        view_distance: 2,
+        ..config
+    };
+    let mut level = LevelData::new(Position::new(5, 80, 9), 1234);
+    level.spawn_angle = 90.0;
+    level.time = 1000;
+    level.difficulty = Difficulty::Hard;
+    level.difficulty_locked = true;
+    level
+        .game_rules
+        .insert("doImmediateRespawn".to_owned(), "true".to_owned());
+    level.save(config.level_path()).unwrap();
+    // Chunks 0..2, 0..2 are saved, from vanilla 1.19.4.
+    let region = config.region_dir().join("r.0.0.mca");
+    std::fs::create_dir_all(config.region_dir()).unwrap();
+    std::fs::copy("tests/data/r.0.0.mca", &region).unwrap();
+    let (mut stream, shutdown, handle) = connect(Server::bind(config.clone()).unwrap());
+    log_in(&mut stream);
+    loop {
+        match receive(&mut stream, State::Configuration) {
+            Clientbound::ClientboundKnownPacks(_) => send(
+                &mut stream,
+                0x07,
+                ServerboundKnownPacks {
+                    packs: vec![Registries::core_pack()],
+                },
+            ),
+            Clientbound::FinishConfiguration(_) => break,
+            _ => {}
+        }
+    }
+    send(&mut stream, 0x03, AcknowledgeFinishConfiguration);
+
+    let Clientbound::LoginPlay(login) = receive(&mut stream, State::Play) else {
+        panic!("expected login (play)");
+    };
+    assert_eq!(login.view_distance, 2);
+    assert!(!login.enable_respawn_screen);
+    assert_eq!(login.hashed_seed, level.hashed_seed());
+    let Clientbound::ChangeDifficulty(difficulty) = receive(&mut stream, State::Play) else {
+        panic!("expected the difficulty");
+    };
+    assert_eq!(difficulty.difficulty, Difficulty::Hard);
+    assert!(difficulty.locked);
+    let Clientbound::SetDefaultSpawnPosition(spawn) = receive(&mut stream, State::Play) else {
+        panic!("expected the spawn position");
+    };
+    assert_eq!(spawn.location, level.spawn);
+    assert_eq!(spawn.angle, 90.0);
+    let Clientbound::SynchronizePlayerPosition(position) = receive(&mut stream, State::Play) else {
+        panic!("expected the player's position");
+    };
+    // Standing on top of the spawn block.
+    assert_eq!(position.y, f64::from(spawn.location.y + 1));
+    let Clientbound::GameEvent(event) = receive(&mut stream, State::Play) else {
+        panic!("expected a game event");
+    };
+    assert_eq!(event.event, GameEvent::START_WAITING_FOR_CHUNKS);
+    let Clientbound::SetCenterChunk(center) = receive(&mut stream, State::Play) else {
+        panic!("expected the center chunk");
+    };
+    assert_eq!((center.x, center.z), (0, 0));
+    let Clientbound::ChunkBatchStart(_) = receive(&mut stream, State::Play) else {
+        panic!("expected a chunk batch");
+    };
+    let mut chunks = Vec::new();
+    let finished = loop {
+        match receive(&mut stream, State::Play) {
+            Clientbound::ChunkData(chunk) => chunks.push(chunk),
+            Clientbound::ChunkBatchFinished(finished) => break finished,
+            packet => panic!("unexpected {packet:?}"),
+        }
+    };
+    assert_eq!(chunks.len(), 25);
+    assert_eq!(finished.batch_size, 25);
+    assert_eq!((chunks[0].x, chunks[0].z), (0, 0));
+    // Saved chunks are sent as they were saved, and the rest are flat.
+    let flat = |chunk: &ChunkData| chunk.sections[1].block_states.palette() == &Palette::Single(0);
+    assert!(!flat(&chunks[0]));
+    let unsaved = chunks
+        .iter()
+        .find(|chunk| (chunk.x, chunk.z) == (-1, 0))
+        .unwrap();
+    assert!(flat(unsaved));
+
+    send(
+        &mut stream,
+        0x00,
+        ConfirmTeleportation {
+            teleport_id: position.teleport_id,
+        },
+    );
+    shutdown.trigger();
+    handle.join().unwrap();
+    assert!(closed(&mut stream));
+
+    // The world is saved on shutdown, with time having moved on.
+    let saved = LevelData::load(config.level_path()).unwrap().unwrap();
+    assert!(saved.time >= 1000);
+    assert_eq!(saved.day_time, saved.time - 1000);
+    assert_eq!(saved.difficulty, Difficulty::Hard);
+    // The chunks that were loaded haven't changed, so they aren't rewritten.
+    let region = Region::open(&region).unwrap();
+    assert_eq!(region.timestamp(0, 0), Some(1_681_322_801));
+    assert!(!config.level_name.join("level.dat_new").exists());
+}
+
+#[test]
+fn compressed_login() {
+    let (config, _world) = config();
+    let config = Config {
+        compression_threshold: Some(16),
+        ..config
+    };
+    let (mut stream, shutdown, handle) = connect(Server::bind(config).unwrap());
+    stream.write_all(&handshake(Intent::Login)).unwrap();
+    send(
+        &mut stream,
+        0x00,
+        LoginStart {
+            name: "Notch".to_owned(),
+            uuid: Uuid::default(),
+        },
+    );
+    let Clientbound::SetCompression(set) = receive(&mut stream, State::Login) else {
+        panic!("expected set compression");
+    };
+    assert_eq!(set.threshold, 16);
+    let compression = Some(Compression::new(16));
+    let packet = Packet::read_with(&mut IoReader(&mut stream), compression).unwrap();
+    let Clientbound::LoginSuccess(success) =
+        Registry::latest().decode(State::Login, packet).unwrap()
+    else {
+        panic!("expected login success");
+    };
+    assert_eq!(success.username, "Notch");
+
+    let mut bytes = Vec::new();
+    Packet::encode::<_, LoginAcknowledged>(0x03, LoginAcknowledged)
+        .unwrap()
+        .write_with(&mut bytes, compression)
+        .unwrap();
+    stream.write_all(&bytes).unwrap();
+    shutdown.trigger();
+    handle.join().unwrap();
+}
+
+#[test]
+fn login_rejections() {
+    for (protocol_version, name, reason) in [
+        (767, "no spaces", "Invalid username"),
+        (767, "ab", "Invalid username"),
+        (47, "Notch", "multiplayer.disconnect.incompatible"),
+    ] {
+        let (mut stream, shutdown, handle, _world) = start();
+        stream
+            .write_all(&handshake_version(protocol_version, Intent::Login))
+            .unwrap();
+        send(
+            &mut stream,
+            0x00,
+            LoginStart {
+                name: name.to_owned(),
+                uuid: Uuid::default(),
+            },
+        );
+        let Clientbound::LoginDisconnect(disconnect) = receive(&mut stream, State::Login) else {
+            panic!("expected a disconnect");
+        };
+        assert!(disconnect.reason.contains(reason), "{}", disconnect.reason);
+        assert!(closed(&mut stream));
+        shutdown.trigger();
+        handle.join().unwrap();
+    }
+}
+
+#[test]
+fn malformed_login_start_is_reported() {
+    let (mut stream, shutdown, handle, _world) = start();
+    stream.write_all(&handshake(Intent::Login)).unwrap();
+    // A name that isn't valid UTF-8.
+    let packet = Packet {
+        id: 0x00,
+        body: vec![2, 0xC3, 0x28],
+    };
+    let mut bytes = Vec::new();
+    packet.write(&mut bytes).unwrap();
+    stream.write_all(&bytes).unwrap();
+    let Clientbound::LoginDisconnect(disconnect) = receive(&mut stream, State::Login) else {
+        panic!("expected a disconnect");
+    };
+    assert!(
+        disconnect.reason.contains("disconnect.packetError"),
+        "{}",
+        disconnect.reason
+    );
+    assert!(closed(&mut stream));
+    shutdown.trigger();
+    handle.join().unwrap();
+}
+
+/// A session server that only knows Notch, and records every join it's asked
+/// about.
+#[derive(Debug, Default)]
+struct MockSession(Arc<Mutex<Vec<(String, String)>>>);
+
+impl SessionService for MockSession {
+    fn has_joined(
+        &self,
+        username: &str,
+        server_hash: &str,
+    ) -> Result<Option<GameProfile>, anyhow::Error> {
+        self.0
+            .lock()
+            .unwrap()
+            .push((username.to_owned(), server_hash.to_owned()));
+        Ok((username == "Notch").then(|| GameProfile {
+            id: Uuid(0x069a_79f4_44e9_4726_a5be_fca9_0e38_aaf5),
+            name: "Notch".to_owned(),
+            properties: vec![Property {
+                name: "textures".to_owned(),
+                value: "e30=".to_owned(),
+                signature: Some("signature".to_owned()),
+            }],
+        }))
+    }
+}
+
+/// A client partway through logging in to an online-mode server.
+struct OnlineLogin {
+    /// The encrypted writing half of the connection.
+    writer: CipherWriter<IoWriter<TcpStream>>,
+    /// The first packet received after encryption started.
+    packet: Clientbound,
+    /// The server hash the client would send to the session server.
+    hash: String,
+    /// Stops the server.
+    shutdown: Shutdown,
+    /// The server thread.
+    handle: JoinHandle<()>,
+    /// The guard of the server's world directory.
+    world: TempDir,
+}
+
+/// Log in as `name` to an online-mode server backed by `session`, up to the
+/// first encrypted packet.
+fn online_login(name: &str, session: MockSession) -> OnlineLogin {
+    let (config, world) = config();
+    let config = Config {
+        online_mode: true,
+        ..config
+    };
+    let server = Server::bind_with_session(config, Box::new(session)).unwrap();
+    let (mut stream, shutdown, handle) = connect(server);
+    stream.write_all(&handshake(Intent::Login)).unwrap();
+    send(
+        &mut stream,
+        0x00,
+        LoginStart {
+            name: name.to_owned(),
+            uuid: Uuid::default(),
+        },
+    );
+    let Clientbound::EncryptionRequest(request) = receive(&mut stream, State::Login) else {
+        panic!("expected an encryption request");
+    };
+    assert!(request.should_authenticate);
+    let key = RsaPublicKey::from_der(&request.public_key).unwrap();
+    assert_eq!(key.size(), 128);
+
+    let mut secret = [0u8; 16];
+    random_bytes(&mut secret).unwrap();
+    send(
+        &mut stream,
+        0x01,
+        EncryptionResponse {
+            shared_secret: key.encrypt(&secret).unwrap(),
+            verify_token: key.encrypt(&request.verify_token).unwrap(),
+        },
+    );
+
+    let mut reader = CipherReader::new(IoReader(stream.try_clone().unwrap()));
+    let mut writer = CipherWriter::new(IoWriter(stream));
+    reader.enable(&secret);
+    writer.enable(&secret);
+    let packet = Registry::latest()
+        .decode(State::Login, Packet::read(&mut reader).unwrap())
+        .unwrap();
+    let hash = auth::server_hash(&request.server_id, &secret, &request.public_key);
+    OnlineLogin {
+        writer,
+        packet,
+        hash,
+        shutdown,
+        handle,
+        world,
+    }
+}
+
+#[test]
+fn online_login_is_encrypted_and_authenticated() {
+    let session = MockSession::default();
+    let joins = Arc::clone(&session.0);
+    let OnlineLogin {
+        mut writer,
+        packet,
+        hash,
+        shutdown,
+        handle,
+        world: _world,
+    } = online_login("Notch", session);
+    let Clientbound::LoginSuccess(success) = packet else {
+        panic!("expected login success");
+    };
+    assert_eq!(
+        success.uuid.to_string(),
+        "069a79f4-44e9-4726-a5be-fca90e38aaf5"
+    );
+    assert_eq!(success.properties[0].name, "textures");
+    assert_eq!(
+        joins.lock().unwrap().as_slice(),
+        [("Notch".to_owned(), hash)]
+    );
+
+    let mut bytes = Vec::new();
+    Packet::encode::<_, LoginAcknowledged>(0x03, LoginAcknowledged)
+        .unwrap()
+        .write(&mut bytes)
+        .unwrap();
+    writer.write(&bytes).unwrap();
+    shutdown.trigger();
+    handle.join().unwrap();
+}
+
+#[test]
+fn online_login_rejects_unverified_players() {
+    let login = online_login("Herobrine", MockSession::default());
+    let Clientbound::LoginDisconnect(disconnect) = login.packet else {
+        panic!("expected a disconnect");
+    };
+    assert!(disconnect.reason.contains("unverified_username"));
+    login.shutdown.trigger();
+    login.handle.join().unwrap();
+}
+
+#[test]
+fn online_mode_needs_a_session_service() {
+    let (config, _world) = config();
+    let config = Config {
+        online_mode: true,
+        ..config
+    };
+    assert!(Server::bind(config).is_err());
+}
@@ -0,0 +1,93 @@
+//! Tests for packet framing.
+
+use minimc::{
+    ProtocolError,
+    io::SliceReader,
+    packet::{Compression, Packet},
+};
+
+#[test]
+fn round_trip() {
+    let packet = Packet::encode::<_, i64>(0x01, 0x0123_4567_89AB_CDEF).unwrap();
+    let mut bytes = Vec::new();
+    packet.write(&mut bytes).unwrap();
+    assert_eq!(
+        bytes,
+        [0x09, 0x01, 0x01, 0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD, 0xEF]
+    );
+
+    let read = Packet::read(&mut SliceReader::new(&bytes)).unwrap();
+    assert_eq!(read, packet);
+    assert_eq!(read.decode::<i64, i64>(()).unwrap(), 0x0123_4567_89AB_CDEF);
+}
+
+#[test]
+fn rejects_oversized_frames() {
+    // A length of 2 MiB + 1.
+    let bytes = [0x81, 0x80, 0x80, 0x01, 0x00];
+    assert!(matches!(
+        Packet::read(&mut SliceReader::new(&bytes)),
+        Err(ProtocolError::PacketTooLarge {
+            len: 2_097_153,
+            max: 2_097_152,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn rejects_trailing_bytes() {
+    let packet = Packet {
+        id: 0x00,
+        body: vec![0x00, 0x00, 0x00, 0x01, 0xFF],
+    };
+    assert!(packet.decode::<i32, i32>(()).is_err());
+    assert!(packet.decode::<u8, u8>(()).is_err());
+}
+
+#[test]
+fn compressed_round_trip() {
+    let compression = Compression::new(64);
+    for (body, compressed) in [(vec![7u8; 16], false), (vec![7u8; 4096], true)] {
+        let packet = Packet { id: 0x27, body };
+        let mut bytes = Vec::new();
+        packet.write_with(&mut bytes, Some(compression)).unwrap();
+        if compressed {
+            assert!(bytes.len() < 100, "{} bytes", bytes.len());
+        } else {
+            // The frame length, a zero uncompressed length, then the raw frame.
+            assert_eq!(bytes[..3], [18, 0x00, 0x27]);
+        }
+        let read = Packet::read_with(&mut SliceReader::new(&bytes), Some(compression)).unwrap();
+        assert_eq!(read, packet);
+    }
+}
+
+#[test]
+fn rejects_bad_compressed_frames() {
+    let packet = Packet {
+        id: 0x00,
+        body: vec![0u8; 4096],
+    };
+    let mut bytes = Vec::new();
+    packet
+        .write_with(&mut bytes, Some(Compression::new(0)))
+        .unwrap();
+
+    // Decompresses past the limit.
+    let strict = Compression {
+        threshold: 0,
+        max_uncompressed: 1024,
+    };
+    assert!(matches!(
+        Packet::read_with(&mut SliceReader::new(&bytes), Some(strict)),
+        Err(ProtocolError::PacketTooLarge {
+            len: 4097,
+            max: 1024,
+            ..
+        })
+    ));
+    // Compressed despite being below the threshold.
+    let lenient = Compression::new(8192);
+    assert!(Packet::read_with(&mut SliceReader::new(&bytes), Some(lenient)).is_err());
+}
@@ -0,0 +1,93 @@
+//! Tests for the registries sent while configuring clients.
+
+use minimc::{
+    packets::{Clientbound, Registry, State},
+    registries::Registries,
+    types::Identifier,
+};
+use serde_json::json;
+use simdnbt::owned::Nbt;
+
+/// Parse an identifier.
+fn id(s: &str) -> Identifier {
+    s.parse().unwrap()
+}
+
+#[test]
+fn vanilla_registries() {
+    let registries = Registries::vanilla();
+    let data = registries.registry_data(true);
+    for registry in [
+        "dimension_type",
+        "worldgen/biome",
+        "damage_type",
+        "chat_type",
+        "wolf_variant",
+        "painting_variant",
+    ] {
+        assert!(
+            data.iter()
+                .any(|data| data.registry == Identifier::minecraft(registry).unwrap()),
+            "{registry}"
+        );
+    }
+
+    let dimension_types = &data[0];
+    assert_eq!(dimension_types.entries[0].id, id("overworld"));
+    let Some(Nbt::Some(overworld)) = &dimension_types.entries[0].data else {
+        panic!("expected data");
+    };
+    assert_eq!(overworld.int("min_y"), Some(-64));
+    assert_eq!(overworld.byte("has_skylight"), Some(1));
+    assert_eq!(overworld.double("coordinate_scale"), Some(1.0));
+
+    let fall = registries.id(&id("damage_type"), &id("fall")).unwrap();
+    let tags = registries.update_tags();
+    let is_fall = tags.registries[0]
+        .tags
+        .iter()
+        .find(|tag| tag.name == id("is_fall"))
+        .unwrap();
+    assert!(is_fall.entries.contains(&fall));
+
+    // 1.21.1 has the mace's damage type, but campfires still use in_fire.
+    let damage_types = data
+        .iter()
+        .find(|data| data.registry == id("damage_type"))
+        .unwrap();
+    assert_eq!(damage_types.entries.len(), 47);
+    assert!(
+        registries
+            .id(&id("damage_type"), &id("mace_smash"))
+            .is_some()
+    );
+    assert!(registries.id(&id("damage_type"), &id("campfire")).is_none());
+}
+
+#[test]
+fn registry_data_round_trips() {
+    let registry = Registry::latest();
+    let packets = Registries::vanilla()
+        .registry_data(true)
+        .into_iter()
+        .map(Clientbound::RegistryData)
+        .chain([Clientbound::UpdateTags(Registries::vanilla().update_tags())]);
+    for packet in packets {
+        let encoded = registry
+            .encode(State::Configuration, packet.clone())
+            .unwrap();
+        let decoded: Clientbound = registry.decode(State::Configuration, encoded).unwrap();
+        assert_eq!(decoded, packet);
+    }
+}
+
+#[test]
+fn tags_must_name_entries() {
+    let json = json!({
+        "feature_flags": [],
+        "registries": {"minecraft:chat_type": {"minecraft:chat": {}}},
+        "tags": {"minecraft:chat_type": {"minecraft:all": ["minecraft:missing"]}}
+    });
+    let error = Registries::from_json(&json).unwrap_err();
+    assert!(error.to_string().contains("minecraft:missing"), "{error}");
+}
@@ -0,0 +1,84 @@
+//! Tests for the packet registry.
+
+use minimc::{
+    ProtocolError,
+    packet::Packet,
+    packets::{
+        Clientbound, Direction, PROTOCOL_VERSION, Registry, Serverbound, State,
+        handshake::{Handshake, Intent},
+        play::{ConfirmTeleportation, SetPlayerOnGround},
+    },
+};
+
+#[test]
+fn round_trip() {
+    let registry = Registry::for_version(PROTOCOL_VERSION).unwrap();
+    let handshake = Serverbound::Handshake(Handshake {
+        protocol_version: PROTOCOL_VERSION,
+        address: "example.com".to_owned(),
+        port: 25565,
+        intent: Intent::Login,
+    });
+    let packet = registry
+        .encode(State::Handshake, handshake.clone())
+        .unwrap();
+    assert_eq!(packet.id, 0x00);
+    let decoded: Serverbound = registry.decode(State::Handshake, packet).unwrap();
+    assert_eq!(decoded, handshake);
+}
+
+#[test]
+fn unknown_packets() {
+    let registry = Registry::latest();
+    let packet = Packet {
+        id: 0x7F,
+        body: vec![1, 2, 3],
+    };
+    let decoded: Clientbound = registry.decode(State::Play, packet.clone()).unwrap();
+    assert_eq!(decoded, Clientbound::Unknown(packet.clone()));
+    assert_eq!(registry.encode(State::Play, decoded).unwrap(), packet);
+}
+
+#[test]
+fn wrong_state() {
+    let handshake = Serverbound::Handshake(Handshake {
+        protocol_version: PROTOCOL_VERSION,
+        address: String::new(),
+        port: 0,
+        intent: Intent::Status,
+    });
+    let error = Registry::latest()
+        .encode(State::Play, handshake)
+        .unwrap_err();
+    assert!(matches!(
+        error,
+        ProtocolError::UnregisteredPacket {
+            direction: Direction::Serverbound,
+            state: State::Play,
+            name: "Handshake",
+        }
+    ));
+    assert!(!error.is_malformed());
+    assert_eq!(
+        error.to_string(),
+        "Serverbound packet Handshake isn't registered in the Play state"
+    );
+    assert!(Registry::for_version(4).is_none());
+}
+
+#[test]
+fn movement_packets() {
+    let registry = Registry::latest();
+    let packet = Packet {
+        id: 0x1D,
+        body: vec![1],
+    };
+    let decoded: Serverbound = registry.decode(State::Play, packet).unwrap();
+    assert_eq!(
+        decoded,
+        Serverbound::SetPlayerOnGround(SetPlayerOnGround { on_ground: true })
+    );
+    assert!(decoded.is_movement());
+    let confirm = Serverbound::ConfirmTeleportation(ConfirmTeleportation { teleport_id: 0 });
+    assert!(!confirm.is_movement());
+}
@@ -0,0 +1,181 @@
+//! Tests for text components.
+
+use minimc::{
+    ProtocolError,
+    packet::Packet,
+    status,
+    text::{ClickEvent, Color, Content, HoverEvent, NbtSource, Style, TextComponent},
+    types::{Identifier, Uuid},
+};
+use serde_json::{Value, json};
+
+/// Encode `component` as network NBT, check it decodes to itself, and return
+/// the encoding.
+fn round_trip(component: &TextComponent) -> Vec<u8> {
+    let packet = Packet::encode::<_, TextComponent>(0x00, component.clone()).unwrap();
+    assert_eq!(
+        &packet.decode::<TextComponent, TextComponent>(()).unwrap(),
+        component
+    );
+    assert_eq!(
+        &TextComponent::from_json(&component.to_json()).unwrap(),
+        component
+    );
+    packet.body
+}
+
+#[test]
+fn plain_text_is_a_bare_string() {
+    let component = TextComponent::text("hi");
+    assert_eq!(component.to_json().to_string(), r#""hi""#);
+    assert_eq!(round_trip(&component), [0x08, 0x00, 0x02, b'h', b'i']);
+}
+
+#[test]
+fn styled_json() {
+    let component = TextComponent::translatable("chat.type.text")
+        .with_arg("Notch")
+        .with_arg(TextComponent::text("hello").bold(true))
+        .color(Color::Gold)
+        .on_click(ClickEvent::SuggestCommand("/msg Notch ".to_owned()))
+        .append(TextComponent::text("!").color(Color::Rgb(0x12_34AB)));
+    assert_eq!(
+        component.to_json().to_string(),
+        concat!(
+            r#"{"translate":"chat.type.text","with":["Notch",{"text":"hello","bold":true}],"#,
+            r#""color":"gold","clickEvent":{"action":"suggest_command","value":"/msg Notch "},"#,
+            r##""extra":[{"text":"!","color":"#1234AB"}]}"##
+        )
+    );
+    round_trip(&component);
+}
+
+#[test]
+fn every_content_round_trips() {
+    let separator = Some(Box::new(TextComponent::text(" | ")));
+    for content in [
+        Content::Text("text".to_owned()),
+        Content::Translatable {
+            key: "key".to_owned(),
+            fallback: Some("fallback".to_owned()),
+            with: vec![],
+        },
+        Content::Score {
+            name: "@p".to_owned(),
+            objective: "kills".to_owned(),
+        },
+        Content::Selector {
+            pattern: "@a".to_owned(),
+            separator: separator.clone(),
+        },
+        Content::Keybind("key.jump".to_owned()),
+        Content::Nbt {
+            path: "Items[0]".to_owned(),
+            interpret: Some(true),
+            separator: None,
+            source: NbtSource::Block("~ ~-1 ~".to_owned()),
+        },
+        Content::Nbt {
+            path: "name".to_owned(),
+            interpret: None,
+            separator,
+            source: NbtSource::Storage(Identifier::minecraft("data").unwrap()),
+        },
+    ] {
+        round_trip(&TextComponent {
+            content,
+            style: Style {
+                italic: Some(false),
+                font: Some(Identifier::minecraft("uniform").unwrap()),
+                insertion: Some("inserted".to_owned()),
+                ..Style::default()
+            },
+            extra: vec![TextComponent::text("child")],
+        });
+    }
+}
+
+#[test]
+fn hover_events_round_trip() {
+    for event in [
+        HoverEvent::ShowText(Box::new(TextComponent::text("tip").italic(true))),
+        HoverEvent::ShowItem {
+            id: Identifier::minecraft("diamond").unwrap(),
+            count: 3,
+        },
+        HoverEvent::ShowEntity {
+            kind: Identifier::minecraft("pig").unwrap(),
+            id: Uuid(0x069a_79f4_44e9_4726_a5be_fca9_0e38_aaf5),
+            name: Some(Box::new(TextComponent::text("Pig"))),
+        },
+    ] {
+        round_trip(&TextComponent::text("hover").on_hover(event));
+    }
+}
+
+#[test]
+fn parses_lists_and_rejects_garbage() {
+    let json = json!(["a", {"text": "b"}]);
+    assert_eq!(
+        TextComponent::from_json(&json).unwrap(),
+        TextComponent::text("a").append("b")
+    );
+
+    for json in [
+        Value::Null,
+        json!([]),
+        json!({}),
+        json!({"text": "x", "color": "mauve"}),
+        json!({"text": "x", "bold": "yes"}),
+        json!({"nbt": "path"}),
+    ] {
+        assert!(
+            matches!(
+                TextComponent::from_json(&json),
+                Err(ProtocolError::Invalid { .. })
+            ),
+            "{json}"
+        );
+    }
+}
+
+#[test]
+fn motd_description() {
+    assert_eq!(
+        status::description("\u{a7}6Gold\nsecond line"),
+        TextComponent::text("\u{a7}6Gold\nsecond line")
+    );
+    assert_eq!(
+        status::description(r#"{"text":"hi","color":"gold"}"#),
+        TextComponent::text("hi").color(Color::Gold)
+    );
+    assert_eq!(
+        status::description(r#"["a",{"text":"b","bold":true}]"#),
+        TextComponent::text("a").append(TextComponent::text("b").bold(true))
+    );
+    // Anything that isn't a component is shown as it is.
+    for motd in ["{not json", "42", r#"{"color":"gold"}"#] {
+        assert_eq!(status::description(motd), TextComponent::text(motd));
+    }
+}
+
+#[test]
+fn legacy_text() {
+    assert_eq!(TextComponent::text("plain").to_legacy(), "plain");
+    let component = TextComponent::text("Gold ")
+        .color(Color::Gold)
+        .append(TextComponent::text("and bold").bold(true))
+        .append(TextComponent::text(" and red").color(Color::Red))
+        .append(TextComponent::text(", still red").color(Color::Red));
+    assert_eq!(
+        component.to_legacy(),
+        "\u{a7}6Gold \u{a7}r\u{a7}6\u{a7}land bold\u{a7}r\u{a7}c and red, still red"
+    );
+    // RGB colors have no code, and translations fall back to their key.
+    assert_eq!(
+        TextComponent::translatable("menu.quit")
+            .color(Color::Rgb(0x12_3456))
+            .to_legacy(),
+        "menu.quit"
+    );
+}
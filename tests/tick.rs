@@ -0,0 +1,120 @@
+//! Tests for the tick loop and the game it runs.
+
+use std::time::{Duration, Instant};
+
+use minimc::{
+    config::Config,
+    game::{Game, Inbound},
+    packets::{
+        Serverbound,
+        play::{ChunkBatchReceived, SetPlayerPosition},
+    },
+    server::{Player, Shared},
+    tick::{Schedule, TickLoop, TickMetrics},
+    types::Uuid,
+};
+
+#[test]
+fn schedule_catches_up() {
+    let start = Instant::now();
+    let millis = |n| start + Duration::from_millis(n);
+    let mut ticks = TickLoop::new(start);
+    assert_eq!(ticks.schedule(start), Schedule::Tick);
+    ticks.finish(start, millis(10));
+    assert_eq!(
+        ticks.schedule(millis(20)),
+        Schedule::Sleep(Duration::from_millis(30))
+    );
+
+    // A tick that overruns by a second is followed by back-to-back ticks
+    // until the loop is on schedule again.
+    assert_eq!(ticks.schedule(millis(50)), Schedule::Tick);
+    ticks.finish(millis(50), millis(1050));
+    let mut now = 1050;
+    let mut caught_up = 0;
+    while ticks.schedule(millis(now)) == Schedule::Tick {
+        ticks.finish(millis(now), millis(now + 1));
+        now += 1;
+        caught_up += 1;
+    }
+    assert_eq!(caught_up, 20);
+    assert_eq!(ticks.metrics().last_tick(), Some(Duration::from_millis(1)));
+}
+
+#[test]
+fn schedule_skips_too_far_behind() {
+    let start = Instant::now();
+    let mut ticks = TickLoop::new(start);
+    let stall = start + TickLoop::INTERVAL * (TickLoop::MAX_CATCH_UP + 1);
+    assert_eq!(ticks.schedule(stall), Schedule::Tick);
+    ticks.finish(stall, stall);
+    assert_eq!(ticks.schedule(stall), Schedule::Sleep(TickLoop::INTERVAL));
+}
+
+#[test]
+fn metrics() {
+    let start = Instant::now();
+    let mut metrics = TickMetrics::default();
+    assert_eq!(metrics.stats(start).tps, [0.0; 3]);
+
+    // Two minutes at full speed, then one at half speed.
+    let mut now = start;
+    while now < start + Duration::from_mins(2) {
+        metrics.record(now, Duration::from_millis(10));
+        now += TickLoop::INTERVAL;
+    }
+    while now < start + Duration::from_mins(3) {
+        metrics.record(now, Duration::from_millis(20));
+        now += TickLoop::INTERVAL * 2;
+    }
+    let stats = metrics.stats(now);
+    assert_eq!(stats.last_tick, Duration::from_millis(20));
+    assert!((stats.mean_mspt - 20.0).abs() < 1e-9);
+    assert!((stats.tps[0] - 10.0).abs() < 1e-9);
+    // The longer windows only cover the three minutes so far.
+    assert!((stats.tps[1] - 50.0 / 3.0).abs() < 1e-9);
+    assert_eq!(stats.tps[1], stats.tps[2]);
+}
+
+#[test]
+fn game_applies_queued_packets() {
+    let shared = Shared::new(Config::default(), None).unwrap();
+    let id = Uuid(1);
+    shared.players().push(Player {
+        name: "Steve".to_owned(),
+        id,
+        latency: Duration::ZERO,
+    });
+    shared.queue(Inbound {
+        player: id,
+        packet: Serverbound::ChunkBatchReceived(ChunkBatchReceived {
+            chunks_per_tick: 7.0,
+        }),
+    });
+
+    shared.queue(Inbound {
+        player: id,
+        packet: Serverbound::SetPlayerPosition(SetPlayerPosition {
+            x: 40.5,
+            y: 64.0,
+            z: -0.5,
+            on_ground: true,
+        }),
+    });
+
+    let mut game = Game::new();
+    assert_eq!(game.chunks_per_tick(id), None);
+    game.tick(&shared);
+    assert_eq!(game.chunks_per_tick(id), Some(7.0));
+    assert_eq!(game.chunk(id), Some((2, -1)));
+    assert_eq!((game.age(), game.day_time()), (1, 1));
+    assert!(shared.take_inbound().is_empty());
+
+    // Players that leave are forgotten within a second.
+    shared.players().clear();
+    for _ in 0..TickLoop::TPS {
+        game.tick(&shared);
+    }
+    assert_eq!(game.chunks_per_tick(id), None);
+    assert_eq!(game.chunk(id), None);
+}
@@ -0,0 +1,308 @@
+//! Round-trip tests for the protocol data types.
+
+use core::fmt::Debug;
+
+use minimc::{
+    McProto, McProtoSelf, ProtocolError,
+    io::IoWriter,
+    packet::Packet,
+    types::{Angle, BitSet, FixedBitSet, Identifier, Length, Position, Uuid, VarNum, ZigZag},
+};
+
+/// Encode `value`, check it decodes to itself, and return its encoding.
+fn round_trip<T: McProtoSelf + Clone + PartialEq + Debug>(value: &T, meta: T::Meta) -> Vec<u8> {
+    let packet = Packet::encode::<_, T>(0x00, value.clone()).unwrap();
+    assert_eq!(&packet.decode::<T, T>(meta).unwrap(), value);
+    packet.body
+}
+
+#[test]
+fn uuid() {
+    let uuid = Uuid(0x069a_79f4_44e9_4726_a5be_fca9_0e38_aaf5);
+    assert_eq!(
+        round_trip(&uuid, ()),
+        0x069a_79f4_44e9_4726_a5be_fca9_0e38_aaf5_u128.to_be_bytes()
+    );
+}
+
+#[test]
+fn offline_uuid() {
+    // What vanilla gives Notch on an offline-mode server.
+    assert_eq!(
+        Uuid::offline("Notch").to_string(),
+        "b50ad385-829d-3141-a216-7e7d7539ba7f"
+    );
+}
+
+#[test]
+fn position() {
+    // The example from the protocol documentation.
+    let position = Position::new(18_357_644, 831, -20_882_616);
+    assert_eq!(
+        round_trip(&position, ()),
+        0x4607_632C_15B4_833F_u64.to_be_bytes()
+    );
+    for position in [
+        Position::new(0, 0, 0),
+        Position::new(-1, -1, -1),
+        Position::new(-(1 << 25), -2048, (1 << 25) - 1),
+        Position::new((1 << 25) - 1, 2047, -(1 << 25)),
+    ] {
+        round_trip(&position, ());
+    }
+    assert!(Packet::encode::<_, Position>(0x00, Position::new(1 << 25, 0, 0)).is_err());
+    assert!(Packet::encode::<_, Position>(0x00, Position::new(0, 2048, 0)).is_err());
+}
+
+#[test]
+fn angle() {
+    assert_eq!(round_trip(&Angle(64), ()), [64]);
+    assert_eq!(Angle::from_degrees(90.0), Angle(64));
+    assert_eq!(Angle::from_degrees(-90.0), Angle(192));
+    assert!((Angle(128).to_degrees() - 180.0).abs() < f32::EPSILON);
+}
+
+#[test]
+fn bit_sets() {
+    let mut set = BitSet::default();
+    set.set(0, true);
+    set.set(65, true);
+    assert!(set.get(65) && !set.get(64) && !set.get(1000));
+    let bytes = round_trip(&set, ());
+    assert_eq!(bytes.len(), 1 + 2 * 8);
+    assert_eq!(bytes[..9], [2, 0, 0, 0, 0, 0, 0, 0, 1]);
+
+    let mut fixed = FixedBitSet::<20>::new();
+    fixed.set(0, true);
+    fixed.set(19, true);
+    assert_eq!(round_trip(&fixed, ()), [0x01, 0x00, 0x08]);
+}
+
+#[test]
+fn identifier() {
+    let id: Identifier = "stone".parse().unwrap();
+    assert_eq!(id.to_string(), "minecraft:stone");
+    let id: Identifier = "minimc:worldgen/noise_settings".parse().unwrap();
+    assert_eq!(id.namespace(), "minimc");
+    assert_eq!(id.path(), "worldgen/noise_settings");
+    assert_eq!(round_trip(&id, ())[0], 30);
+
+    for bad in ["Stone", "minecraft:a b", ":stone", "a/b:c"] {
+        assert!(bad.parse::<Identifier>().is_err(), "{bad}");
+    }
+    let packet = Packet::encode::<_, String>(0x00, "bad id".to_owned()).unwrap();
+    assert!(packet.decode::<Identifier, Identifier>(()).is_err());
+}
+
+#[test]
+fn options_and_arrays() {
+    assert_eq!(round_trip(&Some(5i32), ()), [1, 0, 0, 0, 5]);
+    assert_eq!(round_trip(&None::<i32>, ()), [0]);
+    assert_eq!(round_trip(&vec![1u16, 2, 3], ()), [3, 0, 1, 0, 2, 0, 3]);
+    assert_eq!(round_trip(&Vec::<u16>::new(), ()), [0]);
+
+    let names = vec![Some("a".to_owned()), None];
+    assert_eq!(round_trip(&names, Length::new(16)), [2, 1, 1, b'a', 0]);
+
+    // Lengths that run past the end of the packet fail instead of allocating.
+    let packet = Packet {
+        id: 0x00,
+        body: vec![0xFF, 0xFF, 0xFF, 0xFF, 0x07],
+    };
+    assert!(packet.decode::<Vec<u64>, Vec<u64>>(()).is_err());
+}
+
+#[test]
+fn strings() {
+    // The prefix counts bytes, not characters.
+    let bytes = round_trip(&"h\u{e9}\u{1F600}".to_owned(), Length::new(4));
+    assert_eq!(bytes, [7, b'h', 0xC3, 0xA9, 0xF0, 0x9F, 0x98, 0x80]);
+
+    let decode = |body: Vec<u8>, max_len| {
+        Packet { id: 0x00, body }.decode::<String, String>(Length::new(max_len))
+    };
+    // Too many characters, and more bytes than that many characters can take.
+    assert!(matches!(
+        decode(vec![3, b'a', b'b', b'c'], 2),
+        Err(ProtocolError::StringTooLong { len: 3, max: 2, .. })
+    ));
+    assert!(matches!(
+        decode(vec![7, 0, 0, 0, 0, 0, 0, 0], 2),
+        Err(ProtocolError::StringTooLong { len: 7, max: 6, .. })
+    ));
+    // A declared length far past the end of the packet.
+    assert!(matches!(
+        decode(vec![0xFF, 0xFF, 0x01], 32767),
+        Err(ProtocolError::UnexpectedEof { offset: Some(3) })
+    ));
+    // Invalid UTF-8.
+    assert!(matches!(
+        decode(vec![2, 0xC3, 0x28], 16),
+        Err(ProtocolError::InvalidUtf8 {
+            offset: Some(0),
+            ..
+        })
+    ));
+}
+
+/// A packet with a length-limited field.
+#[derive(Clone, PartialEq, Debug, McProto)]
+struct Named {
+    #[mc(max_len = 4)]
+    name: String,
+    #[mc(max_len = 4)]
+    nickname: Option<String>,
+}
+
+#[test]
+fn writing_checks_limits() {
+    let named = |name: &str, nickname: Option<&str>| Named {
+        name: name.to_owned(),
+        nickname: nickname.map(str::to_owned),
+    };
+    round_trip(&named("abcd", Some("\u{1F600}\u{1F600}")), ());
+    for value in [
+        named("abcde", None),
+        named("a", Some("\u{1F600}\u{1F600}!")),
+    ] {
+        assert!(Packet::encode::<_, Named>(0x00, value).is_err());
+    }
+}
+
+/// Encode `value` through the codec `P`, check it decodes to itself, and
+/// return its encoding.
+fn codec_round_trip<T: PartialEq + Debug + Copy, P: McProto<T, Meta = ()>>(value: T) -> Vec<u8> {
+    let packet = Packet::encode::<T, P>(0x00, value).unwrap();
+    assert_eq!(packet.decode::<T, P>(()).unwrap(), value);
+    packet.body
+}
+
+#[test]
+fn var_nums() {
+    for (value, bytes) in [
+        (0, &[0x00][..]),
+        (127, &[0x7F]),
+        (128, &[0x80, 0x01]),
+        (25565, &[0xDD, 0xC7, 0x01]),
+        (i32::MAX, &[0xFF, 0xFF, 0xFF, 0xFF, 0x07]),
+        (-1, &[0xFF, 0xFF, 0xFF, 0xFF, 0x0F]),
+        (i32::MIN, &[0x80, 0x80, 0x80, 0x80, 0x08]),
+    ] {
+        assert_eq!(codec_round_trip::<i32, VarNum>(value), bytes);
+        assert_eq!(VarNum::encoded_len(value), bytes.len());
+    }
+    assert_eq!(
+        codec_round_trip::<i64, VarNum>(-1),
+        [0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01]
+    );
+    assert_eq!(
+        codec_round_trip::<i64, VarNum>(i64::MIN),
+        [0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x01]
+    );
+    assert_eq!(VarNum::encoded_len(i64::from(i32::MAX)), 5);
+    assert_eq!(VarNum::encoded_len(u64::MAX), 10);
+
+    // One continuation byte too many.
+    let too_long = |len: usize| Packet {
+        id: 0x00,
+        body: [vec![0x80; len - 1], vec![0x00]].concat(),
+    };
+    assert!(matches!(
+        too_long(6).decode::<i32, VarNum>(()),
+        Err(ProtocolError::VarIntTooLong {
+            offset: Some(0),
+            max: 5
+        })
+    ));
+    assert!(matches!(
+        too_long(11).decode::<i64, VarNum>(()),
+        Err(ProtocolError::VarIntTooLong {
+            offset: Some(0),
+            max: 10
+        })
+    ));
+    assert_eq!(too_long(5).decode::<i32, VarNum>(()).unwrap(), 0);
+    assert_eq!(too_long(10).decode::<i64, VarNum>(()).unwrap(), 0);
+}
+
+#[test]
+fn zig_zag() {
+    for (value, bytes) in [
+        (0, &[0x00][..]),
+        (-1, &[0x01]),
+        (1, &[0x02]),
+        (-64, &[0x7F]),
+        (64, &[0x80, 0x01]),
+        (i32::MIN, &[0xFF, 0xFF, 0xFF, 0xFF, 0x0F]),
+    ] {
+        assert_eq!(codec_round_trip::<i32, ZigZag>(value), bytes);
+    }
+    for value in [0, -1, i64::MAX, i64::MIN] {
+        codec_round_trip::<i64, ZigZag>(value);
+    }
+}
+
+#[test]
+fn error_offsets() {
+    // The second boolean is bad.
+    let packet = Packet {
+        id: 0x00,
+        body: vec![0x02, 0x01, 0x02],
+    };
+    let error = packet.decode::<Vec<bool>, Vec<bool>>(()).unwrap_err();
+    assert!(matches!(
+        error,
+        ProtocolError::InvalidEnum {
+            offset: Some(2),
+            name: "bool",
+            value: 2,
+        }
+    ));
+    assert_eq!(error.to_string(), "invalid bool value 2 at byte 2");
+
+    let packet = Packet {
+        id: 0x00,
+        body: vec![0x00, 0x00],
+    };
+    let error = packet.decode::<i32, i32>(()).unwrap_err();
+    assert!(matches!(
+        error,
+        ProtocolError::UnexpectedEof { offset: Some(0) }
+    ));
+
+    let packet = Packet {
+        id: 0x2A,
+        body: vec![0x01, 0xFF],
+    };
+    let error = packet.decode::<bool, bool>(()).unwrap_err();
+    assert_eq!(error.offset(), Some(1));
+    assert!(error.is_malformed());
+}
+
+/// Encode `value` with the codec `P`.
+fn encode<T, P: McProto<T>>(value: T) -> Vec<u8> {
+    let mut writer = IoWriter(Vec::new());
+    P::write(value, &mut writer).unwrap();
+    writer.0
+}
+
+#[test]
+fn var_num_final_byte() {
+    // The last byte used to be written as a whole u32 or u64.
+    assert_eq!(encode::<u32, VarNum>(0), [0x00]);
+    assert_eq!(encode::<u32, VarNum>(127), [0x7F]);
+    assert_eq!(encode::<u32, VarNum>(25565), [0xDD, 0xC7, 0x01]);
+    assert_eq!(
+        encode::<u32, VarNum>(u32::MAX),
+        [0xFF, 0xFF, 0xFF, 0xFF, 0x0F]
+    );
+    assert_eq!(encode::<u64, VarNum>(1), [0x01]);
+    assert_eq!(encode::<u64, VarNum>(300), [0xAC, 0x02]);
+}
+
+#[test]
+fn bool_encoding() {
+    // Booleans used to be written inverted.
+    assert_eq!(encode::<bool, bool>(true), [0x01]);
+    assert_eq!(encode::<bool, bool>(false), [0x00]);
+}
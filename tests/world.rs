@@ -0,0 +1,42 @@
+//! Tests for the world players spawn into.
+
+use minimc::{
+    McProtoSelf,
+    chunk::{Heightmap, Heightmaps},
+    packets::{Clientbound, Registry, State},
+    registries::Registries,
+    world::World,
+};
+
+#[test]
+fn flat_chunk() {
+    let world = World::flat(Registries::vanilla()).unwrap();
+    assert_eq!(world.sections.len(), 24);
+    assert_eq!(world.spawn.y, world.min_y + 15);
+
+    let chunk = world.chunk(3, -4);
+    let biome = world.biome as u8;
+    // A full section of stone, then air.
+    assert_eq!(chunk.sections.len(), 24);
+    let mut data = Vec::new();
+    for section in chunk.sections[..2].iter().cloned() {
+        section.write(&mut data).unwrap();
+    }
+    assert_eq!(data[..8], [0x10, 0x00, 0, 1, 0, 0, biome, 0]);
+    assert_eq!(data[8..16], [0x00, 0x00, 0, 0, 0, 0, biome, 0]);
+    // Every column is one section tall.
+    let heightmaps = Heightmaps::from_nbt(&chunk.heightmaps, 384).unwrap();
+    assert_eq!(heightmaps.world_surface, Heightmap::flat(16));
+    // Sky light above the floor, and none in it or below.
+    let light = &chunk.light;
+    assert!(!light.sky_light_mask.get(1) && light.empty_sky_light_mask.get(1));
+    assert!(light.sky_light_mask.get(2) && light.sky_light_mask.get(25));
+    assert_eq!(light.sky_light.len(), 24);
+
+    let registry = Registry::latest();
+    let packet = Clientbound::ChunkData(chunk);
+    let encoded = registry.encode(State::Play, packet.clone()).unwrap();
+    assert_eq!(encoded.id, 0x27);
+    let decoded: Clientbound = registry.decode(State::Play, encoded).unwrap();
+    assert_eq!(decoded, packet);
+}